@@ -0,0 +1,323 @@
+//! compares `ezgame` against a naive `Vec<(Pos, Vel)>`, which is the baseline
+//! any ECS has to justify itself against
+//!
+//! run with `cargo bench`, or `cargo bench -- iter` for a subset. compare with
+//! `cargo bench --features fast-hash` for the cheaper hashing of `IdHasher`
+
+use std::hint::black_box;
+
+use criterion::{ criterion_group, criterion_main, BatchSize, Criterion };
+use ezgame::*;
+
+/// number of entities in every benchmark
+const N: usize = 1_000_000;
+
+#[derive(Component, Clone, Copy, Default)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component, Clone, Copy, Default)]
+struct Vel(f32, f32, f32);
+
+tags!(A, B, C, D, E, F, G, H, I);
+
+/// a scene with `N` entities, all in the same archetype
+fn scene() -> Scene
+{
+    let mut scene = Scene::default();
+
+    scene.spawn_batch((0..N).map(|_| (Pos::default(), Vel(1.0, 2.0, 3.0))));
+    scene
+}
+
+/// a scene with `N` entities, spread evenly across 16 archetypes that all have
+/// a `Pos` and a `Vel`
+fn fragmented_scene() -> Scene
+{
+    let mut scene = Scene::default();
+
+    for i in 0..N
+    {
+        let ent = scene.spawn((Pos::default(), Vel(1.0, 2.0, 3.0)));
+
+        if i & 1 != 0 { scene.add(ent, (A,)); }
+        if i & 2 != 0 { scene.add(ent, (B,)); }
+        if i & 4 != 0 { scene.add(ent, (C,)); }
+        if i & 8 != 0 { scene.add(ent, (D,)); }
+    }
+    scene
+}
+
+/// a scene with 512 archetypes, one for every combination of 9 tags. each has
+/// 16 entities with a `Pos`, and only those of the first 8 have a `Vel`
+fn archetypes_scene() -> Scene
+{
+    let mut scene = Scene::default();
+
+    for i in 0..512
+    {
+        for _ in 0..16
+        {
+            let ent = scene.spawn((Pos::default(),));
+
+            if i < 8 { scene.add(ent, (Vel(1.0, 2.0, 3.0),)); }
+            if i & 1 != 0 { scene.add(ent, (A,)); }
+            if i & 2 != 0 { scene.add(ent, (B,)); }
+            if i & 4 != 0 { scene.add(ent, (C,)); }
+            if i & 8 != 0 { scene.add(ent, (D,)); }
+            if i & 16 != 0 { scene.add(ent, (E,)); }
+            if i & 32 != 0 { scene.add(ent, (F,)); }
+            if i & 64 != 0 { scene.add(ent, (G,)); }
+            if i & 128 != 0 { scene.add(ent, (H,)); }
+            if i & 256 != 0 { scene.add(ent, (I,)); }
+        }
+    }
+    scene
+}
+
+/// the baseline, with `N` entities
+fn baseline() -> Vec<(Pos, Vel)>
+{
+    vec![(Pos::default(), Vel(1.0, 2.0, 3.0)); N]
+}
+
+fn spawn(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("spawn");
+
+    group.sample_size(10);
+    group.bench_function("ezgame", |b| b.iter(|| black_box(scene())));
+    group.bench_function("ezgame_one_by_one", |b| b.iter(||
+    {
+        let mut scene = Scene::default();
+
+        for _ in 0..N
+        {
+            scene.spawn((Pos::default(), Vel(1.0, 2.0, 3.0)));
+        }
+        black_box(scene)
+    }));
+    group.bench_function("ezgame_with_capacity", |b| b.iter(||
+    {
+        let mut scene = Scene::with_capacity(N);
+
+        for _ in 0..N
+        {
+            scene.spawn((Pos::default(), Vel(1.0, 2.0, 3.0)));
+        }
+        black_box(scene)
+    }));
+    group.bench_function("baseline", |b| b.iter(||
+    {
+        let mut vec = Vec::new();
+
+        for _ in 0..N
+        {
+            vec.push((Pos::default(), Vel(1.0, 2.0, 3.0)));
+        }
+        black_box(vec)
+    }));
+    group.finish();
+}
+
+fn despawn(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("despawn");
+
+    group.sample_size(10);
+    group.bench_function("ezgame", |b| b.iter_batched
+    (
+        ||
+        {
+            let mut scene = scene();
+            let ents = scene
+                .query::<Entity>()
+                .collect::<Vec<_>>();
+
+            (scene, ents)
+        },
+        |(mut scene, ents)|
+        {
+            for ent in ents
+            {
+                scene.despawn(ent);
+            }
+            scene
+        },
+        BatchSize::PerIteration,
+    ));
+    // `swap_remove` is what the chunks do too
+    group.bench_function("baseline", |b| b.iter_batched
+    (
+        baseline,
+        |mut vec|
+        {
+            while !vec.is_empty()
+            {
+                black_box(vec.swap_remove(0));
+            }
+            vec
+        },
+        BatchSize::PerIteration,
+    ));
+    group.finish();
+}
+
+fn iter_one(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("iter_one");
+    let mut scene = scene();
+    let mut fragmented = fragmented_scene();
+    let mut vec = baseline();
+
+    group.bench_function("ezgame", |b| b.iter(||
+    {
+        for pos in scene.query::<&mut Pos>()
+        {
+            pos.0 += 1.0;
+        }
+    }));
+    group.bench_function("ezgame_fragmented", |b| b.iter(||
+    {
+        for pos in fragmented.query::<&mut Pos>()
+        {
+            pos.0 += 1.0;
+        }
+    }));
+    group.bench_function("baseline", |b| b.iter(||
+    {
+        for (pos, _) in &mut vec
+        {
+            pos.0 += 1.0;
+        }
+    }));
+    group.finish();
+}
+
+fn iter_two(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("iter_two");
+    let mut scene = scene();
+    let mut fragmented = fragmented_scene();
+    let mut vec = baseline();
+
+    group.bench_function("ezgame", |b| b.iter(||
+    {
+        for (pos, vel) in scene.query::<(&mut Pos, &Vel)>()
+        {
+            pos.0 += vel.0;
+            pos.1 += vel.1;
+            pos.2 += vel.2;
+        }
+    }));
+    group.bench_function("ezgame_fragmented", |b| b.iter(||
+    {
+        for (pos, vel) in fragmented.query::<(&mut Pos, &Vel)>()
+        {
+            pos.0 += vel.0;
+            pos.1 += vel.1;
+            pos.2 += vel.2;
+        }
+    }));
+    group.bench_function("baseline", |b| b.iter(||
+    {
+        for (pos, vel) in &mut vec
+        {
+            pos.0 += vel.0;
+            pos.1 += vel.1;
+            pos.2 += vel.2;
+        }
+    }));
+    group.finish();
+}
+
+fn iter_chunks(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("iter_chunks");
+    let mut scene = scene();
+
+    // saxpy-like kernel, `pos += a * vel`
+    let a = black_box(0.5);
+
+    group.bench_function("per_entity", |b| b.iter(||
+    {
+        for (pos, vel) in scene.query::<(&mut Pos, &Vel)>()
+        {
+            pos.0 += a * vel.0;
+            pos.1 += a * vel.1;
+            pos.2 += a * vel.2;
+        }
+    }));
+    group.bench_function("chunk_slices", |b| b.iter(||
+    {
+        for (_, (pos, vel)) in scene.query_chunks::<(&mut Pos, &Vel)>()
+        {
+            for (pos, vel) in pos.iter_mut().zip(vel)
+            {
+                pos.0 += a * vel.0;
+                pos.1 += a * vel.1;
+                pos.2 += a * vel.2;
+            }
+        }
+    }));
+    group.finish();
+}
+
+fn par_iter(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("par_iter");
+    let mut scene = scene();
+
+    // heavy enough per entity for the threads to pay for themselves
+    let work = |(pos, vel): (&mut Pos, &Vel)|
+    {
+        for _ in 0..16
+        {
+            pos.0 = (pos.0 + vel.0).sqrt();
+            pos.1 = (pos.1 + vel.1).sqrt();
+            pos.2 = (pos.2 + vel.2).sqrt();
+        }
+    };
+
+    group.sample_size(20);
+    group.bench_function("sequential", |b| b.iter(||
+    {
+        scene.query::<(&mut Pos, &Vel)>().for_each(work);
+    }));
+    for threads in [1, 2, 4, 8]
+    {
+        let pool = ScopedPool::new(threads);
+
+        group.bench_function(format!("threads_{}", threads), |b| b.iter(||
+        {
+            scene.par_for_each::<(&mut Pos, &Vel), _>(&pool, work);
+        }));
+    }
+    group.finish();
+}
+
+fn query_state(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("query_state");
+    let mut scene = archetypes_scene();
+    let mut state = scene.query_state::<(&mut Pos, &Vel)>();
+
+    // a hot query only matching a handful of the many archetypes
+    group.bench_function("uncached", |b| b.iter(||
+    {
+        for (pos, vel) in scene.query::<(&mut Pos, &Vel)>()
+        {
+            pos.0 += vel.0;
+        }
+    }));
+    group.bench_function("cached", |b| b.iter(||
+    {
+        for (pos, vel) in state.iter(&mut scene)
+        {
+            pos.0 += vel.0;
+        }
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, spawn, despawn, iter_one, iter_two, iter_chunks, par_iter, query_state);
+criterion_main!(benches);
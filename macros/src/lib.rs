@@ -1,13 +1,307 @@
-use syn::{ DeriveInput, parse_macro_input };
+use syn::{ Attribute, Data, DeriveInput, Error, Fields, FnArg, GenericArgument, GenericParam, Index, ItemFn, Lit, LitStr, Meta, NestedMeta, PathArguments, ReturnType, Type, parse_macro_input };
+use syn::spanned::Spanned;
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::Span;
+use quote::{ format_ident, quote, quote_spanned };
 
-#[proc_macro_derive(Component)]
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_cmp(input: TokenStream) -> TokenStream
 {
-    /// next component identifier
-    static mut NEXT_ID: u64 = 0;
-    
+    // parse the input tokens into a syntax tree
+    let input = parse_macro_input!(input as DeriveInput);
+
+    // reject types that can't be components
+    if let Err(err) = validate_cmp(&input)
+    {
+        return err.to_compile_error().into();
+    }
+
+    // `#[component(...)]` options
+    let attrs = match CmpAttrs::parse(&input.attrs)
+    {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    // lifecycle hooks, delegated to `CmpHooks`
+    let hooks = if attrs.hooks
+    {
+        quote!
+        {
+            fn on_spawn(&mut self, ent: ezgame::Entity)
+            {
+                <Self as ezgame::CmpHooks>::on_spawn(self, ent)
+            }
+
+            fn on_despawn(&mut self, ent: ezgame::Entity)
+            {
+                <Self as ezgame::CmpHooks>::on_despawn(self, ent)
+            }
+        }
+    }
+    else
+    {
+        quote!()
+    };
+
+    // type info, which can't have generics past `validate_cmp`
+    let where_clause = &input.generics.where_clause;
+    let name = input.ident;
+
+    // display name, overridable with `#[component(name = "...")]`
+    let display = match attrs.name
+    {
+        Some(lit) => quote!(#lit),
+        None => quote!(stringify!(#name)),
+    };
+
+    // meta-data, with the opted-in function pointers. these are spanned to
+    // their option, so a missing `Clone`/`Default` impl is reported there
+    let clone = match attrs.clone
+    {
+        Some(span) => quote_spanned!(span=> .with_clone(ezgame::clone_ptr::<Self>)),
+        None => quote!(),
+    };
+    let default = match attrs.default
+    {
+        Some(span) => quote_spanned!(span=> .with_default(ezgame::default_ptr::<Self>)),
+        None => quote!(),
+    };
+    let eq = match attrs.eq
+    {
+        Some(span) => quote_spanned!(span=> .with_eq(ezgame::eq_ptr::<Self>)),
+        None => quote!(),
+    };
+    let meta = if attrs.clone.is_some() || attrs.default.is_some() || attrs.eq.is_some() || attrs.serde.is_some()
+    {
+        let mut meta = quote!(ezgame::CmpMeta::of::<Self>() #clone #default #eq);
+
+        // serde is feature-gated within ezgame, so it's delegated to a macro there
+        if let Some(span) = attrs.serde
+        {
+            meta = quote_spanned!(span=> ezgame::__cmp_serde!(Self, #meta));
+        }
+        quote!
+        {
+            const META: ezgame::CmpMeta = #meta;
+        }
+    }
+    else
+    {
+        quote!()
+    };
+
+    // the path alone isn't unique, ie. for types of the same name local to different
+    // functions, so the location of the name is hashed too
+    let path = quote_spanned!(name.span()=> concat!(module_path!(), "::", stringify!(#name), "@", line!(), ":", column!()));
+
+    // impl trait
+    TokenStream::from(quote!
+    {
+        impl ezgame::Component for #name #where_clause
+        {
+            const ID: ezgame::CmpId = ezgame::CmpId::of_path(#path);
+            const NAME: &'static str = #display;
+
+            #meta
+
+            #hooks
+        }
+
+        impl ezgame::MutComponent for #name #where_clause { }
+
+        ezgame::__cmp_register!(#name);
+    })
+}
+
+/// implements `Component` for a type that can't be annotated with `#[derive(Component)]`,
+/// ie. one generated by another macro: `impl_component!(crate::path::to::Type);`
+///
+/// the path must start from the crate root, which makes it unique to the type:
+/// `impl_component!(crate::net::Ping)` in `game` is `CmpId::of_path("game::net::Ping")`.
+/// types from other crates can't be components, because of the orphan rule
+#[proc_macro]
+pub fn impl_component(input: TokenStream) -> TokenStream
+{
+    // parse the input tokens into a type
+    let ty = parse_macro_input!(input as Type);
+
+    // same restrictions as the derive
+    if let Err(err) = validate_static(&ty)
+    {
+        return err.to_compile_error().into();
+    }
+
+    // type info, with its path normalized so that spacing doesn't change the ID
+    let (name, path) = match &ty
+    {
+        Type::Path(path) if path.qself.is_none() && path.path.leading_colon.is_none() =>
+        {
+            if path.path.segments.iter().any(|seg| !seg.arguments.is_empty())
+            {
+                return Error::new_spanned(&ty, "components can't be generic, as every instance of the type would share one component ID")
+                    .to_compile_error()
+                    .into();
+            }
+            let idents = path.path.segments
+                .iter()
+                .map(|seg| seg.ident.to_string())
+                .collect::<Vec<_>>();
+
+            // a relative path could name another type elsewhere, and share its ID
+            match idents.split_first()
+            {
+                Some((root, rest)) if root == "crate" && !rest.is_empty() && !rest.iter().any(|seg| seg == "self" || seg == "super" || seg == "crate") =>
+                {
+                    (rest[rest.len() - 1].clone(), rest.join("::"))
+                },
+                _ => return Error::new_spanned(&ty, "the path must start from the crate root, ie. `impl_component!(crate::path::to::Type)`")
+                    .to_compile_error()
+                    .into(),
+            }
+        },
+        _ => return Error::new_spanned(&ty, "expected a path to a type, ie. `impl_component!(crate::path::to::Type)`")
+            .to_compile_error()
+            .into(),
+    };
+
+    // impl trait
+    TokenStream::from(quote!
+    {
+        impl ezgame::Component for #ty
+        {
+            const ID: ezgame::CmpId = ezgame::CmpId::of_path(concat!(env!("CARGO_CRATE_NAME"), "::", #path));
+            const NAME: &'static str = #name;
+        }
+
+        impl ezgame::MutComponent for #ty { }
+
+        ezgame::__cmp_register!(#ty);
+    })
+}
+
+/// turns a function into a struct of the same name implementing `System`, whose
+/// parameters are fetched from the scene every time it's run:
+/// ```ignore
+/// #[system]
+/// fn movement(pos: View<(&mut Pos, &Vel)>, time: Res<Time>) { ... }
+///
+/// scene.run(movement);
+/// ```
+/// the name is also a constant, so every use of it is a new instance with its own
+/// parameter state, ie. `Local`s
+#[proc_macro_attribute]
+pub fn system(attr: TokenStream, input: TokenStream) -> TokenStream
+{
+    // no options
+    if !attr.is_empty()
+    {
+        return Error::new(Span::call_site(), "`#[system]` doesn't take any option")
+            .to_compile_error()
+            .into();
+    }
+
+    // parse the input tokens into a syntax tree
+    let input = parse_macro_input!(input as ItemFn);
+
+    // reject functions that can't be systems
+    if let Err(err) = validate_sys(&input)
+    {
+        return err.to_compile_error().into();
+    }
+
+    // function info
+    let ItemFn { attrs, vis, sig, block } = input;
+    let name = &sig.ident;
+
+    // documentation goes on the struct, everything else on the function
+    let (docs, attrs): (Vec<_>, Vec<_>) = attrs
+        .into_iter()
+        .partition(|attr| attr.path.is_ident("doc"));
+
+    // parameter types, spanned so that unknown ones are reported there
+    let params = sig.inputs
+        .iter()
+        .filter_map(|arg| match arg
+        {
+            FnArg::Typed(arg) => Some(&*arg.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let states = params
+        .iter()
+        .map(|ty| quote_spanned!(ty.span()=> ::std::boxed::Box::new(<<#ty as ezgame::SystemParam>::State as ::std::default::Default>::default())));
+    let fetch = params
+        .iter()
+        .map(|ty| quote_spanned!(ty.span()=> <#ty as ezgame::SystemParam>::fetch(scene, state.next().unwrap().downcast_mut().unwrap())));
+    let access = params
+        .iter()
+        .map(|ty| quote_spanned!(ty.span()=> <#ty as ezgame::SystemParam>::access(&mut access)));
+
+    // impl trait
+    TokenStream::from(quote!
+    {
+        #(#docs)*
+        #[allow(non_camel_case_types)]
+        #vis struct #name
+        {
+            /// state of the parameters, created before the first run
+            state: ::std::option::Option<::std::vec::Vec<::std::boxed::Box<dyn ::std::any::Any + Send>>>,
+        }
+
+        // every use of the name is a new instance, with its own state
+        #(#docs)*
+        #[allow(non_upper_case_globals)]
+        #vis const #name: #name = #name { state: ::std::option::Option::None };
+
+        // SAFETY: the access is that of every parameter, which only fetch what they report
+        unsafe impl ezgame::System for #name
+        {
+            fn run(&mut self, scene: &mut ezgame::Scene)
+            {
+                // SAFETY: the scene is exclusively borrowed for the duration of the call
+                unsafe { ezgame::System::run_shared(self, scene) }
+            }
+
+            unsafe fn run_shared(&mut self, scene: *mut ezgame::Scene)
+            {
+                #(#attrs)*
+                #sig #block
+
+                // reject conflicting parameters, once
+                static CHECK: ::std::sync::Once = ::std::sync::Once::new();
+                CHECK.call_once(|| ezgame::System::access(self).check());
+
+                // state of the parameters, private to this instance
+                #[allow(unused_variables, unused_mut)]
+                let mut state = self.state
+                    .get_or_insert_with(|| ::std::vec![#(#states as ::std::boxed::Box<dyn ::std::any::Any + Send>),*])
+                    .iter_mut();
+
+                // SAFETY: the parameters don't alias, and neither do those of the
+                // systems running alongside this one
+                #name(#(#fetch),*)
+            }
+
+            fn name(&self) -> &str
+            {
+                concat!(module_path!(), "::", stringify!(#name))
+            }
+
+            fn access(&self) -> ezgame::Access
+            {
+                let mut access = ezgame::Access::default();
+
+                #(#access;)*
+                access
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(Resource)]
+pub fn derive_res(input: TokenStream) -> TokenStream
+{
     // parse the input tokens into a syntax tree
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -15,18 +309,342 @@ pub fn derive_cmp(input: TokenStream) -> TokenStream
     let (impl_gen, ty_gen, where_clause) = input.generics.split_for_impl();
     let name = input.ident;
 
-    // increment type ID
-    unsafe { NEXT_ID += 1 };
-    
-    // get the current type ID
-    let id = unsafe { NEXT_ID };
+    // impl trait
+    TokenStream::from(quote!
+    {
+        impl #impl_gen ezgame::Resource for #name #ty_gen #where_clause
+        {
+            const NAME: &'static str = stringify!(#name);
+        }
+    })
+}
+
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream
+{
+    // parse the input tokens into a syntax tree
+    let input = parse_macro_input!(input as DeriveInput);
+
+    // type info
+    let (impl_gen, ty_gen, where_clause) = input.generics.split_for_impl();
+    let name = input.ident;
+
+    // bundles are only made out of structs' fields
+    let fields = match input.data
+    {
+        Data::Struct(data) => data.fields,
+        _ => return Error::new(name.span(), "`#[derive(Bundle)]` is only supported on structs")
+            .to_compile_error()
+            .into(),
+    };
+
+    // (accessor, type) of every field, named or not
+    let (access, ty): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident
+        {
+            Some(ident) => (quote!(#ident), &field.ty),
+            None =>
+            {
+                let i = Index::from(i);
+                (quote!(#i), &field.ty)
+            }
+        })
+        .unzip();
+
+    // duplicate components can only be caught at compile-time if
+    // the bundle isn't generic
+    let check = if input.generics.params.is_empty()
+    {
+        quote!
+        {
+            const _: () = assert!
+            (
+                !<#name as ezgame::CmpSet>::IDS.has_duplicates(),
+                "bundle contains duplicate component types!"
+            );
+        }
+    }
+    else
+    {
+        quote!()
+    };
+
+    // struct-of-slices view, with the same shape as the bundle. generic bundles
+    // don't get one
+    let view = if input.generics.params.is_empty()
+    {
+        let view = format_ident!("{}View", name);
+        let vis = &input.vis;
+        let doc = format!("struct-of-slices view of the components of [`{}`] within a chunk, see `Scene::view`", name);
+        let field_vis = fields
+            .iter()
+            .map(|field| &field.vis)
+            .collect::<Vec<_>>();
+        let body = match &fields
+        {
+            Fields::Named(_) => quote!({ #(#field_vis #access: <#ty as ezgame::ChunkView>::View<'a>),* }),
+            _ => quote!(( #(#field_vis <#ty as ezgame::ChunkView>::View<'a>),* );),
+        };
+        let init = match &fields
+        {
+            Fields::Named(_) => quote!(#view { #(#access: <#ty as ezgame::ChunkView>::view(chunk)),* }),
+            _ => quote!(#view( #(<#ty as ezgame::ChunkView>::view(chunk)),* )),
+        };
+
+        quote!
+        {
+            #[doc = #doc]
+            #vis struct #view<'a> #body
+
+            unsafe impl ezgame::ChunkView for #name
+            {
+                type View<'a> = #view<'a>;
+
+                unsafe fn view<'a>(chunk: &'a ezgame::ArchetypeChunk) -> Self::View<'a>
+                {
+                    #init
+                }
+            }
+        }
+    }
+    else
+    {
+        quote!()
+    };
 
     // impl trait
     TokenStream::from(quote!
     {
-        impl #impl_gen ezgame::Component for #name #ty_gen #where_clause
+        impl #impl_gen ezgame::CmpSet for #name #ty_gen #where_clause
+        {
+            const IDS: ezgame::CmpIds = ezgame::CmpIds::Many(&[#(<#ty as ezgame::CmpSet>::IDS),*]);
+
+            fn instance_types(&self) -> &[ezgame::CmpId]
+            {
+                <Self as ezgame::StaticCmpSet>::types()
+            }
+
+            fn instance_metas(&self) -> &[ezgame::CmpMeta]
+            {
+                <Self as ezgame::StaticCmpSet>::metas()
+            }
+
+            unsafe fn insert(self, arch: &mut ezgame::Archetype, loc: ezgame::EntityLocation)
+            {
+                #(ezgame::CmpSet::insert(self.#access, arch, loc);)*
+            }
+        }
+
+        impl #impl_gen ezgame::StaticCmpSet for #name #ty_gen #where_clause
         {
-            const ID: ezgame::CmpId = unsafe { ezgame::CmpId::from_u64(#id) };
+            fn collect_metas(out: &mut ::std::vec::Vec<ezgame::CmpMeta>)
+            {
+                #(<#ty as ezgame::StaticCmpSet>::collect_metas(out);)*
+            }
         }
+
+        #check
+        #view
     })
-}
\ No newline at end of file
+}
+
+
+/// options of the `#[component(...)]` attribute
+#[derive(Default)]
+struct CmpAttrs
+{
+    /// `#[component(hooks)]`, delegate lifecycle hooks to `CmpHooks`
+    hooks: bool,
+    /// `#[component(clone)]`, store the clone function in the meta-data
+    clone: Option<Span>,
+    /// `#[component(default)]`, store the default function in the meta-data
+    default: Option<Span>,
+    /// `#[component(eq)]`, store the equality function in the meta-data
+    eq: Option<Span>,
+    /// `#[component(serde)]`, store the (de)serialize functions in the meta-data
+    serde: Option<Span>,
+    /// `#[component(name = "...")]`, override the display name
+    name: Option<LitStr>,
+}
+
+impl CmpAttrs
+{
+    /// parse the options out of every `#[component(...)]` attribute
+    fn parse(attrs: &[Attribute]) -> Result<Self, Error>
+    {
+        let mut out = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("component"))
+        {
+            let list = match attr.parse_meta()?
+            {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected `#[component(...)]`")),
+            };
+
+            for nested in list.nested
+            {
+                // every option is keyed by a path
+                let path = match &nested
+                {
+                    NestedMeta::Meta(meta) => meta.path(),
+                    NestedMeta::Lit(lit) => return Err(Error::new_spanned(lit, "expected a component option")),
+                };
+                let span = path.get_ident().map(|ident| ident.span());
+
+                // was this option already given?
+                let dup = match (&nested, span)
+                {
+                    (NestedMeta::Meta(Meta::Path(_)), Some(_)) if path.is_ident("hooks") =>
+                    {
+                        std::mem::replace(&mut out.hooks, true)
+                    }
+                    (NestedMeta::Meta(Meta::Path(_)), Some(span)) if path.is_ident("clone") =>
+                    {
+                        out.clone.replace(span).is_some()
+                    }
+                    (NestedMeta::Meta(Meta::Path(_)), Some(span)) if path.is_ident("default") =>
+                    {
+                        out.default.replace(span).is_some()
+                    }
+                    (NestedMeta::Meta(Meta::Path(_)), Some(span)) if path.is_ident("eq") =>
+                    {
+                        out.eq.replace(span).is_some()
+                    }
+                    (NestedMeta::Meta(Meta::Path(_)), Some(span)) if path.is_ident("serde") =>
+                    {
+                        out.serde.replace(span).is_some()
+                    }
+                    (NestedMeta::Meta(Meta::NameValue(nv)), _) if path.is_ident("name") => match &nv.lit
+                    {
+                        Lit::Str(lit) => out.name.replace(lit.clone()).is_some(),
+                        lit => return Err(Error::new_spanned(lit, "expected a string literal, ie. `name = \"...\"`")),
+                    },
+                    _ if ["hooks", "clone", "default", "eq", "serde", "name"].iter().any(|key| path.is_ident(key)) =>
+                    {
+                        return Err(Error::new_spanned(nested, "malformed component option"));
+                    }
+                    _ => return Err(Error::new_spanned(nested, "unknown component option")),
+                };
+                if dup
+                {
+                    return Err(Error::new_spanned(nested, "duplicate component option"));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// checks that the type being derived can be a component, returning a spanned
+/// error otherwise
+fn validate_cmp(input: &DeriveInput) -> Result<(), Error>
+{
+    // components are `'static` and have a single ID, so no generics
+    match input.generics.params.first()
+    {
+        Some(param @ GenericParam::Lifetime(_)) => return Err(Error::new_spanned
+        (
+            param,
+            "components must be `'static`, so they can't have lifetime parameters"
+        )),
+        Some(param) => return Err(Error::new_spanned
+        (
+            param,
+            "components can't be generic, as every instance of the type would share one component ID"
+        )),
+        None => (),
+    }
+
+    // every field, regardless of the kind of type
+    let fields: Vec<&Fields> = match &input.data
+    {
+        Data::Struct(data) => vec![&data.fields],
+        Data::Enum(data) => data.variants
+            .iter()
+            .map(|variant| &variant.fields)
+            .collect(),
+        Data::Union(data) => return Err(Error::new_spanned
+        (
+            data.union_token,
+            "components can't be unions, as their destructor can't know which field to drop"
+        )),
+    };
+
+    // references within fields must be `'static`
+    fields
+        .into_iter()
+        .flat_map(|fields| fields.iter())
+        .try_for_each(|field| validate_static(&field.ty))
+}
+
+/// checks that a function can be a system, returning a spanned error otherwise
+fn validate_sys(input: &ItemFn) -> Result<(), Error>
+{
+    let sig = &input.sig;
+
+    if let Some(token) = &sig.asyncness
+    {
+        return Err(Error::new_spanned(token, "systems can't be async"));
+    }
+    if let Some(param) = sig.generics.params.first()
+    {
+        return Err(Error::new_spanned(param, "systems can't be generic, their parameters are fetched from the scene"));
+    }
+    if let Some(arg) = sig.inputs.iter().find(|arg| matches!(arg, FnArg::Receiver(_)))
+    {
+        return Err(Error::new_spanned(arg, "systems can't take `self`, use a `#[system]` free function"));
+    }
+    if let ReturnType::Type(_, ty) = &sig.output
+    {
+        return Err(Error::new_spanned(ty, "systems can't return a value"));
+    }
+    Ok(())
+}
+
+/// checks that every reference within a type is `'static`, returning a spanned
+/// error otherwise
+fn validate_static(ty: &Type) -> Result<(), Error>
+{
+    match ty
+    {
+        Type::Reference(r) => match &r.lifetime
+        {
+            Some(lt) if lt.ident == "static" => validate_static(&r.elem),
+            _ => Err(Error::new_spanned
+            (
+                r,
+                "components must be `'static`, so they can only contain `&'static` references"
+            )),
+        },
+        Type::Array(arr) => validate_static(&arr.elem),
+        Type::Slice(slice) => validate_static(&slice.elem),
+        Type::Group(group) => validate_static(&group.elem),
+        Type::Paren(paren) => validate_static(&paren.elem),
+        Type::Ptr(ptr) => validate_static(&ptr.elem),
+        Type::Tuple(tuple) => tuple.elems
+            .iter()
+            .try_for_each(validate_static),
+        Type::Path(path) => path.path.segments
+            .iter()
+            .filter_map(|seg| match &seg.arguments
+            {
+                PathArguments::AngleBracketed(args) => Some(args),
+                _ => None,
+            })
+            .flat_map(|args| args.args.iter())
+            .try_for_each(|arg| match arg
+            {
+                GenericArgument::Type(ty) => validate_static(ty),
+                GenericArgument::Lifetime(lt) if lt.ident != "static" => Err(Error::new_spanned
+                (
+                    lt,
+                    "components must be `'static`, so they can only contain `'static` lifetimes"
+                )),
+                _ => Ok(()),
+            }),
+        _ => Ok(()),
+    }
+}
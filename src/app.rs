@@ -0,0 +1,229 @@
+use std::any::{ Any, TypeId };
+use std::time::{ Duration, Instant };
+
+use crate::{ Events, IntoSystem, Scene, Schedule, StageLabel, Systems };
+
+/// a game: one `Scene`, the `Schedule` of systems that runs over it every frame,
+/// and the loop driving them. it's built up front, then either run with its own
+/// loop(see `App::run`) or updated from an external one, ie. winit's or a headless
+/// server's tick(see `App::update`):
+/// ```ignore
+/// App::new()
+///     .insert_resource(Gravity(9.8))
+///     .add_startup_system(spawn_level)
+///     .add_system(movement)
+///     .run(Duration::from_secs(1) / 60);
+/// ```
+/// the scene's `Time` resource is updated every frame, see `Schedule::run`
+#[derive(Debug)]
+pub struct App
+{
+    /// the one scene of this app
+    scene: Scene,
+    /// run every frame
+    schedule: Schedule,
+    /// run once, before the first frame
+    startup: Systems,
+    /// has the first frame started?
+    started: bool,
+    /// types of the unique plugins added so far, see `Plugin::is_unique`
+    plugins: Vec<TypeId>,
+}
+
+/// reusable functionality packaged as a unit, ie. physics or input: the systems,
+/// stages, resources and events it needs, added to an app all at once through
+/// `App::add_plugin`
+/// ```ignore
+/// struct PhysicsPlugin;
+///
+/// impl Plugin for PhysicsPlugin
+/// {
+///     fn build(&self, app: &mut App)
+///     {
+///         app.insert_resource(Gravity(9.8)).add_system(integrate);
+///     }
+/// }
+/// ```
+pub trait Plugin: Any
+{
+    /// add this plugin's systems, resources and so on to `app`
+    fn build(&self, app: &mut App);
+
+    /// can this plugin only be added once to an app? most plugins add systems
+    /// that shouldn't run twice per frame, but those that are configured per
+    /// instance, ie. one per asset type, may opt out
+    fn is_unique(&self) -> bool
+    {
+        true
+    }
+
+    /// human-readable name of this plugin
+    fn name(&self) -> &str
+    {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// resource that stops `App::run` once the current frame is done, inserted by
+/// any system, ie. through `Commands::insert_resource`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AppExit;
+
+impl App
+{
+    /// the stage systems are added to by `App::add_system`, and the only one in
+    /// a new app
+    pub const UPDATE: StageLabel = StageLabel::new("update");
+
+    /// create a new app, with an empty scene and a single `App::UPDATE` stage
+    pub fn new() -> Self
+    {
+        let mut schedule = Schedule::new();
+        schedule.add_stage(Self::UPDATE);
+
+        Self
+        {
+            scene: Scene::default(),
+            schedule,
+            startup: Systems::new(),
+            started: false,
+            plugins: Vec::new(),
+        }
+    }
+
+    /// add a plugin's functionality to this app, see `Plugin`. panics if the
+    /// plugin is unique and was already added
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self
+    {
+        if plugin.is_unique()
+        {
+            let ty = plugin.type_id();
+
+            if self.plugins.contains(&ty)
+            {
+                panic!("plugin `{}` was already added!", plugin.name());
+            }
+            self.plugins.push(ty);
+        }
+        plugin.build(self);
+        self
+    }
+
+    /// insert a resource into the scene, see `Scene::insert_resource`
+    pub fn insert_resource<T: Send + 'static>(&mut self, res: T) -> &mut Self
+    {
+        self.scene.insert_resource(res);
+        self
+    }
+
+    /// add a system that runs once, before the first frame, ie. to spawn the level
+    pub fn add_startup_system<M, S: IntoSystem<M>>(&mut self, sys: S) -> &mut Self
+    where
+        S::System: Send + 'static
+    {
+        self.startup.add(sys);
+        self
+    }
+
+    /// add a system to the `App::UPDATE` stage, which runs every frame
+    pub fn add_system<M, S: IntoSystem<M>>(&mut self, sys: S) -> &mut Self
+    where
+        S::System: Send + 'static
+    {
+        self.add_system_to_stage(Self::UPDATE, sys)
+    }
+
+    /// add a system to a stage, see `Schedule::add_system_to_stage`
+    pub fn add_system_to_stage<M, S: IntoSystem<M>>(&mut self, label: StageLabel, sys: S) -> &mut Self
+    where
+        S::System: Send + 'static
+    {
+        self.schedule.add_system_to_stage(label, sys);
+        self
+    }
+
+    /// add a system with full access to the scene to a stage, see
+    /// `Schedule::add_exclusive_system_to_stage`
+    pub fn add_exclusive_system_to_stage(&mut self, label: StageLabel, sys: impl FnMut(&mut Scene) + Send + 'static) -> &mut Self
+    {
+        self.schedule.add_exclusive_system_to_stage(label, sys);
+        self
+    }
+
+    /// add an empty stage after every other one, see `Schedule::add_stage`
+    pub fn add_stage(&mut self, label: StageLabel) -> &mut Self
+    {
+        self.schedule.add_stage(label);
+        self
+    }
+
+    /// add an `Events<T>` queue to the scene, updated every frame, see
+    /// `Schedule::add_event`
+    pub fn add_event<T: Send + 'static>(&mut self) -> &mut Self
+    {
+        if self.scene.resource::<Events<T>>().is_none()
+        {
+            self.scene.insert_resource(Events::<T>::new());
+        }
+        self.schedule.add_event::<T>();
+        self
+    }
+
+    /// run a single frame, preceded by the startup systems if it's the first one
+    pub fn update(&mut self)
+    {
+        if !self.started
+        {
+            self.started = true;
+            self.startup.run(&mut self.scene);
+        }
+        self.schedule.run(&mut self.scene);
+    }
+
+    /// run frames until a system inserts the `AppExit` resource, starting one
+    /// every `frame` at most. frames that take longer start the next one right
+    /// away
+    pub fn run(&mut self, frame: Duration)
+    {
+        while self.scene.resource::<AppExit>().is_none()
+        {
+            let start = Instant::now();
+
+            self.update();
+
+            if let Some(rest) = frame.checked_sub(start.elapsed())
+            {
+                std::thread::sleep(rest);
+            }
+        }
+    }
+
+    /// get the scene of this app
+    #[inline]
+    pub fn scene(&self) -> &Scene
+    {
+        &self.scene
+    }
+
+    /// get the scene of this app
+    #[inline]
+    pub fn scene_mut(&mut self) -> &mut Scene
+    {
+        &mut self.scene
+    }
+
+    /// get the schedule of this app, ie. to add stages in between others
+    #[inline]
+    pub fn schedule_mut(&mut self) -> &mut Schedule
+    {
+        &mut self.schedule
+    }
+}
+
+impl Default for App
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
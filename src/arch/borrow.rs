@@ -0,0 +1,102 @@
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use crate::CmpMeta;
+use super::Archetype;
+
+/// runtime borrow flags of an archetype's columns, one per component in the order
+/// of `ArchetypeMeta::ids`. a flag is the number of shared borrows of its column,
+/// or `ColumnBorrows::WRITING` while it's borrowed mutably
+///
+/// they catch conflicting borrows that went around the borrow checker, ie. two
+/// queries over the same scene through `unsafe`, or parallel systems reporting
+/// the wrong `Access`. the `no-runtime-checks` feature turns them into no-ops
+///
+/// only queries take them, chunk iterators and views included: the accessors of
+/// an `ArchetypeChunk`(ie. `ArchetypeChunk::components`) borrow the chunk itself
+/// instead, which the borrow checker already guards
+#[derive(Debug, Default)]
+pub(crate) struct ColumnBorrows(Box<[AtomicUsize]>);
+
+/// borrows of the columns of every archetype visited by a query, released when
+/// it's dropped, see `ColumnBorrows`
+#[derive(Debug)]
+pub(crate) struct ColumnGuard<'a>
+{
+    /// components accessed by the query, and whether it's mutably
+    access: Vec<(CmpMeta, bool)>,
+    /// archetypes whose columns are borrowed
+    held: Vec<&'a Archetype>,
+}
+
+impl ColumnBorrows
+{
+    /// flag of a column that's borrowed mutably
+    const WRITING: usize = usize::MAX;
+
+    /// create the borrow flags of `len` unborrowed columns
+    pub(crate) fn new(len: usize) -> Self
+    {
+        Self((0..len).map(|_| AtomicUsize::new(0)).collect())
+    }
+
+    /// borrow the column at `i`, returning `false` instead if it's already borrowed
+    /// mutably, or at all if `mutable`
+    pub(crate) fn acquire(&self, i: usize, mutable: bool) -> bool
+    {
+        let flag = &self.0[i];
+
+        if mutable
+        {
+            flag.compare_exchange(0, Self::WRITING, Ordering::Acquire, Ordering::Relaxed).is_ok()
+        }
+        else
+        {
+            flag.fetch_update(Ordering::Acquire, Ordering::Relaxed, |n| (n < Self::WRITING - 1).then(|| n + 1)).is_ok()
+        }
+    }
+
+    /// release a borrow of the column at `i`, see `ColumnBorrows::acquire`
+    pub(crate) fn release(&self, i: usize, mutable: bool)
+    {
+        if mutable
+        {
+            self.0[i].store(0, Ordering::Release);
+        }
+        else
+        {
+            self.0[i].fetch_sub(1, Ordering::Release);
+        }
+    }
+}
+
+impl<'a> ColumnGuard<'a>
+{
+    /// create a guard for a query accessing the components in `access`
+    pub(crate) fn new(access: Vec<(CmpMeta, bool)>) -> Self
+    {
+        Self { access, held: Vec::new() }
+    }
+
+    /// borrow the columns of `arch` accessed by the query, until this guard is dropped.
+    /// panics if one of them is already borrowed in a conflicting way
+    pub(crate) fn acquire(&mut self, arch: &'a Archetype)
+    {
+        if cfg!(feature = "no-runtime-checks")
+        {
+            return;
+        }
+        arch.borrow_columns(&self.access);
+        self.held.push(arch);
+    }
+}
+
+impl Drop for ColumnGuard<'_>
+{
+    fn drop(&mut self)
+    {
+        for arch in &self.held
+        {
+            arch.release_columns(&self.access);
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
 use std::ptr::NonNull;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
 
 use super::{ Archetype, ArchetypeMeta };
-use crate::Entity;
+use crate::{ CmpId, CmpMeta, Component, Entity, EntityLocation };
 
 /// a single, 16kb chunk in an archetype
 #[derive(Debug)]
@@ -11,7 +13,7 @@ pub struct ArchetypeChunk
 {
     /// meta-data about this chunk's parent `Archetype`, which is shared with
     /// it too
-    meta: Rc<ArchetypeMeta>,
+    meta: Arc<ArchetypeMeta>,
     /// ~16kb chunk of packed `EntId` + `impl Component`
     ///
     /// `*data.get()[0]` is the first entity ID, therefore, `data.get()`
@@ -27,6 +29,10 @@ pub struct ArchetypeChunk
     pub(super) data: UnsafeCell<NonNull<u8>>,
     /// number of entities currently stored in this chunk
     pub(super) len: usize,
+    /// (added, changed) change ticks of every column, in the same order as
+    /// `ArchetypeMeta::ids`. they're atomic so that queries, which only borrow
+    /// chunks immutably, can mark the columns they write to
+    ticks: Box<[(AtomicU64, AtomicU64)]>,
 }
 
 impl ArchetypeChunk
@@ -38,8 +44,11 @@ impl ArchetypeChunk
     // returns the chunk's index
     pub(super) fn append_to(arch: &mut Archetype) -> usize
     {
+        debug_assert!(arch.meta.max > 0, "cannot create a chunk that holds zero entities!");
+        assert!(arch.chunks.len() <= EntityLocation::MAX_CHUNK, "too many chunks to be addressed by an `EntityLocation`!");
+
         // clone the archetype meta shared reference
-        let meta = Rc::clone(&arch.meta);
+        let meta = Arc::clone(&arch.meta);
         // first get a well-aligned layout
         let layout = meta.layout;
         // make a heap allocation and get the pointer
@@ -52,11 +61,16 @@ impl ArchetypeChunk
 
         // chunk starts empty(no entities)
         let len = 0;
+        // ...and unchanged
+        let ticks = meta.ids
+            .iter()
+            .map(|_| Default::default())
+            .collect();
 
         // mark the new chunk as free(which it will be)
         arch.free.insert(arch.chunks.len());
         // append the chunk to the archetype
-        arch.chunks.push(ArchetypeChunk { meta, data, len });
+        arch.chunks.push(ArchetypeChunk { meta, data, len, ticks });
 
         // return the new chunk's index
         arch.chunks.len() - 1
@@ -89,14 +103,246 @@ impl ArchetypeChunk
             std::slice::from_raw_parts_mut(ptr, self.len)
         }
     }
+
+    /// returns a slice of the `T` components within this chunk. the slice returned only
+    /// contains the occupied entity slots, not the entire capacity: `&[T].len() == chunk.len()`
+    pub fn components<T: Component>(&self) -> &[T]
+    {
+        unsafe
+        {
+            // pointer to the start of `T` components
+            let ptr = self.ptr(self.meta.get::<T>().1) as *const T;
+
+            // create slice
+            std::slice::from_raw_parts(ptr, self.len)
+        }
+    }
+
+    /// see `ArchetypeChunk::components`
+    ///
+    /// reinterprets the `T` components as `U`s, ie. to read a `[f32; 3]` component as a
+    /// `glam::Vec3`. this isn't a conversion: the bytes are read as-is. panics if `U`
+    /// isn't the same size as `T`, or needs a stricter alignment
+    ///
+    /// # Safety
+    /// every `T` component must also be a valid `U` value
+    pub unsafe fn components_as<T: Component, U>(&self) -> &[U]
+    {
+        assert!
+        (
+            size_of::<T>() == size_of::<U>() && align_of::<T>() >= align_of::<U>(),
+            "can't read `{}` components as `{}`, their layouts are incompatible!",
+            T::NAME,
+            std::any::type_name::<U>(),
+        );
+        let cmp = self.components::<T>();
+
+        std::slice::from_raw_parts(cmp.as_ptr() as *const U, cmp.len())
+    }
+
+    /// returns a slice of the `T` components within this chunk. the slice returned only
+    /// contains the occupied entity slots, not the entire capacity: `&[T].len() == chunk.len()`
+    pub fn components_mut<T: Component>(&mut self) -> &mut [T]
+    {
+        self.mark_changed(T::ID);
+
+        unsafe
+        {
+            // pointer to the start of `T` components
+            let ptr = self.ptr(self.meta.get::<T>().1) as *mut T;
+
+            // create slice
+            std::slice::from_raw_parts_mut(ptr, self.len)
+        }
+    }
+
+    /// returns both the entity IDs and the `T` components within this chunk, which are
+    /// parallel slices of the same length: `&[Entity][i]` owns `&[T][i]`
+    pub fn entities_components<T: Component>(&self) -> (&[Entity], &[T])
+    {
+        (self.entities(), self.components::<T>())
+    }
+
+    /// see `ArchetypeChunk::entities_components`
+    pub fn entities_components_mut<T: Component>(&mut self) -> (&[Entity], &mut [T])
+    {
+        self.mark_changed(T::ID);
+
+        unsafe
+        {
+            // pointers to the start of entity IDs and `T` components, which never overlap
+            let ents = (*self.data.get()).as_ptr() as *const Entity;
+            let cmps = self.ptr(self.meta.get::<T>().1) as *mut T;
+
+            // create slices
+            (std::slice::from_raw_parts(ents, self.len), std::slice::from_raw_parts_mut(cmps, self.len))
+        }
+    }
+
+    /// returns a pointer to the start of the `T` components within this chunk, which
+    /// can be written through as long as no slice of them is alive
+    pub(crate) fn components_ptr<T: Component>(&self) -> *mut T
+    {
+        self.ptr(self.meta.get::<T>().1) as *mut T
+    }
+
+    /// returns the bytes of the `id` components within this chunk. the slice returned only
+    /// contains the occupied entity slots, not the entire capacity:
+    /// `slice.len() == chunk.len() * size_of(id)`, where the size is always that of the
+    /// archetype's `CmpMeta`, never one supplied by the caller
+    ///
+    /// the bytes of padding are uninitialized, hence `MaybeUninit`: only components
+    /// without padding can be read as a `&[u8]`
+    pub fn components_dyn(&self, id: CmpId) -> &[MaybeUninit<u8>]
+    {
+        let (meta, offset) = self.meta.get_dyn(id);
+        let len = self.column_len(meta, *offset);
+
+        unsafe
+        {
+            // pointer to the start of `id` components
+            let ptr = self.ptr(*offset) as *const MaybeUninit<u8>;
+
+            // create slice
+            std::slice::from_raw_parts(ptr, len)
+        }
+    }
+
+    /// returns the bytes of the `id` components within this chunk. the slice returned only
+    /// contains the occupied entity slots, not the entire capacity:
+    /// `slice.len() == chunk.len() * size_of(id)`, padding included like
+    /// `ArchetypeChunk::components_dyn`
+    ///
+    /// # Safety
+    /// the bytes written must remain a valid `id` component
+    pub unsafe fn components_dyn_mut(&mut self, id: CmpId) -> &mut [MaybeUninit<u8>]
+    {
+        let (meta, offset) = self.meta.get_dyn(id);
+        let len = self.column_len(meta, *offset);
+
+        self.mark_changed(id);
+
+        // pointer to the start of `id` components
+        let ptr = self.ptr(*offset) as *mut MaybeUninit<u8>;
+
+        // create slice
+        std::slice::from_raw_parts_mut(ptr, len)
+    }
+
+    /// number of bytes occupied by the column of `meta` components, which starts at
+    /// `offset`. in debug builds, checks that it doesn't straddle into the next column
+    /// or past the chunk's allocation
+    fn column_len(&self, meta: &CmpMeta, offset: usize) -> usize
+    {
+        let len = self.len * meta.size();
+
+        debug_assert!
+        (
+            offset + self.meta.max * meta.size() <= self.meta.layout.size() && len <= self.meta.max * meta.size(),
+            "column of component `{}` overruns its chunk!", meta.name(),
+        );
+        len
+    }
+
+    /// number of entities currently stored in this chunk
+    #[inline]
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    /// is this chunk empty of entities?
+    #[inline]
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// change tick at which the `id` components were last added to this chunk, ie.
+    /// by spawning an entity or moving one into this archetype. panics if `id`
+    /// isn't stored in this chunk
+    ///
+    /// ticks are tracked per chunk rather than per entity: a single entity being
+    /// added is enough to bump the whole column
+    pub fn added_tick(&self, id: CmpId) -> u64
+    {
+        self.ticks[self.column(id)].0.load(Ordering::Relaxed)
+    }
+
+    /// change tick at which the `id` components were last borrowed mutably within
+    /// this chunk, or added to it. panics if `id` isn't stored in this chunk
+    ///
+    /// ticks are tracked per chunk rather than per entity, and any mutable borrow
+    /// counts as a change whether or not it's written through
+    pub fn changed_tick(&self, id: CmpId) -> u64
+    {
+        self.ticks[self.column(id)].1.load(Ordering::Relaxed)
+    }
+
+    /// stamp the `id` components of this chunk as changed, at the scene's current tick
+    pub(crate) fn mark_changed(&self, id: CmpId)
+    {
+        let tick = self.meta.tick.load(Ordering::Relaxed);
+
+        self.ticks[self.column(id)].1.store(tick, Ordering::Relaxed);
+    }
+
+    /// stamp every column of this chunk as both added and changed, at the scene's
+    /// current tick
+    pub(super) fn mark_added(&self)
+    {
+        let tick = self.meta.tick.load(Ordering::Relaxed);
+
+        for (added, changed) in self.ticks.iter()
+        {
+            added.store(tick, Ordering::Relaxed);
+            changed.store(tick, Ordering::Relaxed);
+        }
+    }
+
+    /// index of the `id` column within `self.ticks`
+    fn column(&self, id: CmpId) -> usize
+    {
+        self.meta.ids
+            .binary_search(&id)
+            .unwrap_or_else(|_| self.meta.missing(format_args!("{:?}", id)))
+    }
+
+    /// returns a pointer to `offset` bytes into this chunk's allocation
+    pub(super) fn ptr(&self, offset: usize) -> *mut u8
+    {
+        unsafe
+        {
+            (*self.data.get()).as_ptr().add(offset)
+        }
+    }
 }
 
+// SAFETY: the chunk owns its allocation, and the components in it are `Send`, dynamic
+// ones included(see `CmpMeta::new_dynamic`)
+unsafe impl Send for ArchetypeChunk { }
+
 impl Drop for ArchetypeChunk
 {
     fn drop(&mut self)
     {
         unsafe
         {
+            // drop every occupied component slot...
+            for (meta, offset) in self.meta.cmp.values()
+            {
+                // ...of the types that need it
+                if let Some(drop) = meta.drop_fn()
+                {
+                    let ptr = (*self.data.get()).as_ptr().add(*offset);
+
+                    for i in 0..self.len
+                    {
+                        drop(ptr.add(i * meta.size()));
+                    }
+                }
+            }
+            // ...then free the allocation
             std::alloc::dealloc((*self.data.get()).as_ptr(), self.meta.layout);
         }
     }
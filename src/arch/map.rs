@@ -1,11 +1,13 @@
-use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
 
-use crate::{ CmpId, CmpSet };
+use crate::{ CmpId, CmpMeta, StaticCmpSet };
+use crate::hsh::{ IdHashMap, id_hash_map };
 use super::Archetype;
 
-/// structure that maps component `Vec<TypeMeta>` to component archetypes in
+/// structure that maps sorted component `Vec<CmpId>` to component archetypes in
 /// a hashmap-like structure
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ArchetypeMap
 {
     /// complete list of `Archetype`s. the collection can be expanded but is
@@ -13,18 +15,71 @@ pub struct ArchetypeMap
     /// reference an archetype
     arch: Vec<Archetype>,
     /// maps sorted `Vec<CmpId>` to an archetype index in `self.arch`
-    map: HashMap<Vec<CmpId>, usize>,
+    map: IdHashMap<Vec<CmpId>, usize>,
+    /// `(size, alignment)` of every component type within an archetype, which
+    /// the meta-data of new archetypes is checked against
+    layouts: IdHashMap<CmpId, (usize, usize)>,
+    /// current change tick, which every archetype stamps its chunks' columns
+    /// with when they're added to or borrowed mutably
+    tick: Arc<AtomicU64>,
+    /// called with every new archetype, see `ArchetypeMap::on_new_archetype`
+    observers: ArchetypeObservers,
+    /// unique identifier of this map, see `ArchetypeMap::uid`
+    uid: u64,
 }
 
+/// next `ArchetypeMap::uid`(thread-safe)
+static MAP_CURSOR: AtomicU64 = AtomicU64::new(1);
+
+/// callbacks of `ArchetypeMap::on_new_archetype`
+#[derive(Default)]
+struct ArchetypeObservers(Vec<Box<ArchetypeObserver>>);
+
+/// see `ArchetypeMap::on_new_archetype`
+type ArchetypeObserver = dyn FnMut(usize, &[CmpId]) + Send + Sync;
+
 impl ArchetypeMap
 {
+    /// create a new, empty map with room for `archetypes` archetypes without
+    /// reallocating
+    pub fn with_capacity(archetypes: usize) -> Self
+    {
+        Self
+        {
+            arch: Vec::with_capacity(archetypes),
+            map: id_hash_map(archetypes),
+            layouts: id_hash_map(0),
+            // starts past zero, so that everything counts as changed since tick 0
+            tick: Arc::new(AtomicU64::new(1)),
+            observers: ArchetypeObservers::default(),
+            uid: MAP_CURSOR.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// call `f` with the ID and component types of every archetype created from
+    /// now on, right after it's created, ie. to update a cache of the archetypes
+    /// matching a query rather than scanning them all again
+    pub fn on_new_archetype(&mut self, f: impl FnMut(usize, &[CmpId]) + Send + Sync + 'static)
+    {
+        self.observers.0.push(Box::new(f));
+    }
+
+    /// get the archetype made of exactly the components in `S`, creating it
+    /// if it doesn't exist yet
+    pub fn get_or_insert<S: StaticCmpSet>(&mut self) -> &mut Archetype
+    {
+        self.get_or_insert_dyn(S::types(), S::metas())
+    }
+
     /// see `ArchetypeMap::get_or_insert`
     ///
-    /// both `types` and the output of `meta` MUST be sorted via their `Ord` traits,
-    /// similar to implementing the `ComponentSet` trait on a concrete type
-    pub fn get_or_insert(&mut self, set: &impl CmpSet) -> &mut Archetype
+    /// both `types` and `metas` MUST be sorted via their `Ord` traits, similar
+    /// to implementing the `CmpSet` trait on a concrete type. panics if a meta's
+    /// layout differs from that of the same component type in another archetype,
+    /// ie. inconsistent meta-data from a scripting host
+    pub fn get_or_insert_dyn(&mut self, types: &[CmpId], metas: &[CmpMeta]) -> &mut Archetype
     {
-        let id = set.types(|types| match self.map.get_mut(types)
+        let id = match self.map.get(types)
         {
             Some(i) => *i,
             None =>
@@ -32,15 +87,153 @@ impl ArchetypeMap
                 // ID of the new archetype
                 let id = self.arch.len();
 
+                // sets with duplicates are the only ones without an archetype
+                if let Some(dup) = CmpMeta::find_duplicate(metas)
+                {
+                    panic!("component set contains `{}` more than once!", dup.name());
+                }
+                // layouts must agree with every other archetype's
+                for meta in metas
+                {
+                    let layout = (meta.size(), meta.alignment());
+
+                    if let Some(prev) = self.layouts.get(&meta.id()).filter(|prev| **prev != layout)
+                    {
+                        panic!
+                        (
+                            "component `{}` has a size of {} and alignment of {}, but it's {} and {} in other archetypes!",
+                            meta.name(), layout.0, layout.1, prev.0, prev.1,
+                        );
+                    }
+                }
+                self.layouts.extend(metas.iter().map(|meta| (meta.id(), (meta.size(), meta.alignment()))));
+
                 // create new archetype
                 self.map.insert(Vec::from(types), id);
-                self.arch.push(Archetype::new(id, &set.metas()));
+                self.arch.push(Archetype::new(id, &Vec::from(metas), Arc::clone(&self.tick)));
+
+                // notify the observers
+                for f in &mut self.observers.0
+                {
+                    f(id, types);
+                }
 
                 // return ID of the new archetype
                 id
             }
-        });
+        };
 
         &mut self.arch[id]
     }
-}
\ No newline at end of file
+
+    /// get the index of the archetype made of exactly the components in `types`,
+    /// if it exists. `types` MUST be sorted via its `Ord` trait
+    pub fn find(&self, types: &[CmpId]) -> Option<usize>
+    {
+        self.map.get(types).copied()
+    }
+
+    /// get an archetype by its ID(see `EntityLocation::archetype`), or `None` if
+    /// it's out of range
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<&Archetype>
+    {
+        self.arch.get(id)
+    }
+
+    /// iterate the archetypes that contain the component type `id`, ie. to run
+    /// custom iteration strategies over every entity with that component
+    pub fn iter_containing(&self, id: CmpId) -> impl Iterator<Item = &Archetype>
+    {
+        self.arch
+            .iter()
+            .filter(move |arch| arch.meta().contains_dyn(id))
+    }
+
+    /// see `ArchetypeMap::iter_containing`
+    pub fn iter_containing_mut(&mut self, id: CmpId) -> impl Iterator<Item = &mut Archetype>
+    {
+        self.arch
+            .iter_mut()
+            .filter(move |arch| arch.meta().contains_dyn(id))
+    }
+
+    /// counter bumped whenever a new archetype is created. since archetypes are
+    /// never removed, it's also the number of archetypes in this map
+    #[inline]
+    pub fn generation(&self) -> usize
+    {
+        self.arch.len()
+    }
+
+    /// identifier unique to this map among every one ever created, ie. to tell
+    /// the archetypes of different scenes apart. it's never 0
+    #[inline]
+    pub fn uid(&self) -> u64
+    {
+        self.uid
+    }
+
+    /// current change tick, see `Scene::tick`
+    #[inline]
+    pub fn change_tick(&self) -> u64
+    {
+        self.tick.load(Ordering::Relaxed)
+    }
+
+    /// advance the change tick, returning the previous one
+    pub(crate) fn advance_tick(&mut self) -> u64
+    {
+        self.tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// get the complete list of archetypes, indexed by their ID
+    #[inline]
+    pub fn inner(&self) -> &[Archetype]
+    {
+        &self.arch
+    }
+
+    /// get the complete list of archetypes, indexed by their ID. it's returned as
+    /// a slice so that archetypes can be mutated, but never added or removed
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut [Archetype]
+    {
+        &mut self.arch
+    }
+
+    /// get two distinct archetypes mutably, by their IDs
+    pub(crate) fn pair_mut(&mut self, a: usize, b: usize) -> (&mut Archetype, &mut Archetype)
+    {
+        debug_assert_ne!(a, b, "cannot borrow the same archetype twice!");
+
+        if a < b
+        {
+            let (lo, hi) = self.arch.split_at_mut(b);
+
+            (&mut lo[a], &mut hi[0])
+        }
+        else
+        {
+            let (lo, hi) = self.arch.split_at_mut(a);
+
+            (&mut hi[0], &mut lo[b])
+        }
+    }
+}
+
+impl std::fmt::Debug for ArchetypeObservers
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{} observers", self.0.len())
+    }
+}
+
+impl Default for ArchetypeMap
+{
+    fn default() -> Self
+    {
+        Self::with_capacity(0)
+    }
+}
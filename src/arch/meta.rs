@@ -1,7 +1,9 @@
-use std::collections::HashMap;
 use std::alloc::Layout;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
 
-use crate::{ CmpId, CmpMeta, Entity };
+use crate::{ CmpId, CmpMeta, Component, Entity, EntityLocation };
+use crate::hsh::{ IdHashMap, id_hash_map };
 use super::ArchetypeChunk;
 
 /// meta-data about an archetype, this is caclulated once and never altered in
@@ -12,7 +14,10 @@ pub struct ArchetypeMeta
     /// index of this archetype in the `Scene`'s archetype vector
     pub(super) id: usize,
     /// (meta-data, offset) about the components' types stored in this archetype
-    pub(super) cmp: HashMap<CmpId, (CmpMeta, usize)>,
+    pub(super) cmp: IdHashMap<CmpId, (CmpMeta, usize)>,
+    /// IDs of the components' types stored in this archetype, sorted via the
+    /// `Ord` trait on `CmpId`
+    pub(super) ids: Vec<CmpId>,
     /// (cached) max entities that can be stored in a single chunk within
     /// this archetype
     ///
@@ -21,12 +26,15 @@ pub struct ArchetypeMeta
     pub(super) max: usize,
     /// (cached) layout for every chunk allocations for this archetype
     pub(super) layout: Layout,
+    /// the scene's current change tick, shared by every archetype in its
+    /// `ArchetypeMap`
+    pub(super) tick: Arc<AtomicU64>,
 }
 
 impl ArchetypeMeta
 {
     /// create a new archetype meta from a sorted vector of component meta
-    pub(super) fn new(id: usize, types: &Vec<CmpMeta>) -> Self
+    pub(super) fn new(id: usize, types: &Vec<CmpMeta>, tick: Arc<AtomicU64>) -> Self
     {
         // assert types are sorted
         debug_assert!
@@ -37,9 +45,13 @@ impl ArchetypeMeta
             "component meta is unsorted or contains duplicates!"
         );
 
-        // alignment of chunks is that of Entity, because `*self.data.get()` starts
-        // with entity IDs
-        let align = std::mem::align_of::<Entity>();
+        // alignment of chunks is at least that of Entity, because `*self.data.get()`
+        // starts with entity IDs. component offsets are padded relative to the start
+        // of the chunk, so it must also be aligned to the most-aligned component
+        let align = types
+            .iter()
+            .map(|t| t.alignment())
+            .fold(std::mem::align_of::<Entity>(), usize::max);
 
         // size, in bytes, of all components + ID for one entity excluding padding
         let size = std::mem::size_of::<Entity>() + types
@@ -47,6 +59,27 @@ impl ArchetypeMeta
             .fold(0, |acc, n| acc + n.size());
         // max entities that can be stored in this chunk
         let max = ArchetypeChunk::TARGET_SIZE / size;
+
+        // every entity in this chunk must be addressable by an `EntityLocation`
+        debug_assert!(max <= EntityLocation::MAX_INDEX + 1, "too many entities per chunk to be addressed by an `EntityLocation`!");
+
+        // components don't fit in a single chunk, so inserting would corrupt memory
+        if max == 0
+        {
+            let largest = types
+                .iter()
+                .max_by_key(|t| t.size())
+                .unwrap();
+
+            panic!
+            (
+                "components are too large to fit in an archetype chunk({} > {} bytes), largest is `{}`({} bytes)!",
+                size,
+                ArchetypeChunk::TARGET_SIZE,
+                largest.name(),
+                largest.size()
+            );
+        }
         // `alloc`: size, in bytes, of the allocation per chunk. it over-allocates slightly
         // to have space for padding, but ends up roughly equal to `16kb`
         // `meta`: meta info about the components within this archetype
@@ -56,7 +89,7 @@ impl ArchetypeMeta
             // start with entity IDs
             let mut alloc = std::mem::size_of::<Entity>() * max;
             // meta will have exact same size as `types` argument
-            let mut meta = HashMap::with_capacity(types.len());
+            let mut meta = id_hash_map(types.len());
 
             for t in types
             {
@@ -76,7 +109,64 @@ impl ArchetypeMeta
         // layout for a chunk allocation within this archetype
         let layout = Layout::from_size_align(alloc, align).unwrap();
 
+        // sorted component IDs
+        let ids = types
+            .iter()
+            .map(|t| t.id())
+            .collect();
+
         // return the archetype meta...
-        ArchetypeMeta { id, cmp, max, layout }
+        ArchetypeMeta { id, cmp, ids, max, layout, tick }
+    }
+
+    /// does this archetype store the component type `T`?
+    #[inline]
+    pub fn contains<T: Component>(&self) -> bool
+    {
+        self.contains_dyn(T::ID)
+    }
+
+    /// does this archetype store the component type `id`?
+    #[inline]
+    pub fn contains_dyn(&self, id: CmpId) -> bool
+    {
+        self.cmp.contains_key(&id)
+    }
+
+    /// get the (meta-data, offset) of the component type `T` within this
+    /// archetype. panics if `T` isn't stored in this archetype
+    #[inline]
+    pub fn get<T: Component>(&self) -> &(CmpMeta, usize)
+    {
+        self.cmp
+            .get(&T::ID)
+            .unwrap_or_else(|| self.missing(format_args!("`{}`", T::NAME)))
+    }
+
+    /// get the (meta-data, offset) of the component type `id` within this
+    /// archetype. panics if `id` isn't stored in this archetype
+    pub fn get_dyn(&self, id: CmpId) -> &(CmpMeta, usize)
+    {
+        self.cmp
+            .get(&id)
+            .unwrap_or_else(|| self.missing(format_args!("{:?}", id)))
+    }
+
+    /// panics because the component `name` was accessed, but isn't stored in this
+    /// archetype. the message lists those that are, to tell the archetypes apart
+    #[cold]
+    pub(super) fn missing(&self, name: std::fmt::Arguments) -> !
+    {
+        let names = self.ids
+            .iter()
+            .map(|id| format!("`{}`", self.cmp[id].0.name()))
+            .collect::<Vec<_>>();
+
+        panic!
+        (
+            "attempting to access component {} not within this archetype! it contains [{}]",
+            name,
+            names.join(", ")
+        );
     }
 }
\ No newline at end of file
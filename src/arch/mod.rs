@@ -2,43 +2,56 @@
 mod chunk;
 mod meta;
 mod map;
+mod borrow;
 
 pub use self::chunk::*;
 pub use self::meta::*;
 pub use self::map::*;
+pub(crate) use self::borrow::*;
 
 // Archetype
 use std::collections::HashSet;
-use std::rc::Rc;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
 
-use super::{ CmpMeta, Entity, EntityLocation };
+use super::{ CmpId, CmpMeta, Component, Entity, EntityLocation };
 
 // collection of a specific combination of components
 #[derive(Debug)]
 pub struct Archetype
 {
     /// meta-data about this `Archetype`
-    pub(self) meta: Rc<ArchetypeMeta>,
+    pub(self) meta: Arc<ArchetypeMeta>,
     /// all chunks in this archetype. the collection can be expanded but is
-    /// never shrunk, therefore elements are 'pinned' and an index can safely
-    /// reference a chunk
+    /// never shrunk while it holds entities, therefore elements are 'pinned'
+    /// and an index can safely reference a chunk
     pub(self) chunks: Vec<ArchetypeChunk>,
     /// list of chunk indices with free entity slots and zero shared components
     ///
     /// TODO: shared component to free chunk map of type `HashMap<..., Vec<ArchetypeChunkIndex>>
     pub(self) free: HashSet<usize>,
+    /// number of entities across every chunk
+    pub(self) len: usize,
+    /// runtime borrow flags of every column, see `ColumnBorrows`
+    pub(self) borrows: ColumnBorrows,
 }
 
 impl Archetype
 {
-    /// create a new archetype from a sorted vector of component meta
-    pub(crate) fn new(id: usize, types: &Vec<CmpMeta>) -> Self
+    /// create a new archetype from a sorted vector of component meta, stamping
+    /// changes with the shared `tick`
+    pub(crate) fn new(id: usize, types: &Vec<CmpMeta>, tick: Arc<AtomicU64>) -> Self
     {
+        assert!(id < EntityLocation::MAX_ARCHETYPE, "too many archetypes to be addressed by an `EntityLocation`!");
+
         Self
         {
-            meta: Rc::new(ArchetypeMeta::new(id, types)),
+            meta: Arc::new(ArchetypeMeta::new(id, types, tick)),
             chunks: Default::default(),
             free: Default::default(),
+            len: 0,
+            borrows: ColumnBorrows::new(types.len()),
         }
     }
 
@@ -58,6 +71,7 @@ impl Archetype
 
         // increment length
         chunk.len += 1;
+        self.len += 1;
 
         // chunk is full
         if chunk.len == self.meta.max
@@ -67,8 +81,498 @@ impl Archetype
 
         // insert entity ID
         chunk.entities_mut()[index] = e;
+        chunk.mark_added();
 
         // returns location
         EntityLocation::new(archetype, chunk_id, index)
     }
-}
\ No newline at end of file
+
+    /// allocate enough chunks for at least `additional` more entities to be inserted
+    /// into this archetype without allocating
+    pub fn reserve(&mut self, additional: usize)
+    {
+        // free slots within the existing chunks
+        let mut free = self.free
+            .iter()
+            .map(|i| self.meta.max - self.chunks[*i].len)
+            .sum::<usize>();
+
+        while free < additional
+        {
+            ArchetypeChunk::append_to(self);
+
+            free += self.meta.max;
+        }
+    }
+
+    /// number of entities stored in this archetype, across every chunk
+    #[inline]
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    /// is this archetype empty of entities? its chunks may still be allocated
+    #[inline]
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// number of entities that can be stored in this archetype without allocating
+    pub fn capacity(&self) -> usize
+    {
+        self.chunks.len() * self.meta.max
+    }
+
+    /// deallocate every chunk of this archetype if it's empty of entities, in
+    /// which case no `EntityLocation` can reference them. returns whether any
+    /// chunk was deallocated
+    pub fn release_if_empty(&mut self) -> bool
+    {
+        if !self.is_empty() || self.chunks.is_empty()
+        {
+            return false;
+        }
+        self.chunks = Vec::new();
+        self.free = HashSet::new();
+
+        true
+    }
+
+    /// number of chunks in this archetype with room for more entities
+    pub fn free_chunk_count(&self) -> usize
+    {
+        self.free.len()
+    }
+
+    /// ratio of unused entity slots to the total capacity of this archetype,
+    /// from `0.0`(every chunk is full, or there's none) to `1.0`(every chunk is
+    /// empty). high fragmentation after churny gameplay means memory is wasted
+    pub fn fragmentation(&self) -> f32
+    {
+        match self.capacity()
+        {
+            0 => 0.0,
+            cap => (cap - self.len) as f32 / cap as f32,
+        }
+    }
+
+    /// get all the chunks in this archetype
+    #[inline]
+    pub fn chunks(&self) -> &[ArchetypeChunk]
+    {
+        &self.chunks
+    }
+
+    /// get all the chunks in this archetype. it's returned as a slice so that
+    /// chunks can be mutated, but never added or removed
+    #[inline]
+    pub fn chunks_mut(&mut self) -> &mut [ArchetypeChunk]
+    {
+        &mut self.chunks
+    }
+
+    /// writes a component into the slot of the entity at `loc`, without dropping
+    /// the slot's previous contents
+    ///
+    /// # Safety
+    /// `loc` must point to an entity within this archetype whose `T` slot hasn't
+    /// been written to yet(ie. it was just returned by `Archetype::insert`)
+    pub unsafe fn write<T: Component>(&mut self, loc: EntityLocation, cmp: T)
+    {
+        self.debug_check_location(loc);
+
+        // pointer to the start of `T` components...
+        let ptr = self.chunks[loc.chunk()].ptr(self.meta.get::<T>().1) as *mut T;
+
+        // ...then to the entity's own slot
+        ptr.add(loc.index()).write(cmp);
+    }
+
+    /// get the `T` component of the entity at `loc`, or `None` if this archetype
+    /// doesn't store `T` components
+    pub fn get<T: Component>(&self, loc: EntityLocation) -> Option<&T>
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+
+        if !self.meta.contains::<T>()
+        {
+            return None;
+        }
+        self.chunks[loc.chunk()]
+            .components::<T>()
+            .get(loc.index())
+    }
+
+    /// get the `T` component of the entity at `loc`, or `None` if this archetype
+    /// doesn't store `T` components
+    pub fn get_mut<T: Component>(&mut self, loc: EntityLocation) -> Option<&mut T>
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+
+        if !self.meta.contains::<T>()
+        {
+            return None;
+        }
+        self.chunks[loc.chunk()]
+            .components_mut::<T>()
+            .get_mut(loc.index())
+    }
+
+    /// overwrites the `T` component of the entity at `loc`, dropping the previous one.
+    /// returns false, dropping `cmp` instead, if this archetype doesn't store `T` components
+    pub fn set<T: Component>(&mut self, loc: EntityLocation, cmp: T) -> bool
+    {
+        // a stale location would otherwise look like a missing component
+        self.debug_check_location(loc);
+
+        match self.get_mut::<T>(loc)
+        {
+            Some(old) =>
+            {
+                *old = cmp;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// copies the bytes of a component into the slot of the entity at `loc`, without
+    /// dropping the slot's previous contents. panics if `bytes` isn't exactly the size
+    /// of an `id` component
+    ///
+    /// # Safety
+    /// `loc` must point to an entity within this archetype whose `id` slot hasn't
+    /// been written to yet(ie. it was just returned by `Archetype::insert`), and `bytes`
+    /// must be a valid `id` component, which is moved into this archetype
+    pub unsafe fn write_dyn(&mut self, loc: EntityLocation, id: CmpId, bytes: &[u8])
+    {
+        let (meta, _) = self.meta.get_dyn(id);
+
+        assert_eq!(bytes.len(), meta.size(), "component `{}` has the wrong size!", meta.name());
+
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.slot_dyn(loc, id), bytes.len());
+    }
+
+    /// default-constructs the `id` component into the slot of the entity at `loc`,
+    /// without dropping the slot's previous contents. panics if the component type
+    /// has no default function
+    ///
+    /// # Safety
+    /// `loc` must point to an entity within this archetype whose `id` slot hasn't
+    /// been written to yet(ie. it was just returned by `Archetype::insert`)
+    pub unsafe fn write_default_dyn(&mut self, loc: EntityLocation, id: CmpId)
+    {
+        let (meta, _) = self.meta.get_dyn(id);
+        let default = meta
+            .default_fn()
+            .unwrap_or_else(|| panic!("component `{}` has no default function!", meta.name()));
+
+        default(self.slot_dyn(loc, id));
+    }
+
+    /// get the bytes of the `id` component of the entity at `loc`, whose padding is
+    /// uninitialized, see `ArchetypeChunk::components_dyn`
+    pub fn get_dyn(&self, loc: EntityLocation, id: CmpId) -> &[MaybeUninit<u8>]
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+
+        let chunk = &self.chunks[loc.chunk()];
+        let size = self.meta.get_dyn(id).0.size();
+
+        &chunk.components_dyn(id)[loc.index() * size..(loc.index() + 1) * size]
+    }
+
+    /// can the components of this archetype be cloned? that is, do they all
+    /// have a clone function
+    pub fn is_cloneable(&self) -> bool
+    {
+        self.meta.cmp
+            .values()
+            .all(|(meta, _)| meta.clone_fn().is_some())
+    }
+
+    /// clones the components of the entity at `loc` into the slot of the entity at
+    /// `dst_loc`, within `dst`. returns false, without cloning anything, if one of the
+    /// components doesn't have a clone function
+    ///
+    /// `dst` may be this very archetype
+    ///
+    /// # Safety
+    /// `dst_loc` must have just been returned by `dst.insert(...)`, and `dst` must store
+    /// a subset of this archetype's component types
+    pub unsafe fn clone_row(&self, loc: EntityLocation, dst: &Archetype, dst_loc: EntityLocation) -> bool
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+        debug_assert_eq!(dst_loc.archetype(), dst.meta.id, "entity location is within another archetype!");
+
+        // check every component first, so nothing is partially cloned
+        let cloneable = dst.meta.ids
+            .iter()
+            .all(|id| self.meta.get_dyn(*id).0.clone_fn().is_some());
+
+        if !cloneable
+        {
+            return false;
+        }
+        for id in &dst.meta.ids
+        {
+            self.meta.get_dyn(*id).0.try_clone(self.slot_dyn(loc, *id), dst.slot_dyn(dst_loc, *id));
+        }
+        true
+    }
+
+    /// get the IDs of the component types stored in this archetype, sorted via
+    /// the `Ord` trait on `CmpId`
+    #[inline]
+    pub fn types(&self) -> &[CmpId]
+    {
+        &self.meta.ids
+    }
+
+    /// get the meta-data about this archetype
+    #[inline]
+    pub fn meta(&self) -> &ArchetypeMeta
+    {
+        &self.meta
+    }
+
+    /// borrow the columns of the components in `access` that are stored in this archetype,
+    /// mutably or not. panics with the component's name if one of them is already borrowed
+    /// in a conflicting way, without keeping any borrow, see `ColumnBorrows`
+    pub(crate) fn borrow_columns(&self, access: &[(CmpMeta, bool)])
+    {
+        for (n, (meta, mutable)) in access.iter().enumerate()
+        {
+            let i = match self.meta.ids.binary_search(&meta.id())
+            {
+                Ok(i) => i,
+                Err(_) => continue,
+            };
+            if !self.borrows.acquire(i, *mutable)
+            {
+                self.release_columns(&access[..n]);
+
+                match mutable
+                {
+                    true => panic!("component `{}` is already borrowed!", meta.name()),
+                    false => panic!("component `{}` is already borrowed mutably!", meta.name()),
+                }
+            }
+        }
+    }
+
+    /// release the columns borrowed by `Archetype::borrow_columns`
+    pub(crate) fn release_columns(&self, access: &[(CmpMeta, bool)])
+    {
+        for (meta, mutable) in access
+        {
+            if let Ok(i) = self.meta.ids.binary_search(&meta.id())
+            {
+                self.borrows.release(i, *mutable);
+            }
+        }
+    }
+
+    /// get this archetype's index in the `Scene`'s archetype vector, which is
+    /// what `EntityLocation::archetype` refers to
+    #[inline]
+    pub fn id(&self) -> usize
+    {
+        self.meta.id
+    }
+
+    /// panics if this archetype's list of chunks with free slots is out of sync
+    /// with the chunks themselves
+    pub(crate) fn validate(&self)
+    {
+        assert_eq!
+        (
+            self.len,
+            self.chunks.iter().map(ArchetypeChunk::len).sum::<usize>(),
+            "archetype {} miscounts its entities!", self.meta.id
+        );
+        for (i, chunk) in self.chunks.iter().enumerate()
+        {
+            assert!(chunk.len <= self.meta.max, "chunk {} of archetype {} is over capacity!", i, self.meta.id);
+            assert_eq!
+            (
+                chunk.len < self.meta.max,
+                self.free.contains(&i),
+                "chunk {} of archetype {} is wrongly marked as free or full!", i, self.meta.id
+            );
+        }
+    }
+
+    /// removes the entity at `loc` from this archetype, dropping all of its components.
+    /// returns the entity that was moved into `loc` to fill the gap, if any
+    pub(crate) fn remove(&mut self, loc: EntityLocation) -> Option<Entity>
+    {
+        unsafe
+        {
+            // drop every component...
+            self.drop_components(loc, &self.meta.ids);
+
+            // ...then fill the gap
+            self.forget(loc)
+        }
+    }
+
+    /// drops the components `ids` of the entity at `loc` in place, leaving their
+    /// slots uninitialized
+    ///
+    /// # Safety
+    /// the slots must be written to(or the entity removed) immediately after
+    pub(crate) unsafe fn drop_components(&self, loc: EntityLocation, ids: &[CmpId])
+    {
+        for id in ids
+        {
+            if let Some(drop) = self.meta.get_dyn(*id).0.drop_fn()
+            {
+                drop(self.slot_dyn(loc, *id));
+            }
+        }
+    }
+
+    /// moves the components of the entity at `loc` into the slot of the entity at
+    /// `dst_loc`, within `dst`. components that aren't stored in `dst` or that are
+    /// listed in `overwrite` are dropped instead. returns the entity that was moved
+    /// into `loc` to fill the gap, if any
+    ///
+    /// # Safety
+    /// `dst_loc` must have just been returned by `dst.insert(...)`, and every component
+    /// of `dst` that isn't moved must be written immediately after
+    pub(crate) unsafe fn migrate(&mut self, loc: EntityLocation, dst: &mut Archetype, dst_loc: EntityLocation, overwrite: &[CmpId]) -> Option<Entity>
+    {
+        for id in &self.meta.ids
+        {
+            let (meta, _) = self.meta.get_dyn(*id);
+            let src = self.slot_dyn(loc, *id);
+
+            // move component...
+            if dst.meta.cmp.contains_key(id) && !overwrite.contains(id)
+            {
+                std::ptr::copy_nonoverlapping(src, dst.slot_dyn(dst_loc, *id), meta.size());
+            }
+            // ...or drop it
+            else if let Some(drop) = meta.drop_fn()
+            {
+                drop(src);
+            }
+        }
+        // fill the gap
+        self.forget(loc)
+    }
+
+    /// calls the `Component::on_spawn` hook of the components `ids` for the entity at `loc`
+    pub(crate) fn on_spawn(&self, loc: EntityLocation, ids: &[CmpId])
+    {
+        let ent = self.chunks[loc.chunk()].entities()[loc.index()];
+
+        for id in ids
+        {
+            unsafe
+            {
+                (self.meta.get_dyn(*id).0.on_spawn_fn())(self.slot_dyn(loc, *id), ent);
+            }
+        }
+    }
+
+    /// calls the `Component::on_despawn` hook of the components `ids` for the entity at `loc`
+    pub(crate) fn on_despawn(&self, loc: EntityLocation, ids: &[CmpId])
+    {
+        let ent = self.chunks[loc.chunk()].entities()[loc.index()];
+
+        for id in ids
+        {
+            unsafe
+            {
+                (self.meta.get_dyn(*id).0.on_despawn_fn())(self.slot_dyn(loc, *id), ent);
+            }
+        }
+    }
+
+    /// removes the entity at `loc` from this archetype without dropping its components,
+    /// which must have been moved elsewhere(or dropped) beforehand. returns the entity
+    /// that was moved into `loc` to fill the gap, if any
+    unsafe fn forget(&mut self, loc: EntityLocation) -> Option<Entity>
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+
+        let chunk = &mut self.chunks[loc.chunk()];
+        let last = chunk.len - 1;
+
+        // last entity in the chunk fills the gap...
+        let moved = if loc.index() != last
+        {
+            for (meta, offset) in self.meta.cmp.values()
+            {
+                let src = chunk.ptr(offset + last * meta.size());
+                let dst = chunk.ptr(offset + loc.index() * meta.size());
+
+                std::ptr::copy_nonoverlapping(src, dst, meta.size());
+            }
+            let ent = chunk.entities()[last];
+
+            chunk.entities_mut()[loc.index()] = ent;
+
+            Some(ent)
+        }
+        // ...unless it's the one being removed
+        else
+        {
+            None
+        };
+
+        // decrement length
+        chunk.len -= 1;
+        self.len -= 1;
+
+        // chunk has room to spare
+        self.free.insert(loc.chunk());
+
+        moved
+    }
+
+    /// returns a pointer to the `id` component slot of the entity at `loc`
+    pub(crate) fn slot_dyn(&self, loc: EntityLocation, id: CmpId) -> *mut u8
+    {
+        self.debug_check_location(loc);
+
+        let (meta, offset) = self.meta.get_dyn(id);
+
+        self.chunks[loc.chunk()].ptr(offset + loc.index() * meta.size())
+    }
+
+    /// debug-asserts that `loc` points to an entity within this archetype, rather
+    /// than past the end of a chunk, where writing would corrupt its free capacity
+    #[inline]
+    fn debug_check_location(&self, loc: EntityLocation)
+    {
+        debug_assert_eq!(loc.archetype(), self.meta.id, "entity location is within another archetype!");
+        debug_assert!(loc.chunk() < self.chunks.len(), "entity location is past the last chunk!");
+        debug_assert!(loc.index() < self.chunks[loc.chunk()].len(), "entity location is past the end of its chunk!");
+    }
+}
+impl<'a> IntoIterator for &'a Archetype
+{
+    type Item = &'a ArchetypeChunk;
+    type IntoIter = std::slice::Iter<'a, ArchetypeChunk>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        self.chunks.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Archetype
+{
+    type Item = &'a mut ArchetypeChunk;
+    type IntoIter = std::slice::IterMut<'a, ArchetypeChunk>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        self.chunks.iter_mut()
+    }
+}
@@ -1,67 +1,223 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::{ OnceLock, RwLock };
+
+use crate::{ Archetype, Entity, EntityLocation };
+#[cfg(feature = "serde")]
+use crate::{ DeserializeFn, SerializeFn };
+
 /// a statically-defined, non-shared component
 ///
 /// this trait should absolutely *not* be implemented manually,
-/// and must rather use `#[derive(Component)]`
+/// and must rather use `#[derive(Component)]`(or `impl_component!`
+/// for types that can't be annotated)
 pub trait Component: Sync + Send + Sized + 'static
 {
     /// unique identifier for this type of component
     const ID: CmpId;
 
+    /// human-readable name of this type of component
+    const NAME: &'static str;
+
     /// meta-data about this component type
-    const META: CmpMeta = CmpMeta
-    {
-        id: Self::ID,
-        size: std::mem::size_of::<Self>() as u32,
-        align: std::mem::align_of::<Self>() as u32,
-        drop: drop_ptr::<Self>
-    };
+    const META: CmpMeta = CmpMeta::of::<Self>();
+
+    /// called right after this component is inserted into the scene, whether
+    /// it's through `Scene::spawn` or `Scene::add`
+    ///
+    /// see `CmpHooks` to override it
+    #[allow(unused_variables)]
+    fn on_spawn(&mut self, ent: Entity) { }
+
+    /// called right before this component is dropped from the scene, whether
+    /// it's through `Scene::despawn` or `Scene::remove`, or overwritten by
+    /// `Scene::add`
+    ///
+    /// see `CmpHooks` to override it
+    #[allow(unused_variables)]
+    fn on_despawn(&mut self, ent: Entity) { }
+}
+
+/// a `Component` that queries can borrow mutably, which is every component but the
+/// hierarchy's `Parent` and `Children`: the scene keeps those consistent, and
+/// swapping them between entities wouldn't. implemented alongside `Component` by
+/// `#[derive(Component)]` and `impl_component!`
+pub trait MutComponent: Component { }
+
+/// lifecycle hooks of a `Component`
+///
+/// since `Component` is implemented through `#[derive(Component)]`, its hooks
+/// are overriden by implementing this trait and adding `#[component(hooks)]`
+/// to the derive
+pub trait CmpHooks
+{
+    /// see `Component::on_spawn`
+    #[allow(unused_variables)]
+    fn on_spawn(&mut self, ent: Entity) { }
+
+    /// see `Component::on_despawn`
+    #[allow(unused_variables)]
+    fn on_despawn(&mut self, ent: Entity) { }
 }
 
 /// a tuple of non-duplicate, arbitrarily ordered `Component` types
 /// and `SharedComponent` types
-pub trait CmpSet
+///
+/// tuples are sets up to 26 elements, and sets can be nested to go past
+/// that: `((A, B, ..., Z), (AA, AB))` is flattened into a single set. see
+/// `DynCmpSet` for sets built at runtime
+pub trait CmpSet: Sized + 'static
 {
+    /// compile-time tree of the component IDs in this set, used to reject
+    /// duplicate component types before the program even runs
+    const IDS: CmpIds;
+
     /// get the component type IDs in this component set, sorted via the `Ord`
-    /// trait on `CmpId`.
+    /// trait on `CmpId`. used when spawning or adding this set, see `StaticCmpSet::types`
+    fn instance_types(&self) -> &[CmpId];
+
+    /// get the meta-data of the component types in this set, sorted via the `Ord`
+    /// trait on `CmpMeta`, see `CmpSet::instance_types`
+    fn instance_metas(&self) -> &[CmpMeta];
+
+    /// move the components in this set into the slots of a freshly inserted
+    /// entity
     ///
-    /// takes a `FnOnce` with the actual IDs as parameter because the
-    /// following isn't feasible in Rust:
-    /// ```rust
-    /// // we don't want to copy the &[CmpId] slice everytime we get the
-    /// // components in this set
-    /// fn types(&self) -> &[CmpId]
-    /// {
-    ///     &[A::ID, B::ID] // err: cannot return borrow to temporary value
-    /// }
-    /// ```
-    /// for static bundles, this makes zero difference. for dynamic bundles,
-    /// however, they can cache a `Vec<CmpId>` in self
+    /// # Safety
+    /// `loc` must have just been returned by `arch.insert(...)`, and `arch` must
+    /// contain exactly the component types in this set. the slots are written
+    /// without dropping their previous(uninitialized) contents
+    unsafe fn insert(self, arch: &mut Archetype, loc: EntityLocation);
+}
+
+/// a `CmpSet` whose components are known from its type alone, which is every set
+/// but `DynCmpSet`. only those can be nested in other sets, or name the components
+/// to remove from an entity or the archetype to look up
+pub trait StaticCmpSet: CmpSet
+{
+    /// append the meta-data of every component type in this set to `out`, in
+    /// no particular order. only used to build the cache behind `StaticCmpSet::metas`
+    fn collect_metas(out: &mut Vec<CmpMeta>);
+
+    /// get the component type IDs in this component set, sorted via the `Ord`
+    /// trait on `CmpId`.
     ///
-    /// the `FnOnce` returns some type T for convenience in internal implementations
-    fn types<T>(&self, f: impl FnOnce(&[CmpId]) -> T) -> T;
+    /// the list is built once per set type then cached for the rest of the
+    /// program, so spawning doesn't allocate nor sort
+    fn types() -> &'static [CmpId]
+    {
+        &SetCache::get::<Self>().types
+    }
+
+    /// get the meta-data of the component types in this set, sorted via the `Ord`
+    /// trait on `CmpMeta`. cached like `StaticCmpSet::types`
+    fn metas() -> &'static [CmpMeta]
+    {
+        &SetCache::get::<Self>().metas
+    }
+}
+
+/// sorted component IDs and meta-data of a `CmpSet` type, built on first use
+/// and kept for the rest of the program
+struct SetCache
+{
+    /// see `StaticCmpSet::types`
+    types: Vec<CmpId>,
+    /// see `StaticCmpSet::metas`
+    metas: Vec<CmpMeta>,
+}
+
+impl SetCache
+{
+    /// get the cache of `S`, building it if it's the first time. generic
+    /// functions can't have their own statics, so sets are keyed by type
+    fn get<S: StaticCmpSet>() -> &'static SetCache
+    {
+        static CACHE: OnceLock<RwLock<HashMap<TypeId, &'static SetCache>>> = OnceLock::new();
+
+        let cache = CACHE.get_or_init(Default::default);
+
+        // already built
+        if let Some(set) = cache.read().unwrap().get(&TypeId::of::<S>())
+        {
+            return set;
+        }
+
+        let mut metas = Vec::new();
+
+        S::collect_metas(&mut metas);
+        metas.sort();
 
-    /// get a copy of the meta inside this component set, sorted via the `Ord`
-    /// trait on `CmpMeta`
-    fn metas(&self) -> Vec<CmpMeta>;
+        let types = metas
+            .iter()
+            .map(CmpMeta::id)
+            .collect();
+
+        // another thread may have built it in the meantime
+        let mut cache = cache
+            .write()
+            .unwrap();
+        cache
+            .entry(TypeId::of::<S>())
+            .or_insert_with(|| Box::leak(Box::new(SetCache { types, metas })));
+
+        cache[&TypeId::of::<S>()]
+    }
+}
+
+/// compile-time tree of component IDs, as found in `CmpSet::IDS`
+///
+/// nested sets keep their nesting here, since flattening them would need
+/// arrays of generic length
+#[derive(Debug, Copy, Clone)]
+pub enum CmpIds
+{
+    /// a single component type
+    One(CmpId),
+    /// a set of component types, which may themselves be sets
+    Many(&'static [CmpIds]),
 }
 
 /// meta-data about a component type, rust-compiled or dynamic
 #[derive(Debug, Clone)]
 pub struct CmpMeta
 {
+    /// human-readable name of the type, for diagnostics
+    name: &'static str,
     /// component ID generated via the `Component` derive
     id: CmpId,
     /// size, in bytes, of the type
     size: u32,
     /// alignment, in bytes, of the type
     align: u32,
-    /// destructor function ptr
-    drop: DropFn,
+    /// destructor function ptr, or `None` if the type doesn't need to be dropped
+    drop: Option<DropFn>,
+    /// clone function ptr, or `None` if the type isn't known to be `Clone`
+    clone: Option<CloneFn>,
+    /// default-construction function ptr, or `None` if the type isn't known
+    /// to be `Default`
+    default: Option<DefaultFn>,
+    /// equality function ptr, or `None` if the type isn't known to be `PartialEq`
+    eq: Option<EqFn>,
+    /// serialize function ptr, or `None` if the type isn't known to be `Serialize`
+    #[cfg(feature = "serde")]
+    serialize: Option<SerializeFn>,
+    /// deserialize function ptr, or `None` if the type isn't known to be `Deserialize`
+    #[cfg(feature = "serde")]
+    deserialize: Option<DeserializeFn>,
+    /// `Component::on_spawn` function ptr
+    on_spawn: HookFn,
+    /// `Component::on_despawn` function ptr
+    on_despawn: HookFn,
 }
 
 /// unique identifer for a component type, rust-compiled or dynamic
 ///
-/// new instances should be obtained from `Component::id` exclusively
+/// new instances should be obtained from `Component::id` exclusively. the IDs
+/// of rust-compiled types are hashes of their path and location(see `CmpId::of_path`),
+/// so their ordering, and that of the archetype keys sorted by it, is the same
+/// across builds of the same sources regardless of declaration order
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct CmpId(u64);
 
@@ -70,6 +226,29 @@ pub struct CmpId(u64);
 /// dropped.
 pub type DropFn = unsafe fn(*mut u8);
 
+/// function pointer to clone a certain type, given a void ptr to the
+/// source and a void ptr to the(uninitialized) destination
+pub type CloneFn = unsafe fn(*const u8, *mut u8);
+
+/// function pointer to default-construct a certain type, given a void ptr
+/// to the(uninitialized) destination
+pub type DefaultFn = unsafe fn(*mut u8);
+
+/// function pointer to compare two values of a certain type, given a void
+/// ptr to each
+pub type EqFn = unsafe fn(*const u8, *const u8) -> bool;
+
+/// function pointer to a certain type's lifecycle hook, given a void
+/// ptr and the entity which owns it
+pub type HookFn = unsafe fn(*mut u8, Entity);
+
+/// next dynamic component identifier(thread-safe). dynamic IDs have their high bit
+/// set, so they never conflict with those of the `#[derive(Component)]`
+static DYN_CURSOR: AtomicU64 = AtomicU64::new(CmpId::DYNAMIC);
+
+/// a lifecycle hook that does nothing, for dynamic component types
+unsafe fn hook_none(_: *mut u8, _: Entity) { }
+
 /// drops a certain type given a void ptr. used in the `Component::META`
 /// constant, as it is a `DropFn` type
 #[allow(dead_code)]
@@ -78,11 +257,393 @@ unsafe fn drop_ptr<T>(ptr: *mut u8)
     ptr.cast::<T>().drop_in_place()
 }
 
+/// a lone component is the simplest component set
+impl<T: Component> CmpSet for T
+{
+    const IDS: CmpIds = CmpIds::One(T::ID);
+
+    fn instance_types(&self) -> &[CmpId]
+    {
+        Self::types()
+    }
+
+    fn instance_metas(&self) -> &[CmpMeta]
+    {
+        Self::metas()
+    }
+
+    unsafe fn insert(self, arch: &mut Archetype, loc: EntityLocation)
+    {
+        arch.write(loc, self)
+    }
+}
+
+impl<T: Component> StaticCmpSet for T
+{
+    fn collect_metas(out: &mut Vec<CmpMeta>)
+    {
+        out.push(T::META);
+    }
+
+    // lone components need neither sorting nor the cache
+    fn types() -> &'static [CmpId]
+    {
+        const { &[T::ID] }
+    }
+
+    fn metas() -> &'static [CmpMeta]
+    {
+        const { &[T::META] }
+    }
+}
+
+/// a component set built at runtime, ie. by an editor or a loader, which can be
+/// passed to `Scene::spawn` or `Scene::add` like any tuple. it isn't a
+/// `StaticCmpSet` though, so it can't be nested in another set
+///
+/// components are moved into the set as bytes. they're moved again into the
+/// scene when it's spawned, or dropped with the set otherwise
+#[derive(Debug, Default)]
+pub struct DynCmpSet
+{
+    /// IDs of the components, sorted
+    types: Vec<CmpId>,
+    /// meta-data of the components, in the same order
+    metas: Vec<CmpMeta>,
+    /// bytes of the components, in the same order
+    bytes: Vec<Box<[u8]>>,
+}
+
+impl DynCmpSet
+{
+    /// create a new, empty component set
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// move a component into this set
+    pub fn push<T: Component>(&mut self, cmp: T) -> &mut Self
+    {
+        let cmp = std::mem::ManuallyDrop::new(cmp);
+        let bytes = unsafe
+        {
+            std::slice::from_raw_parts(&*cmp as *const T as *const u8, std::mem::size_of::<T>())
+        };
+
+        // SAFETY: the value is forgotten, and the bytes now own it
+        unsafe { self.push_raw(T::META, bytes) }
+    }
+
+    /// move a component into this set, given its meta-data and bytes. panics if
+    /// `bytes` isn't exactly the size of a `meta` component
+    ///
+    /// # Safety
+    /// `bytes` must be a valid `meta` component, which is moved into this set so
+    /// it must not be dropped by the caller
+    pub unsafe fn push_raw(&mut self, meta: CmpMeta, bytes: &[u8]) -> &mut Self
+    {
+        assert_eq!(bytes.len(), meta.size(), "component `{}` has the wrong size!", meta.name());
+
+        // keep sorted
+        let i = self.types.partition_point(|id| *id <= meta.id());
+
+        self.types.insert(i, meta.id());
+        self.metas.insert(i, meta);
+        self.bytes.insert(i, Box::from(bytes));
+        self
+    }
+
+    /// number of components in this set
+    pub fn len(&self) -> usize
+    {
+        self.types.len()
+    }
+
+    /// is this set empty of components?
+    pub fn is_empty(&self) -> bool
+    {
+        self.types.is_empty()
+    }
+}
+
+impl CmpSet for DynCmpSet
+{
+    /// components are only known at runtime
+    const IDS: CmpIds = CmpIds::Many(&[]);
+
+    fn instance_types(&self) -> &[CmpId]
+    {
+        &self.types
+    }
+
+    fn instance_metas(&self) -> &[CmpMeta]
+    {
+        &self.metas
+    }
+
+    unsafe fn insert(mut self, arch: &mut Archetype, loc: EntityLocation)
+    {
+        for (id, bytes) in self.types.iter().zip(&self.bytes)
+        {
+            arch.write_dyn(loc, *id, bytes);
+        }
+        // moved into the archetype
+        self.bytes.clear();
+    }
+}
+
+impl Drop for DynCmpSet
+{
+    fn drop(&mut self)
+    {
+        for (meta, bytes) in self.metas.iter().zip(&self.bytes)
+        {
+            let drop_fn = match meta.drop_fn()
+            {
+                Some(drop_fn) => drop_fn,
+                None => continue,
+            };
+
+            // the bytes aren't aligned, so move them to a temporary first
+            let layout = std::alloc::Layout::from_size_align(meta.size(), meta.alignment()).unwrap();
+            unsafe
+            {
+                let buf = match layout.size()
+                {
+                    0 => layout.align() as *mut u8,
+                    _ => std::alloc::alloc(layout),
+                };
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, layout.size());
+                drop_fn(buf);
+
+                if layout.size() > 0
+                {
+                    std::alloc::dealloc(buf, layout);
+                }
+            }
+        }
+    }
+}
+
+/// clones a certain type given a void ptr to the source and destination. used in
+/// `CmpMeta::with_clone`, as it is a `CloneFn` type
+///
+/// # Safety
+/// `src` must point to a valid `T`, and `dst` to a properly aligned, uninitialized
+/// slot for one
+pub unsafe fn clone_ptr<T: Clone>(src: *const u8, dst: *mut u8)
+{
+    dst.cast::<T>().write((*src.cast::<T>()).clone())
+}
+
+/// default-constructs a certain type given a void ptr to the destination. used in
+/// `CmpMeta::with_default`, as it is a `DefaultFn` type
+///
+/// # Safety
+/// `dst` must point to a properly aligned, uninitialized slot for a `T`
+pub unsafe fn default_ptr<T: Default>(dst: *mut u8)
+{
+    dst.cast::<T>().write(T::default())
+}
+
+/// compares two values of a certain type given a void ptr to each. used in
+/// `CmpMeta::with_eq`, as it is an `EqFn` type
+///
+/// # Safety
+/// `a` and `b` must both point to a valid `T`
+pub unsafe fn eq_ptr<T: PartialEq>(a: *const u8, b: *const u8) -> bool
+{
+    *a.cast::<T>() == *b.cast::<T>()
+}
+
+/// defines marker components, which are unit structs stored in zero bytes
+/// per entity: `tags! { Player, pub Dead, Selected }`
+#[macro_export]
+macro_rules! tags
+{
+    ($($(#[$attr:meta])* $vis:vis $name:ident),* $(,)?) =>
+    {
+        $(
+            $(#[$attr])*
+            #[derive($crate::Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+            #[component(clone, default, eq)]
+            $vis struct $name;
+        )*
+    };
+}
+
+/// adds the serde functions of `$ty` to the `$meta` expression, used by
+/// `#[component(serde)]`
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cmp_serde
+{
+    ($ty:ty, $meta:expr) =>
+    {
+        $meta.with_serde($crate::serialize_ptr::<$ty>, $crate::deserialize_ptr::<$ty>)
+    };
+}
+
+/// see the other `__cmp_serde`
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cmp_serde
+{
+    ($ty:ty, $meta:expr) =>
+    {
+        compile_error!("`#[component(serde)]` requires the `serde` feature of ezgame")
+    };
+}
+
+/// submits the meta-data of `$ty` to the global `Registry`, used by
+/// `#[derive(Component)]` and `impl_component!` since the `inventory` crate
+/// is an optional dependency of ezgame
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cmp_register
+{
+    ($ty:ty) =>
+    {
+        $crate::__inventory::submit!
+        {
+            $crate::CmpEntry(<$ty as $crate::Component>::META)
+        }
+    };
+}
+
+/// see the other `__cmp_register`
+#[cfg(not(feature = "inventory"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cmp_register
+{
+    ($ty:ty) => { };
+}
+
+/// calls `Component::on_spawn` given a void ptr. used in the `Component::META`
+/// constant, as it is a `HookFn` type
+unsafe fn on_spawn_ptr<T: Component>(ptr: *mut u8, ent: Entity)
+{
+    (*ptr.cast::<T>()).on_spawn(ent)
+}
+
+/// calls `Component::on_despawn` given a void ptr. used in the `Component::META`
+/// constant, as it is a `HookFn` type
+unsafe fn on_despawn_ptr<T: Component>(ptr: *mut u8, ent: Entity)
+{
+    (*ptr.cast::<T>()).on_despawn(ent)
+}
+
+/// implements `CmpSet` for a tuple of `StaticCmpSet`s, whose components are flattened
+/// into a single set
+macro_rules! impl_cmp_set
+{
+    ($($name:ident),*) =>
+    {
+        #[allow(non_snake_case, unused_mut, unused_variables)]
+        impl<$($name: StaticCmpSet),*> CmpSet for ($($name,)*)
+        {
+            const IDS: CmpIds = CmpIds::Many(&[$($name::IDS),*]);
+
+            fn instance_types(&self) -> &[CmpId]
+            {
+                Self::types()
+            }
+
+            fn instance_metas(&self) -> &[CmpMeta]
+            {
+                Self::metas()
+            }
+
+            unsafe fn insert(self, arch: &mut Archetype, loc: EntityLocation)
+            {
+                let ($($name,)*) = self;
+
+                $($name.insert(arch, loc);)*
+            }
+        }
+
+        #[allow(unused_mut, unused_variables)]
+        impl<$($name: StaticCmpSet),*> StaticCmpSet for ($($name,)*)
+        {
+            fn collect_metas(out: &mut Vec<CmpMeta>)
+            {
+                $($name::collect_metas(out);)*
+            }
+        }
+    };
+}
+
+/// implements `CmpSet` for every tuple up to the arity given
+macro_rules! impl_cmp_sets
+{
+    () =>
+    {
+        impl_cmp_set!();
+    };
+    ($head:ident $(, $tail:ident)*) =>
+    {
+        impl_cmp_set!($head $(, $tail)*);
+        impl_cmp_sets!($($tail),*);
+    };
+}
+
+impl_cmp_sets!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z);
+
 impl CmpId
 {
+    /// the bit set in every dynamic component ID
+    const DYNAMIC: u64 = 1 << 63;
+
+    /// the bit set in the ID of every component type implemented within this crate,
+    /// so they never collide with the IDs handed out by `#[derive(Component)]`
+    pub(crate) const BUILTIN: u64 = 1 << 62;
+
+    /// bits left for the IDs of `#[derive(Component)]`, clear of the dynamic and
+    /// builtin bits
+    const STATIC_MASK: u64 = u64::MAX >> 2;
+
+    /// is this the ID of a component type registered at runtime, via
+    /// `CmpMeta::new_dynamic`?
+    #[inline]
+    pub fn is_dynamic(&self) -> bool
+    {
+        self.0 & Self::DYNAMIC != 0
+    }
+
+    /// creates the ID of a Rust component type from a string unique to it. the
+    /// derive uses its full path and the location of its name, ie.
+    /// `"game::Pos@12:8"`, since types local to different functions can share a
+    /// path. see `impl_component!` for the types it implements
+    ///
+    /// the path is hashed(FNV-1a), so the IDs of types from different crates
+    /// share one keyspace and are stable across builds, unlike a counter within
+    /// each crate's macro expansion
+    pub const fn of_path(path: &str) -> Self
+    {
+        let bytes = path.as_bytes();
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut i = 0;
+
+        while i < bytes.len()
+        {
+            hash ^= bytes[i] as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            i += 1;
+        }
+        Self(hash & Self::STATIC_MASK)
+    }
+
     /// creates a new component ID instance from its inner u64. this should
-    /// only be called by the `#[derive(Component)]` implementation, hence why
-    /// it's unsafe.
+    /// only be called for component types implemented within this crate, hence
+    /// why it's unsafe.
+    ///
+    /// # Safety
+    /// `n` must not be shared with any other component type
     #[allow(dead_code)]
     pub const unsafe fn from_u64(n: u64) -> Self
     {
@@ -92,6 +653,134 @@ impl CmpId
 
 impl CmpMeta
 {
+    /// find a component type that's listed more than once in `metas`, which
+    /// MUST be sorted via its `Ord` trait
+    pub fn find_duplicate(metas: &[CmpMeta]) -> Option<&CmpMeta>
+    {
+        metas
+            .windows(2)
+            .find(|n| n[0] == n[1])
+            .map(|n| &n[0])
+    }
+
+    /// create the meta-data of a rust-compiled component type. this is the
+    /// default `Component::META`
+    pub const fn of<T: Component>() -> CmpMeta
+    {
+        CmpMeta
+        {
+            name: T::NAME,
+            id: T::ID,
+            size: std::mem::size_of::<T>() as u32,
+            align: std::mem::align_of::<T>() as u32,
+            drop: if std::mem::needs_drop::<T>() { Some(drop_ptr::<T>) } else { None },
+            clone: None,
+            default: None,
+            eq: None,
+            #[cfg(feature = "serde")]
+            serialize: None,
+            #[cfg(feature = "serde")]
+            deserialize: None,
+            on_spawn: on_spawn_ptr::<T>,
+            on_despawn: on_despawn_ptr::<T>,
+        }
+    }
+
+    /// see `CmpMeta::of`
+    ///
+    /// also stores `T`'s clone function, so that type-erased code can duplicate
+    /// its components. used by `#[component(clone)]`
+    pub const fn of_clone<T: Component + Clone>() -> CmpMeta
+    {
+        CmpMeta::of::<T>().with_clone(clone_ptr::<T>)
+    }
+
+    /// see `CmpMeta::of`
+    ///
+    /// also stores `T`'s default function, so that type-erased code can create
+    /// its components. used by `#[component(default)]`
+    pub const fn of_default<T: Component + Default>() -> CmpMeta
+    {
+        CmpMeta::of::<T>().with_default(default_ptr::<T>)
+    }
+
+    /// see `CmpMeta::of`
+    ///
+    /// also stores `T`'s equality function, so that type-erased code can compare
+    /// its components. used by `#[component(eq)]`
+    pub const fn of_eq<T: Component + PartialEq>() -> CmpMeta
+    {
+        CmpMeta::of::<T>().with_eq(eq_ptr::<T>)
+    }
+
+    /// overwrite the clone function of this component type
+    pub const fn with_clone(mut self, clone: CloneFn) -> CmpMeta
+    {
+        self.clone = Some(clone);
+        self
+    }
+
+    /// overwrite the default function of this component type
+    pub const fn with_default(mut self, default: DefaultFn) -> CmpMeta
+    {
+        self.default = Some(default);
+        self
+    }
+
+    /// overwrite the equality function of this component type
+    pub const fn with_eq(mut self, eq: EqFn) -> CmpMeta
+    {
+        self.eq = Some(eq);
+        self
+    }
+
+    /// overwrite the serialize and deserialize functions of this component type
+    #[cfg(feature = "serde")]
+    pub const fn with_serde(mut self, serialize: SerializeFn, deserialize: DeserializeFn) -> CmpMeta
+    {
+        self.serialize = Some(serialize);
+        self.deserialize = Some(deserialize);
+        self
+    }
+
+    /// create the meta-data of a component type defined at runtime(ie. by a
+    /// scripting language), allocating it a new unique `CmpId`.
+    ///
+    /// its components are treated as plain bytes by the scene, and `drop`, if
+    /// any, is called on them before they're removed. like rust components, they
+    /// must be safe to send to and share with other threads, since scenes and
+    /// parallel systems are. the name is leaked, as component types live for the
+    /// whole program
+    pub fn new_dynamic(name: &str, size: u32, align: u32, drop: Option<DropFn>) -> CmpMeta
+    {
+        debug_assert!(align.is_power_of_two(), "component alignment must be a power of two!");
+
+        CmpMeta
+        {
+            name: Box::leak(name.into()),
+            id: CmpId(DYN_CURSOR.fetch_add(1, Ordering::Relaxed)),
+            size,
+            align,
+            drop,
+            clone: None,
+            default: None,
+            eq: None,
+            #[cfg(feature = "serde")]
+            serialize: None,
+            #[cfg(feature = "serde")]
+            deserialize: None,
+            on_spawn: hook_none,
+            on_despawn: hook_none,
+        }
+    }
+
+    /// get this component type's human-readable name
+    #[inline]
+    pub fn name(&self) -> &'static str
+    {
+        self.name
+    }
+
     /// get this component type's unique identifier
     #[inline]
     pub fn id(&self) -> CmpId
@@ -126,13 +815,201 @@ impl CmpMeta
     {
         self.align as usize
     }
+
+    /// get this component type's destructor, or `None` if it doesn't need
+    /// to be dropped
+    #[inline]
+    pub fn drop_fn(&self) -> Option<DropFn>
+    {
+        self.drop
+    }
+
+    /// get this component type's clone function, or `None` if it isn't known
+    /// to be `Clone`
+    #[inline]
+    pub fn clone_fn(&self) -> Option<CloneFn>
+    {
+        self.clone
+    }
+
+    /// clone a component of this type from `src` into `dst`, returning false
+    /// and leaving `dst` untouched if this type has no clone function
+    ///
+    /// # Safety
+    /// `src` must point to a valid component of this type, and `dst` to a
+    /// properly aligned, uninitialized slot for one
+    pub unsafe fn try_clone(&self, src: *const u8, dst: *mut u8) -> bool
+    {
+        match self.clone
+        {
+            Some(clone) =>
+            {
+                clone(src, dst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// get this component type's default function, or `None` if it isn't known
+    /// to be `Default`
+    #[inline]
+    pub fn default_fn(&self) -> Option<DefaultFn>
+    {
+        self.default
+    }
+
+    /// get this component type's equality function, or `None` if it isn't
+    /// known to be `PartialEq`
+    #[inline]
+    pub fn eq_fn(&self) -> Option<EqFn>
+    {
+        self.eq
+    }
+
+    /// compare two components of this type, returning `None` if this type has
+    /// no equality function
+    ///
+    /// # Safety
+    /// `a` and `b` must both point to a valid component of this type
+    pub unsafe fn values_eq(&self, a: *const u8, b: *const u8) -> Option<bool>
+    {
+        self.eq.map(|eq| eq(a, b))
+    }
+
+    /// get this component type's serialize function, or `None` if it isn't
+    /// known to be `Serialize`
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn serialize_fn(&self) -> Option<SerializeFn>
+    {
+        self.serialize
+    }
+
+    /// get this component type's deserialize function, or `None` if it isn't
+    /// known to be `Deserialize`
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn deserialize_fn(&self) -> Option<DeserializeFn>
+    {
+        self.deserialize
+    }
+
+    /// get this component type's `Component::on_spawn` hook
+    #[inline]
+    pub fn on_spawn_fn(&self) -> HookFn
+    {
+        self.on_spawn
+    }
+
+    /// get this component type's `Component::on_despawn` hook
+    #[inline]
+    pub fn on_despawn_fn(&self) -> HookFn
+    {
+        self.on_despawn
+    }
+}
+
+impl CmpIds
+{
+    /// number of component types in this tree, counting duplicates
+    pub const fn len(&self) -> usize
+    {
+        match self
+        {
+            CmpIds::One(_) => 1,
+            CmpIds::Many(ids) =>
+            {
+                let mut len = 0;
+                let mut i = 0;
+
+                while i < ids.len()
+                {
+                    len += ids[i].len();
+                    i += 1;
+                }
+                len
+            }
+        }
+    }
+
+    /// is this tree empty?
+    pub const fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// get the `n`th component ID of this tree, in depth-first order
+    pub const fn nth(&self, mut n: usize) -> Option<CmpId>
+    {
+        match self
+        {
+            CmpIds::One(id) if n == 0 => Some(*id),
+            CmpIds::One(_) => None,
+            CmpIds::Many(ids) =>
+            {
+                let mut i = 0;
+
+                while i < ids.len()
+                {
+                    let len = ids[i].len();
+
+                    if n < len
+                    {
+                        return ids[i].nth(n);
+                    }
+                    n -= len;
+                    i += 1;
+                }
+                None
+            }
+        }
+    }
+
+    /// flatten this tree into a vector of component IDs, sorted via the `Ord`
+    /// trait on `CmpId`. see `StaticCmpSet::types` for a cached version
+    pub fn sorted(&self) -> Vec<CmpId>
+    {
+        let mut ids = (0..self.len())
+            .filter_map(|n| self.nth(n))
+            .collect::<Vec<_>>();
+
+        ids.sort();
+        ids
+    }
+
+    /// does this tree contain the same component ID more than once?
+    pub const fn has_duplicates(&self) -> bool
+    {
+        let len = self.len();
+        let mut i = 0;
+
+        while i < len
+        {
+            let mut j = i + 1;
+
+            while j < len
+            {
+                if let (Some(a), Some(b)) = (self.nth(i), self.nth(j))
+                {
+                    if a.0 == b.0
+                    {
+                        return true;
+                    }
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        false
+    }
 }
 
 impl PartialOrd for CmpMeta
 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering>
     {
-        self.id.partial_cmp(&other.id)
+        Some(self.cmp(other))
     }
 }
 
@@ -0,0 +1,185 @@
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// resource recording how long every system took over the last few frames, fed
+/// by `Schedule::run` while it's enabled. systems that run more than once in a
+/// frame, ie. in a fixed stage, are recorded once with their total duration
+/// ```ignore
+/// scene.insert_resource(Diagnostics::new());
+/// // ...some frames later
+/// println!("{}", scene.resource::<Diagnostics>().unwrap().report());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Diagnostics
+{
+    /// is `Schedule::run` recording timings?
+    enabled: bool,
+    /// number of frames kept per system
+    frames: usize,
+    /// timings of every system recorded so far, in the order they first ran
+    systems: Vec<SystemTimings>,
+}
+
+/// durations of a single system over the last few frames, see `Diagnostics`
+#[derive(Debug, Clone)]
+pub struct SystemTimings
+{
+    /// name of the system, see `System::name`
+    name: String,
+    /// durations, oldest first
+    samples: VecDeque<Duration>,
+}
+
+impl Diagnostics
+{
+    /// default number of frames kept per system
+    pub const FRAMES: usize = 120;
+
+    /// create new, enabled diagnostics keeping the last `Diagnostics::FRAMES`
+    /// frames of every system
+    pub fn new() -> Self
+    {
+        Self::with_frames(Self::FRAMES)
+    }
+
+    /// create new, enabled diagnostics keeping the last `frames` frames of every
+    /// system. panics if `frames` is zero
+    pub fn with_frames(frames: usize) -> Self
+    {
+        assert!(frames > 0, "diagnostics must keep at least one frame!");
+
+        Self { enabled: true, frames, systems: Vec::new() }
+    }
+
+    /// start or stop recording timings, without losing those recorded so far
+    pub fn set_enabled(&mut self, enabled: bool)
+    {
+        self.enabled = enabled;
+    }
+
+    /// is `Schedule::run` recording timings?
+    #[inline]
+    pub fn is_enabled(&self) -> bool
+    {
+        self.enabled
+    }
+
+    /// record how long a system took for one frame, dropping its oldest sample if
+    /// there are already too many
+    pub fn record(&mut self, name: &str, duration: Duration)
+    {
+        let i = match self.systems.iter().position(|sys| sys.name == name)
+        {
+            Some(i) => i,
+            None =>
+            {
+                self.systems.push(SystemTimings { name: name.to_owned(), samples: VecDeque::with_capacity(self.frames) });
+                self.systems.len() - 1
+            }
+        };
+        let samples = &mut self.systems[i].samples;
+
+        if samples.len() == self.frames
+        {
+            samples.pop_front();
+        }
+        samples.push_back(duration);
+    }
+
+    /// get the timings of a system, or `None` if it never ran while recording
+    pub fn get(&self, name: &str) -> Option<&SystemTimings>
+    {
+        self.systems
+            .iter()
+            .find(|sys| sys.name == name)
+    }
+
+    /// iterate the timings of every system, in the order they first ran
+    pub fn iter(&self) -> impl Iterator<Item = &SystemTimings>
+    {
+        self.systems.iter()
+    }
+
+    /// forget every timing recorded so far
+    pub fn clear(&mut self)
+    {
+        self.systems.clear();
+    }
+
+    /// format the timings of every system as a table, slowest on average first
+    pub fn report(&self) -> String
+    {
+        const HEADER: &str = "system";
+
+        let mut systems = self.systems.iter().collect::<Vec<_>>();
+        systems.sort_by_key(|sys| Reverse(sys.avg()));
+
+        let width = systems
+            .iter()
+            .map(|sys| sys.name.len())
+            .fold(HEADER.len(), usize::max);
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+
+        let mut out = format!("{:<w$}  {:>10}  {:>10}  {:>10}\n", HEADER, "min(ms)", "avg(ms)", "max(ms)", w = width);
+        for sys in systems
+        {
+            // writing to a string can't fail
+            let _ = writeln!(out, "{:<w$}  {:>10.3}  {:>10.3}  {:>10.3}", sys.name, ms(sys.min()), ms(sys.avg()), ms(sys.max()), w = width);
+        }
+        out
+    }
+}
+
+impl Default for Diagnostics
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl SystemTimings
+{
+    /// name of the system, see `System::name`
+    #[inline]
+    pub fn name(&self) -> &str
+    {
+        &self.name
+    }
+
+    /// iterate the recorded durations, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = Duration> + '_
+    {
+        self.samples.iter().copied()
+    }
+
+    /// duration of the most recent frame
+    pub fn last(&self) -> Duration
+    {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    /// shortest recorded duration
+    pub fn min(&self) -> Duration
+    {
+        self.samples().min().unwrap_or_default()
+    }
+
+    /// average recorded duration
+    pub fn avg(&self) -> Duration
+    {
+        match self.samples.len()
+        {
+            0 => Duration::ZERO,
+            n => self.samples().sum::<Duration>() / n as u32,
+        }
+    }
+
+    /// longest recorded duration
+    pub fn max(&self) -> Duration
+    {
+        self.samples().max().unwrap_or_default()
+    }
+}
@@ -1,8 +1,10 @@
+use std::cell::Cell;
 use std::sync::atomic::{ AtomicU64, Ordering };
 use std::ops::{ Range, Index };
-use std::collections::HashMap;
 use std::fmt::Display;
 
+use crate::hsh::{ IdHashMap, id_hash_map };
+
 /// unique identifier for an entity(64bit integer)
 ///
 /// obtained from `Scene::spawn` and can be stored for
@@ -14,22 +16,41 @@ pub struct Entity(u64);
 /// next entity ID(thread-safe)
 static ENT_CURSOR: AtomicU64 = AtomicU64::new(0);
 
+thread_local!
+{
+    /// entity IDs reserved by this thread, see `Entity::reserve_block`
+    static ENT_BLOCK: Cell<EntityBlock> = const { Cell::new(EntityBlock { next: 0, end: 0, size: 0 }) };
+}
+
+/// block of entity IDs reserved by a thread from `ENT_CURSOR`, handed out
+/// without touching the cursor until it runs out
+#[derive(Debug, Copy, Clone)]
+struct EntityBlock
+{
+    /// next ID handed out
+    next: u64,
+    /// end of the block, exclusive
+    end: u64,
+    /// size of the blocks reserved, or 0 if this thread doesn't reserve any
+    size: u64,
+}
+
 /// structure that maps entity IDs to their component archetype in
 /// a "double hashmap" like structure
 #[derive(Debug, Default)]
 pub struct EntityMap
 {
-    chunks: HashMap<u64, EntityMapChunk>
+    chunks: IdHashMap<u64, EntityMapChunk>
 }
 
 /// the storage location of an entity's components
+///
+/// packed into a single `u64`, from most to least significant bits:
+/// - 24 bits archetype ID
+/// - 24 bits chunk ID within the archetype
+/// - 16 bits entity index within the chunk
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct EntityLocation
-{
-    archetype: usize,
-    chunk: usize,
-    index: usize,
-}
+pub struct EntityLocation(u64);
 
 /// a chunk within an entity map
 ///
@@ -51,12 +72,62 @@ impl Entity
         self.0
     }
 
+    /// make the current thread reserve entity IDs `size` at a time, ie. when
+    /// spawning into a scene of its own, to be merged later(see `Scene::append`).
+    /// IDs are then handed out from the thread's block, and the global cursor is
+    /// only touched once per block rather than once per spawn. blocks never
+    /// overlap, so entities stay unique across threads
+    ///
+    /// the rest of the previous block, if any, is dropped. a `size` of 0 drops
+    /// it and goes back to allocating straight from the global cursor
+    pub fn reserve_block(size: u64)
+    {
+        let start = if size == 0 { 0 } else { ENT_CURSOR.fetch_add(size, Ordering::Relaxed) };
+
+        ENT_BLOCK.with(|block| block.set(EntityBlock { next: start, end: start + size, size }));
+    }
+
     /// allocate `n` entities and return the range of
-    /// their IDs
+    /// their IDs. allocating 0 entities is valid, returning
+    /// an empty range without touching the cursor
+    ///
+    /// they're taken from the current thread's block if it
+    /// reserves some(see `Entity::reserve_block`), which is
+    /// refilled when it runs out. allocations larger than a
+    /// block go straight to the global cursor
     pub(crate) fn next(n: u64) -> Range<Entity>
     {
-        debug_assert!(n > 0, "cannot allocate 0 entities!");
+        ENT_BLOCK.with(|cell|
+        {
+            let mut block = cell.get();
+
+            if block.size == 0 || n > block.size
+            {
+                return Self::next_global(n);
+            }
+            if block.end - block.next < n
+            {
+                block.next = ENT_CURSOR.fetch_add(block.size, Ordering::Relaxed);
+                block.end = block.next + block.size;
+            }
+            let start = block.next;
+
+            block.next += n;
+            cell.set(block);
+
+            Entity(start)..Entity(start + n)
+        })
+    }
+
+    /// see `Entity::next`, allocating from the global cursor
+    fn next_global(n: u64) -> Range<Entity>
+    {
+        if n == 0
+        {
+            let i = ENT_CURSOR.load(Ordering::Relaxed);
 
+            return Entity(i)..Entity(i);
+        }
         let i = ENT_CURSOR.fetch_add(n, Ordering::Relaxed);
 
         Range
@@ -70,7 +141,12 @@ impl Entity
     ///
     /// normally this is undefined behaviour, because entities
     /// are obtained from `World::spawn` or queries, but is needed
-    /// in internal operations
+    /// in internal operations. this isn't checked in any way, see
+    /// `Scene::entity` for a conversion that checks the entity is
+    /// alive, ie. for IDs received over the network
+    ///
+    /// # Safety
+    /// `id` must belong to an entity previously obtained from `Entity::next`
     #[inline]
     pub unsafe fn from_u64(id: u64) -> Self
     {
@@ -78,6 +154,15 @@ impl Entity
     }
 }
 
+/// entity IDs can be sent over the wire, and converted back with `Scene::entity`
+impl From<Entity> for u64
+{
+    fn from(ent: Entity) -> u64
+    {
+        ent.0
+    }
+}
+
 impl Display for Entity
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
@@ -112,6 +197,16 @@ impl Index<Entity> for EntityMap
 
 impl EntityMap
 {
+    /// create a new, empty map with room for roughly `entities` entities with
+    /// contiguous IDs, without reallocating
+    pub fn with_capacity(entities: usize) -> Self
+    {
+        Self
+        {
+            chunks: id_hash_map(entities.div_ceil(EntityMapChunk::SIZE)),
+        }
+    }
+
     /// insert a new (Entity, Location) pair into the map, or
     /// silently overwrite an existing one
     pub fn insert(&mut self, e: Entity, loc: EntityLocation)
@@ -154,7 +249,7 @@ impl EntityMap
     }
 
     /// remove the (Entity, Location) pair for the given entity
-    /// in this map
+    /// in this map. removing an entity that isn't in it is a no-op
     pub fn remove(&mut self, e: Entity)
     {
         // index of entity within chunk
@@ -165,22 +260,28 @@ impl EntityMap
         let e_ind = e_ind as usize;
 
         // get chunk
-        if let Some(chunk) =  self.chunks.get_mut(&c_ind)
+        let chunk = match self.chunks.get_mut(&c_ind)
         {
-            // check if entity existed...
-            if chunk.map[e_ind] != EntityLocation::NULL
-            {
-                chunk.len -= 1;
-            }
+            Some(chunk) => chunk,
+            // chunk doesn't exist -> none of its entities are in this map
+            None => return,
+        };
+
+        // entity isn't in this map(ie. it was already removed), so there's
+        // nothing to un-count
+        if chunk.map[e_ind] == EntityLocation::NULL
+        {
+            return;
+        }
+        debug_assert!(chunk.len > 0, "entity map chunk miscounts its entities!");
 
-            // ...set to null regardless of previous state
-            chunk.map[e_ind] = EntityLocation::NULL;
+        chunk.map[e_ind] = EntityLocation::NULL;
+        chunk.len -= 1;
 
-            // remove the chunk if empty
-            if chunk.len == 0
-            {
-                self.chunks.remove(&c_ind);
-            }
+        // remove the chunk once it's empty, so that `len == 0` chunks never linger
+        if chunk.len == 0
+        {
+            self.chunks.remove(&c_ind);
         }
     }
 
@@ -205,6 +306,21 @@ impl EntityMap
         }
     }
 
+    /// number of entities in this map
+    pub fn len(&self) -> usize
+    {
+        self.chunks
+            .values()
+            .map(|chunk| chunk.len)
+            .sum()
+    }
+
+    /// is this map empty of entities?
+    pub fn is_empty(&self) -> bool
+    {
+        self.chunks.is_empty()
+    }
+
     /// does this map contains the entity `e`?
     /// basically, is the entity alive as far as this map knows?
     pub fn contains(&self, e: Entity) -> bool
@@ -230,34 +346,56 @@ impl EntityMapChunk
 
 impl EntityLocation
 {
-    /// represents an null entity location
-    pub const NULL: EntityLocation = EntityLocation { archetype: usize::MAX, chunk: 0, index: 0 };
+    /// represents an null entity location. its archetype ID is `EntityLocation::MAX_ARCHETYPE`,
+    /// which is never given to an actual archetype
+    pub const NULL: EntityLocation = EntityLocation(u64::MAX);
+
+    /// number of bits used by each part of the location
+    const ARCHETYPE_BITS: u32 = 24;
+    const CHUNK_BITS: u32 = 24;
+    const INDEX_BITS: u32 = 16;
+
+    /// largest archetype ID that can be packed, which is reserved for `NULL`
+    pub const MAX_ARCHETYPE: usize = (1 << Self::ARCHETYPE_BITS) - 1;
+    /// largest chunk ID that can be packed
+    pub const MAX_CHUNK: usize = (1 << Self::CHUNK_BITS) - 1;
+    /// largest entity index that can be packed
+    pub const MAX_INDEX: usize = (1 << Self::INDEX_BITS) - 1;
 
     /// get the archetype ID part of this `EntityLocation`
     #[inline]
     pub fn archetype(&self) -> usize
     {
-        self.archetype
+        (self.0 >> (Self::CHUNK_BITS + Self::INDEX_BITS)) as usize
     }
 
     /// get the chunk ID within the archetype part of this `EntityLocation`
     #[inline]
     pub fn chunk(&self) -> usize
     {
-        self.chunk
+        (self.0 >> Self::INDEX_BITS) as usize & Self::MAX_CHUNK
     }
 
     /// get the entity index within the chunk within the archetype part of this `EntityLocation`
     #[inline]
     pub fn index(&self) -> usize
     {
-        self.index
+        self.0 as usize & Self::MAX_INDEX
     }
 
     /// create a new entity location. this is an inner library operation
     pub(crate) fn new(archetype: usize, chunk: usize, index: usize) -> Self
     {
-        Self { archetype, chunk, index }
+        debug_assert!(archetype < Self::MAX_ARCHETYPE, "archetype ID {} is too large to be packed!", archetype);
+        debug_assert!(chunk <= Self::MAX_CHUNK, "chunk ID {} is too large to be packed!", chunk);
+        debug_assert!(index <= Self::MAX_INDEX, "entity index {} is too large to be packed!", index);
+
+        Self
+        (
+            (archetype as u64) << (Self::CHUNK_BITS + Self::INDEX_BITS)
+            | (chunk as u64) << Self::INDEX_BITS
+            | index as u64
+        )
     }
 }
 
@@ -271,7 +409,7 @@ impl Display for EntityLocation
         }
         else
         {
-            f.write_fmt(format_args!("archetypes[{}].chunks[{}].index[{}]", self.archetype, self.chunk, self.index))
+            f.write_fmt(format_args!("archetypes[{}].chunks[{}].index[{}]", self.archetype(), self.chunk(), self.index()))
         }
     }
 }
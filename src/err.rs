@@ -0,0 +1,40 @@
+use std::fmt::Display;
+
+use crate::{ CmpId, Entity };
+
+/// an error from an operation on a `Scene`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcsError
+{
+    /// a component set contains the same component type more than once
+    DuplicateComponent(&'static str),
+    /// an entity isn't in the scene, ie. it was despawned
+    EntityNotFound(Entity),
+    /// an entity is in the scene, but doesn't have a component type
+    ComponentMissing(Entity, &'static str),
+    /// a component type isn't registered in the scene or the global `Registry`
+    UnknownComponent(CmpId),
+    /// a component type can't be (de)serialized, see `#[component(serde)]`
+    NotSerializable(&'static str),
+    /// parenting an entity to another would create a cycle in the hierarchy, see
+    /// `Scene::set_parent`
+    HierarchyCycle(Entity, Entity),
+}
+
+impl Display for EcsError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            EcsError::DuplicateComponent(name) => write!(f, "component set contains `{}` more than once!", name),
+            EcsError::EntityNotFound(ent) => write!(f, "entity {} isn't in this scene!", ent.id()),
+            EcsError::ComponentMissing(ent, name) => write!(f, "entity {} doesn't have a `{}` component!", ent.id(), name),
+            EcsError::UnknownComponent(id) => write!(f, "component {:?} isn't registered!", id),
+            EcsError::NotSerializable(name) => write!(f, "component `{}` can't be (de)serialized!", name),
+            EcsError::HierarchyCycle(child, parent) => write!(f, "making entity {} the parent of entity {} would create a cycle!", parent.id(), child.id()),
+        }
+    }
+}
+
+impl std::error::Error for EcsError { }
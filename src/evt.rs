@@ -0,0 +1,159 @@
+use std::marker::PhantomData;
+
+use crate::Scene;
+
+/// double-buffered queue of events of type `T`, ie. collisions, stored as a
+/// resource: systems `send` events, and every other system interested in them
+/// reads them through its own `EventReader`.
+///
+/// `Events::update` must be called once per frame, see `Schedule::add_event`.
+/// events are dropped on the second update after they were sent, so readers
+/// have until the end of the next frame to see them
+#[derive(Debug)]
+pub struct Events<T>
+{
+    /// events sent during the previous frame
+    old: Vec<T>,
+    /// events sent during this frame
+    new: Vec<T>,
+    /// number of events sent before the first one in `old`
+    old_start: usize,
+    /// number of events sent before the first one in `new`
+    new_start: usize,
+}
+
+impl<T> Events<T>
+{
+    /// create a new, empty event queue
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// send an event, to be read by every `EventReader`
+    pub fn send(&mut self, ev: T)
+    {
+        self.new.push(ev);
+    }
+
+    /// swap the buffers, dropping the events sent before the previous update
+    pub fn update(&mut self)
+    {
+        std::mem::swap(&mut self.old, &mut self.new);
+
+        self.new.clear();
+        self.old_start = self.new_start;
+        self.new_start += self.old.len();
+    }
+
+    /// iterate every event still buffered, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &T>
+    {
+        self.old
+            .iter()
+            .chain(&self.new)
+    }
+
+    /// number of events still buffered
+    pub fn len(&self) -> usize
+    {
+        self.old.len() + self.new.len()
+    }
+
+    /// are there no events buffered?
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// get a reader that will only see the events sent from now on
+    pub fn reader(&self) -> EventReader<T>
+    {
+        EventReader { cursor: self.sent(), missed: 0, marker: PhantomData }
+    }
+
+    /// number of events ever sent
+    fn sent(&self) -> usize
+    {
+        self.new_start + self.new.len()
+    }
+}
+
+impl<T> Default for Events<T>
+{
+    fn default() -> Self
+    {
+        Self { old: Vec::new(), new: Vec::new(), old_start: 0, new_start: 0 }
+    }
+}
+
+/// cursor into an `Events<T>` queue, so that each reader sees every event
+/// exactly once. a new reader sees every event still buffered
+///
+/// a reader that isn't read for more than a frame misses the events that were
+/// dropped in the meantime, see `EventReader::missed`
+#[derive(Debug)]
+pub struct EventReader<T>
+{
+    /// number of events sent before the next one to read
+    cursor: usize,
+    /// number of events dropped before they could be read
+    missed: usize,
+    /// `T` is only used for its type
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> EventReader<T>
+{
+    /// iterate the events sent since this reader was last read, oldest first
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T>
+    {
+        // events dropped in the meantime
+        let start = self.cursor.max(events.old_start);
+
+        self.missed += start - self.cursor;
+        self.cursor = events.sent();
+
+        let old = (start - events.old_start).min(events.old.len());
+        let new = start.saturating_sub(events.new_start);
+
+        events.old[old..]
+            .iter()
+            .chain(&events.new[new..])
+    }
+
+    /// number of events this reader hasn't read yet
+    pub fn len(&self, events: &Events<T>) -> usize
+    {
+        events.sent() - self.cursor.max(events.old_start)
+    }
+
+    /// has this reader read every event?
+    pub fn is_empty(&self, events: &Events<T>) -> bool
+    {
+        self.len(events) == 0
+    }
+
+    /// number of events that were dropped before this reader could read them
+    pub fn missed(&self) -> usize
+    {
+        self.missed
+    }
+}
+
+impl<T> Default for EventReader<T>
+{
+    fn default() -> Self
+    {
+        Self { cursor: 0, missed: 0, marker: PhantomData }
+    }
+}
+
+/// update the scene's `Events<T>` queue, if any
+pub(crate) fn update_events<T: 'static>(scene: &mut Scene)
+{
+    if let Some(events) = scene.resource_mut::<Events<T>>()
+    {
+        events.update();
+    }
+}
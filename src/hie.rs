@@ -0,0 +1,185 @@
+use std::ops::Deref;
+
+use crate::{ CmpId, Component, EcsError, Entity, Scene };
+
+/// component pointing to the parent of an entity within the scene's hierarchy.
+/// it's kept consistent with the parent's `Children` by the scene, so it can only
+/// be changed through `Scene::set_parent` and `Scene::remove_parent`, and can't
+/// be created(or copied onto another entity) outside of them, nor borrowed
+/// mutably by queries. removing it(or `Children`) with `Scene::remove` takes
+/// the entity out of the hierarchy like `Scene::remove_parent` would
+#[derive(Debug, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+/// component listing the children of an entity within the scene's hierarchy,
+/// in the order they were parented. like `Parent`, it can't be created outside
+/// of the scene, which keeps the two consistent
+#[derive(Debug, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Parent
+{
+    /// get the parent entity
+    #[inline]
+    pub fn get(&self) -> Entity
+    {
+        self.0
+    }
+}
+
+impl Deref for Children
+{
+    type Target = [Entity];
+
+    fn deref(&self) -> &[Entity]
+    {
+        &self.0
+    }
+}
+
+impl Component for Parent
+{
+    const ID: CmpId = unsafe { CmpId::from_u64(CmpId::BUILTIN | 200) };
+    const NAME: &'static str = "Parent";
+}
+
+impl Component for Children
+{
+    const ID: CmpId = unsafe { CmpId::from_u64(CmpId::BUILTIN | 201) };
+    const NAME: &'static str = "Children";
+}
+
+crate::__cmp_register!(Parent);
+crate::__cmp_register!(Children);
+
+impl Scene
+{
+    /// make `parent` the parent of `child`, removing it from the children of its
+    /// previous parent, if any. fails if either entity isn't in this scene, or
+    /// if `child` is `parent` or one of its ancestors, which would create a cycle
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), EcsError>
+    {
+        for ent in &[child, parent]
+        {
+            if self.location(*ent).is_none()
+            {
+                return Err(EcsError::EntityNotFound(*ent));
+            }
+        }
+
+        // walk up from the new parent, looking for the child
+        let mut ancestor = Some(parent);
+        while let Some(ent) = ancestor
+        {
+            if ent == child
+            {
+                return Err(EcsError::HierarchyCycle(child, parent));
+            }
+            ancestor = self.parent(ent);
+        }
+
+        // leave the previous parent...
+        if let Some(prev) = self.parent(child)
+        {
+            self.remove_child(prev, child);
+        }
+        match self.get_mut::<Parent>(child)
+        {
+            Some(cmp) => cmp.0 = parent,
+            None => { self.add(child, Parent(parent)); },
+        }
+        // ...for the new one
+        match self.get_mut::<Children>(parent)
+        {
+            Some(cmp) => cmp.0.push(child),
+            None => { self.add(parent, Children(vec![child])); },
+        }
+        Ok(())
+    }
+
+    /// detach `child` from its parent, making it a root of the hierarchy. returns
+    /// the previous parent, or `None` if it didn't have one
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity>
+    {
+        let parent = self.parent(child)?;
+
+        // which also removes it from the parent's children
+        self.remove::<Parent>(child);
+
+        Some(parent)
+    }
+
+    /// get the parent of an entity, or `None` if it's a root of the hierarchy or
+    /// isn't in this scene
+    pub fn parent(&self, child: Entity) -> Option<Entity>
+    {
+        self.get::<Parent>(child).map(Parent::get)
+    }
+
+    /// get the children of an entity, in the order they were parented. it's empty
+    /// if the entity has no children or isn't in this scene
+    pub fn children(&self, parent: Entity) -> &[Entity]
+    {
+        self.get::<Children>(parent).map_or(&[], |cmp| &cmp.0)
+    }
+
+    /// despawn an entity along with its children, their children, and so on. returns
+    /// false if the entity isn't in this scene
+    pub fn despawn_recursive(&mut self, ent: Entity) -> bool
+    {
+        if self.location(ent).is_none()
+        {
+            return false;
+        }
+
+        // every entity in the subtree, parents before their children
+        let mut subtree = vec![ent];
+        let mut i = 0;
+        while let Some(parent) = subtree.get(i).copied()
+        {
+            subtree.extend_from_slice(self.children(parent));
+            i += 1;
+        }
+        // children first, so that none are orphaned on the way
+        for ent in subtree.into_iter().rev()
+        {
+            self.despawn(ent);
+        }
+        true
+    }
+
+    /// take an entity out of the hierarchy before its `Parent` and/or `Children` are
+    /// removed(or it's despawned), removing it from its parent's children and/or
+    /// orphaning its own. this moves entities around, so locations must be looked up
+    /// again
+    pub(crate) fn detach(&mut self, ent: Entity, parent: bool, children: bool)
+    {
+        if let Some(parent) = self.parent(ent).filter(|_| parent)
+        {
+            self.remove_child(parent, ent);
+        }
+        if !children
+        {
+            return;
+        }
+        // children can't be changed while they're borrowed from the scene
+        let children = self
+            .get_mut::<Children>(ent)
+            .map(|cmp| std::mem::take(&mut cmp.0))
+            .unwrap_or_default();
+
+        for child in children
+        {
+            self.remove::<Parent>(child);
+        }
+    }
+
+    /// remove `child` from the children of `parent`
+    fn remove_child(&mut self, parent: Entity, child: Entity)
+    {
+        if let Some(cmp) = self.get_mut::<Children>(parent)
+        {
+            cmp.0.retain(|ent| *ent != child);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+/// a fast, non-cryptographic hasher for integer keys like entity and component
+/// IDs, which are already well-distributed. unlike SipHash, it doesn't resist
+/// hash flooding. it's the hasher of the scene's internal maps with the
+/// `fast-hash` feature, and user maps keyed by `Entity` can use it regardless,
+/// through `BuildHasherDefault<IdHasher>`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdHasher
+{
+    hash: u64,
+}
+
+impl IdHasher
+{
+    /// odd constant with well-mixed bits, from `rustc`'s FxHasher
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    /// mix a word into the hash
+    #[inline]
+    fn add(&mut self, word: u64)
+    {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for IdHasher
+{
+    #[inline]
+    fn finish(&self) -> u64
+    {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8])
+    {
+        let mut words = bytes.chunks_exact(8);
+
+        for word in &mut words
+        {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(word);
+
+            self.add(u64::from_le_bytes(buf));
+        }
+        for byte in words.remainder()
+        {
+            self.add(*byte as u64);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, n: u64)
+    {
+        self.add(n);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, n: usize)
+    {
+        self.add(n as u64);
+    }
+}
+
+/// builds the hasher of the scene's internal maps, keyed by entity and component
+/// IDs: `IdHasher` with the `fast-hash` feature, and SipHash otherwise
+#[cfg(feature = "fast-hash")]
+pub type IdBuildHasher = std::hash::BuildHasherDefault<IdHasher>;
+
+/// see the other `IdBuildHasher`
+#[cfg(not(feature = "fast-hash"))]
+pub type IdBuildHasher = std::collections::hash_map::RandomState;
+
+/// hashmap keyed by entity or component IDs, see `IdBuildHasher`
+pub(crate) type IdHashMap<K, V> = HashMap<K, V, IdBuildHasher>;
+
+/// create a new, empty `IdHashMap` with room for `n` entries
+pub(crate) fn id_hash_map<K, V>(n: usize) -> IdHashMap<K, V>
+{
+    HashMap::with_capacity_and_hasher(n, IdBuildHasher::default())
+}
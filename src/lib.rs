@@ -2,13 +2,53 @@ pub use ezgame_macros::*;
 
 mod ent;    // entity
 mod cmp;    // component
-            // system
+mod sys;    // system
+mod sch;    // schedule
+mod qry;    // query
+mod res;    // resource
+mod evt;    // event
+mod tim;    // time
+mod tsk;    // task
+mod err;    // error
+mod reg;    // registry
+mod hsh;    // hash
 
 mod arch;   // archetype
 mod scn;    // scene
+mod hie;    // hierarchy
+mod app;    // application
+mod dgn;    // diagnostics
+
+#[cfg(any(feature = "glam", feature = "cgmath"))]
+mod math;   // math crates
+#[cfg(feature = "serde")]
+mod ser;    // serialization
+#[cfg(feature = "glam")]
+mod tfm;    // transform
 
 pub use ent::*;
 pub use cmp::*;
+pub use sys::*;
+pub use sch::*;
+pub use qry::*;
+pub use res::*;
+pub use evt::*;
+pub use tim::*;
+pub use tsk::*;
+pub use err::*;
+pub use reg::*;
+pub use hsh::*;
 
 pub use arch::*;
-pub use scn::*;
\ No newline at end of file
+pub use scn::*;
+pub use hie::*;
+pub use app::*;
+pub use dgn::*;
+#[cfg(feature = "serde")]
+pub use ser::*;
+#[cfg(feature = "glam")]
+pub use tfm::*;
+
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+pub use inventory as __inventory;
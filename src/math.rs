@@ -0,0 +1,44 @@
+//! `Component` implementations for the types of common math crates, behind
+//! their cargo feature of the same name
+
+use crate::{ CmpId, Component, MutComponent };
+
+/// implements `Component` for foreign types, given their unique index within
+/// this crate's built-in component IDs
+macro_rules! impl_builtin
+{
+    ($($ty:ty => $n:literal,)*) =>
+    {
+        $(
+            impl Component for $ty
+            {
+                const ID: CmpId = unsafe { CmpId::from_u64(CmpId::BUILTIN | $n) };
+                const NAME: &'static str = stringify!($ty);
+            }
+
+            impl MutComponent for $ty { }
+
+            crate::__cmp_register!($ty);
+        )*
+    };
+}
+
+#[cfg(feature = "glam")]
+impl_builtin!
+{
+    glam::Vec2 => 0,
+    glam::Vec3 => 1,
+    glam::Vec4 => 2,
+    glam::Quat => 3,
+    glam::Mat4 => 4,
+}
+
+#[cfg(feature = "cgmath")]
+impl_builtin!
+{
+    cgmath::Vector2<f32> => 100,
+    cgmath::Vector3<f32> => 101,
+    cgmath::Vector4<f32> => 102,
+    cgmath::Quaternion<f32> => 103,
+    cgmath::Matrix4<f32> => 104,
+}
@@ -0,0 +1,858 @@
+use std::marker::PhantomData;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use crate::{ Access, Archetype, ArchetypeChunk, ArchetypeMap, ColumnGuard, CmpMeta, StaticCmpSet, Component, MutComponent, Entity, Scene, TaskPool };
+
+/// cached list of the archetypes matching the query `Q`, see `Scene::query_state`
+///
+/// archetypes are never removed from a scene, so the cache only needs to
+/// look at the archetypes created since it was last updated. for scenes
+/// with a stable composition, this turns finding the matching archetypes
+/// into a single comparison, so that hot queries go straight to their
+/// archetypes rather than scanning all of them every time they're run
+#[derive(Debug)]
+pub struct QueryState<Q: Query>
+{
+    /// indices of the matching archetypes, in ascending order
+    matches: Vec<usize>,
+    /// `ArchetypeMap::generation` when this cache was last updated
+    generation: usize,
+    /// `ArchetypeMap::uid` of the archetypes this cache is about
+    map: u64,
+    /// `Q` is only used for its type
+    marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: Query> QueryState<Q>
+{
+    /// create a new, empty query cache
+    pub fn new() -> Self
+    {
+        Self
+        {
+            matches: Default::default(),
+            generation: 0,
+            map: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// bring this cache up to date with the archetypes in `map`, only
+    /// looking at those created since the last update. a cache that was
+    /// last used with another scene starts over
+    pub fn update(&mut self, map: &ArchetypeMap)
+    {
+        // another scene's archetypes
+        if self.map != map.uid()
+        {
+            self.matches.clear();
+            self.generation = 0;
+            self.map = map.uid();
+        }
+        // up to date
+        if self.generation == map.generation()
+        {
+            return;
+        }
+
+        // check new archetypes
+        let new = map.inner()[self.generation..]
+            .iter()
+            .filter(|arch| Q::matches(arch))
+            .map(|arch| arch.id());
+
+        self.matches.extend(new);
+        self.generation = map.generation();
+    }
+
+    /// get the indices of the matching archetypes, as of the last update
+    #[inline]
+    pub fn matches(&self) -> &[usize]
+    {
+        &self.matches
+    }
+
+    /// iterate the components of every entity matching `Q`, see `Scene::query`.
+    /// only the archetypes created since the last call are matched against `Q`
+    pub fn iter<'a>(&'a mut self, scene: &'a mut Scene) -> QueryIter<'a, Q>
+    {
+        self.update(scene.archetypes());
+
+        QueryIter::cached(scene.archetypes().inner(), &self.matches, 0).tracking(scene.version())
+    }
+
+    /// iterate the archetypes within `scene` that match `Q`
+    pub fn archetypes<'a>(&'a mut self, scene: &'a Scene) -> impl Iterator<Item = &'a Archetype>
+    {
+        self.update(scene.archetypes());
+
+        let arch = scene.archetypes().inner();
+
+        self.matches
+            .iter()
+            .map(move |i| &arch[*i])
+    }
+
+    /// iterate the archetypes within `scene` that match `Q`
+    pub fn archetypes_mut<'a>(&'a mut self, scene: &'a mut Scene) -> impl Iterator<Item = &'a mut Archetype>
+    {
+        self.update(scene.archetypes());
+
+        let arch = scene.archetypes_mut().inner_mut().as_mut_ptr();
+
+        self.matches
+            .iter()
+            // SAFETY: matches are unique indices within `arch`, which is
+            // exclusively borrowed for `'a`
+            .map(move |i| unsafe { &mut *arch.add(*i) })
+    }
+}
+
+impl<Q: Query> Default for QueryState<Q>
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// component sets that can be borrowed as a struct of slices within a chunk, so
+/// that the innermost loops of a system don't fetch anything per entity. it's
+/// implemented for components, as `&mut [T]`, and for `#[derive(Bundle)]` structs,
+/// as a `{Bundle}View` struct generated alongside them:
+/// ```ignore
+/// #[derive(Bundle)]
+/// struct Body { pos: Pos, vel: Vel }
+///
+/// for BodyView { pos, vel } in scene.view::<Body>() { ... }
+/// ```
+///
+/// # Safety
+/// `ChunkView::view` must only borrow the columns of the components in this set
+pub unsafe trait ChunkView: StaticCmpSet
+{
+    /// struct of slices over the components of a chunk
+    type View<'a>;
+
+    /// borrow the components of `chunk` mutably, as slices
+    ///
+    /// # Safety
+    /// `chunk` must contain every component in this set, which musn't be borrowed
+    /// elsewhere for `'a`
+    unsafe fn view<'a>(chunk: &'a ArchetypeChunk) -> Self::View<'a>;
+}
+
+unsafe impl<T: Component> ChunkView for T
+{
+    type View<'a> = &'a mut [T];
+
+    unsafe fn view<'a>(chunk: &'a ArchetypeChunk) -> Self::View<'a>
+    {
+        chunk.mark_changed(T::ID);
+
+        std::slice::from_raw_parts_mut(chunk.components_ptr::<T>(), chunk.len())
+    }
+}
+
+/// a set of component accesses, fetched for every entity of the archetypes it
+/// matches: `&T`, `&mut T`, `Option<Q>`, `Entity` or a tuple of those.
+///
+/// this is the single extension point of queries, where per-archetype matching,
+/// per-chunk column setup and per-entity fetching happen
+///
+/// # Safety
+/// `Query::access` must report every component that `Query::get` reads or writes, and
+/// items must be safe to get on any thread(see `View::par_for_each`)
+pub unsafe trait Query
+{
+    /// type yielded for every entity
+    type Item<'a>;
+    /// column pointers within a single chunk
+    type Fetch: Copy;
+
+    /// does `arch` contain every component required by this query?
+    fn matches(arch: &Archetype) -> bool;
+
+    /// list every component accessed by this query, and whether it's mutably
+    fn access(out: &mut Vec<(CmpMeta, bool)>);
+
+    /// set up the column pointers of `chunk`, within `arch`
+    ///
+    /// # Safety
+    /// `arch` must match this query, and own `chunk`
+    unsafe fn fetch(arch: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch;
+
+    /// get the item of the entity at `index` within a fetched chunk
+    ///
+    /// # Safety
+    /// `index` must be occupied within the fetched chunk, which must outlive `'a`. mutable
+    /// items must not alias any other reference
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>;
+
+    /// should `chunk` be iterated, given the change tick at which the query last
+    /// ran? only change filters like `Changed<T>` skip chunks
+    fn filter_chunk(_chunk: &ArchetypeChunk, _last_run: u64) -> bool
+    {
+        true
+    }
+}
+
+/// queries that never access components mutably, so that their items can be
+/// fetched from a shared scene, see `Scene::get_components`
+///
+/// # Safety
+/// `Query::access` must not report any mutable access
+pub unsafe trait ReadOnlyQuery: Query { }
+
+unsafe impl<T: Component> Query for &T
+{
+    type Item<'a> = &'a T;
+    type Fetch = *const T;
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        arch.meta().contains::<T>()
+    }
+
+    fn access(out: &mut Vec<(CmpMeta, bool)>)
+    {
+        out.push((T::META, false));
+    }
+
+    unsafe fn fetch(_: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+    {
+        chunk.components_ptr::<T>()
+    }
+
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+    {
+        &*fetch.add(index)
+    }
+}
+
+unsafe impl<T: MutComponent> Query for &mut T
+{
+    type Item<'a> = &'a mut T;
+    type Fetch = *mut T;
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        arch.meta().contains::<T>()
+    }
+
+    fn access(out: &mut Vec<(CmpMeta, bool)>)
+    {
+        out.push((T::META, true));
+    }
+
+    unsafe fn fetch(_: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+    {
+        chunk.mark_changed(T::ID);
+        chunk.components_ptr::<T>()
+    }
+
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+    {
+        &mut *fetch.add(index)
+    }
+}
+
+unsafe impl<Q: Query> Query for Option<Q>
+{
+    type Item<'a> = Option<Q::Item<'a>>;
+    type Fetch = Option<Q::Fetch>;
+
+    fn matches(_: &Archetype) -> bool
+    {
+        true
+    }
+
+    fn access(out: &mut Vec<(CmpMeta, bool)>)
+    {
+        Q::access(out)
+    }
+
+    unsafe fn fetch(arch: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+    {
+        if Q::matches(arch)
+        {
+            Some(Q::fetch(arch, chunk))
+        }
+        else
+        {
+            None
+        }
+    }
+
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+    {
+        fetch.map(|fetch| Q::get(fetch, index))
+    }
+}
+
+unsafe impl Query for Entity
+{
+    type Item<'a> = Entity;
+    type Fetch = *const Entity;
+
+    fn matches(_: &Archetype) -> bool
+    {
+        true
+    }
+
+    fn access(_: &mut Vec<(CmpMeta, bool)>) { }
+
+    unsafe fn fetch(_: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+    {
+        chunk.entities().as_ptr()
+    }
+
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+    {
+        *fetch.add(index)
+    }
+}
+
+/// query filter matching the archetypes that contain `T`, without accessing it
+pub struct With<T: Component>(PhantomData<fn() -> T>);
+
+/// query filter matching the archetypes that don't contain `T`
+pub struct Without<T: Component>(PhantomData<fn() -> T>);
+
+unsafe impl<T: Component> Query for With<T>
+{
+    type Item<'a> = ();
+    type Fetch = ();
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        arch.meta().contains::<T>()
+    }
+
+    fn access(_: &mut Vec<(CmpMeta, bool)>) { }
+
+    unsafe fn fetch(_: &Archetype, _: &ArchetypeChunk) -> Self::Fetch { }
+
+    unsafe fn get<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> { }
+}
+
+unsafe impl<T: Component> Query for Without<T>
+{
+    type Item<'a> = ();
+    type Fetch = ();
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        !arch.meta().contains::<T>()
+    }
+
+    fn access(_: &mut Vec<(CmpMeta, bool)>) { }
+
+    unsafe fn fetch(_: &Archetype, _: &ArchetypeChunk) -> Self::Fetch { }
+
+    unsafe fn get<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> { }
+}
+
+unsafe impl<T: Component> ReadOnlyQuery for &T { }
+unsafe impl<Q: ReadOnlyQuery> ReadOnlyQuery for Option<Q> { }
+unsafe impl ReadOnlyQuery for Entity { }
+unsafe impl<T: Component> ReadOnlyQuery for With<T> { }
+unsafe impl<T: Component> ReadOnlyQuery for Without<T> { }
+
+/// query filter matching the entities whose `T` component was added since the
+/// query last ran, see `Scene::query_since`
+///
+/// changes are tracked per chunk rather than per entity: every entity sharing a
+/// chunk with one that received `T` passes the filter too. there are false
+/// positives, but never false negatives
+pub struct Added<T: Component>(PhantomData<fn() -> T>);
+
+/// query filter matching the entities whose `T` component was borrowed mutably, or
+/// added, since the query last ran, see `Scene::query_since`
+///
+/// changes are tracked per chunk rather than per entity: every entity sharing a
+/// chunk with one whose `T` changed passes the filter too. there are false
+/// positives, but never false negatives
+pub struct Changed<T: Component>(PhantomData<fn() -> T>);
+
+unsafe impl<T: Component> Query for Added<T>
+{
+    type Item<'a> = ();
+    type Fetch = ();
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        arch.meta().contains::<T>()
+    }
+
+    fn access(_: &mut Vec<(CmpMeta, bool)>) { }
+
+    unsafe fn fetch(_: &Archetype, _: &ArchetypeChunk) -> Self::Fetch { }
+
+    unsafe fn get<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> { }
+
+    fn filter_chunk(chunk: &ArchetypeChunk, last_run: u64) -> bool
+    {
+        chunk.added_tick(T::ID) > last_run
+    }
+}
+
+unsafe impl<T: Component> Query for Changed<T>
+{
+    type Item<'a> = ();
+    type Fetch = ();
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        arch.meta().contains::<T>()
+    }
+
+    fn access(_: &mut Vec<(CmpMeta, bool)>) { }
+
+    unsafe fn fetch(_: &Archetype, _: &ArchetypeChunk) -> Self::Fetch { }
+
+    unsafe fn get<'a>(_: Self::Fetch, _: usize) -> Self::Item<'a> { }
+
+    fn filter_chunk(chunk: &ArchetypeChunk, last_run: u64) -> bool
+    {
+        chunk.changed_tick(T::ID) > last_run
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyQuery for Added<T> { }
+unsafe impl<T: Component> ReadOnlyQuery for Changed<T> { }
+
+/// queries whose items can be fetched for a whole chunk at once, as slices:
+/// `(&mut [Pos], &[Vel])` for `(&mut Pos, &Vel)`, see `Scene::query_chunks`.
+/// plain slices are what LLVM vectorizes, unlike per-entity iterators
+///
+/// # Safety
+/// `ChunkQuery::slice` must only borrow what `Query::get` would for every entity
+/// of the chunk
+pub unsafe trait ChunkQuery: Query
+{
+    /// components of every entity within a single chunk
+    type Slice<'a>;
+
+    /// get the items of the first `len` entities within a fetched chunk
+    ///
+    /// # Safety
+    /// `len` must be the length of the fetched chunk, which must outlive `'a`.
+    /// mutable slices must not alias any other reference
+    unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>;
+}
+
+unsafe impl<T: Component> ChunkQuery for &T
+{
+    type Slice<'a> = &'a [T];
+
+    unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>
+    {
+        std::slice::from_raw_parts(fetch, len)
+    }
+}
+
+unsafe impl<T: MutComponent> ChunkQuery for &mut T
+{
+    type Slice<'a> = &'a mut [T];
+
+    unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>
+    {
+        std::slice::from_raw_parts_mut(fetch, len)
+    }
+}
+
+unsafe impl<Q: ChunkQuery> ChunkQuery for Option<Q>
+{
+    type Slice<'a> = Option<Q::Slice<'a>>;
+
+    unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>
+    {
+        fetch.map(|fetch| Q::slice(fetch, len))
+    }
+}
+
+unsafe impl ChunkQuery for Entity
+{
+    type Slice<'a> = &'a [Entity];
+
+    unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>
+    {
+        std::slice::from_raw_parts(fetch, len)
+    }
+}
+
+/// implements `ChunkQuery` for filters, which have no items
+macro_rules! impl_chunk_query_filter
+{
+    ($($name:ident),*) =>
+    {
+        $(
+            unsafe impl<T: Component> ChunkQuery for $name<T>
+            {
+                type Slice<'a> = ();
+
+                unsafe fn slice<'a>(_: Self::Fetch, _: usize) -> Self::Slice<'a> { }
+            }
+        )*
+    };
+}
+
+impl_chunk_query_filter!(With, Without, Added, Changed);
+
+/// implements `Query`, `ReadOnlyQuery` and `ChunkQuery` for a tuple of the arity given
+macro_rules! impl_query
+{
+    ($($name:ident),*) =>
+    {
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        unsafe impl<$($name: Query),*> Query for ($($name,)*)
+        {
+            type Item<'a> = ($($name::Item<'a>,)*);
+            type Fetch = ($($name::Fetch,)*);
+
+            fn matches(arch: &Archetype) -> bool
+            {
+                true $(&& $name::matches(arch))*
+            }
+
+            fn access(out: &mut Vec<(CmpMeta, bool)>)
+            {
+                $($name::access(out);)*
+            }
+
+            unsafe fn fetch(arch: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+            {
+                ($($name::fetch(arch, chunk),)*)
+            }
+
+            unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+            {
+                let ($($name,)*) = fetch;
+
+                ($($name::get($name, index),)*)
+            }
+
+            fn filter_chunk(chunk: &ArchetypeChunk, last_run: u64) -> bool
+            {
+                true $(&& $name::filter_chunk(chunk, last_run))*
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlyQuery),*> ReadOnlyQuery for ($($name,)*) { }
+
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        unsafe impl<$($name: ChunkQuery),*> ChunkQuery for ($($name,)*)
+        {
+            type Slice<'a> = ($($name::Slice<'a>,)*);
+
+            unsafe fn slice<'a>(fetch: Self::Fetch, len: usize) -> Self::Slice<'a>
+            {
+                let ($($name,)*) = fetch;
+
+                ($($name::slice($name, len),)*)
+            }
+        }
+    };
+}
+
+/// implements `Query`, `ReadOnlyQuery` and `ChunkQuery` for every tuple up to the arity given
+macro_rules! impl_queries
+{
+    () =>
+    {
+        impl_query!();
+    };
+    ($head:ident $(, $tail:ident)*) =>
+    {
+        impl_query!($head $(, $tail)*);
+        impl_queries!($($tail),*);
+    };
+}
+
+impl_queries!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+/// panics if `Q` accesses the same component mutably more than once, or both
+/// mutably and immutably. returns every component accessed, sorted by ID
+pub(crate) fn check_access<Q: Query>() -> Vec<(CmpMeta, bool)>
+{
+    let mut access = Vec::new();
+
+    Q::access(&mut access);
+    access.sort_by_key(|(meta, _)| meta.id());
+
+    for n in access.windows(2)
+    {
+        if n[0].0.id() == n[1].0.id() && (n[0].1 || n[1].1)
+        {
+            panic!("query accesses `{}` mutably while it's already borrowed!", n[0].0.name());
+        }
+    }
+    access
+}
+
+/// iterator over the items of every entity matching `Q`, see `Scene::query`
+pub struct QueryIter<'a, Q: Query>
+{
+    /// matching chunks left to look at
+    chunks: MatchingChunks<'a, Q>,
+    /// column pointers of the chunk being iterated
+    fetch: Option<Q::Fetch>,
+    /// next entity within the chunk being iterated
+    index: usize,
+    /// number of entities within the chunk being iterated
+    len: usize,
+}
+
+/// iterator over the components of every chunk matching `Q`, as slices, see
+/// `Scene::query_chunks`
+pub struct QueryChunks<'a, Q: ChunkQuery>
+{
+    /// matching chunks left to look at
+    chunks: MatchingChunks<'a, Q>,
+}
+
+/// iterator over the non-empty chunks matching `Q` that pass its filters, along
+/// with their archetype
+struct MatchingChunks<'a, Q: Query>
+{
+    /// archetypes left to look at
+    archs: ArchetypeIter<'a>,
+    /// archetype being iterated, and its chunks left to look at
+    arch: Option<(&'a Archetype, std::slice::Iter<'a, ArchetypeChunk>)>,
+    /// change tick at which the query last ran, see `Query::filter_chunk`
+    last_run: u64,
+    /// structural version of the scene, and its value when the iterator was
+    /// created, see `QueryIter::tracking`
+    version: Option<(&'a AtomicU64, u64)>,
+    /// columns borrowed in the archetypes visited so far
+    guard: ColumnGuard<'a>,
+    /// `Q` is only used for its type
+    marker: PhantomData<fn() -> Q>,
+}
+
+/// archetypes a `QueryIter` looks at
+enum ArchetypeIter<'a>
+{
+    /// every archetype, matched against the query along the way
+    All(std::slice::Iter<'a, Archetype>),
+    /// the archetypes at the cached indices, known to match, see `QueryState`
+    Cached(&'a [Archetype], std::slice::Iter<'a, usize>),
+}
+
+impl<'a, Q: Query> QueryIter<'a, Q>
+{
+    /// create a new iterator over `archs`, which must be exclusively borrowed for `'a`
+    /// if `Q` accesses any component mutably. chunks unchanged since `last_run` are
+    /// skipped by change filters
+    pub(crate) fn new(archs: &'a [Archetype], last_run: u64) -> Self
+    {
+        Self::with_archetypes(ArchetypeIter::All(archs.iter()), last_run)
+    }
+
+    /// see `QueryIter::new`, iterating only the archetypes at `matches`, which
+    /// must all match `Q`
+    pub(crate) fn cached(archs: &'a [Archetype], matches: &'a [usize], last_run: u64) -> Self
+    {
+        Self::with_archetypes(ArchetypeIter::Cached(archs, matches.iter()), last_run)
+    }
+
+    /// see `QueryIter::new`
+    fn with_archetypes(archs: ArchetypeIter<'a>, last_run: u64) -> Self
+    {
+        let access = check_access::<Q>();
+
+        Self
+        {
+            chunks: MatchingChunks
+            {
+                archs,
+                arch: None,
+                last_run,
+                version: None,
+                guard: ColumnGuard::new(access),
+                marker: PhantomData,
+            },
+            fetch: None,
+            index: 0,
+            len: 0,
+        }
+    }
+
+    /// in debug builds, panic if `version`(see `Scene::structural_version`) is
+    /// bumped while this iterator is still in use. a structural change can move
+    /// or free the chunks being iterated, which is only possible by going around
+    /// the borrow checker but would otherwise be undefined behaviour
+    pub(crate) fn tracking(mut self, version: &'a AtomicU64) -> Self
+    {
+        self.chunks.version = Some((version, version.load(Ordering::Relaxed)));
+        self
+    }
+
+    /// iterate the same chunks as slices instead, see `Scene::query_chunks`. this
+    /// iterator must not have been advanced yet
+    pub(crate) fn into_chunks(self) -> QueryChunks<'a, Q>
+    where
+        Q: ChunkQuery
+    {
+        debug_assert!(self.fetch.is_none());
+
+        QueryChunks { chunks: self.chunks }
+    }
+}
+
+impl<Q: Query> QueryIter<'_, Q>
+{
+    /// components accessed by this query, see `Access::of_query`
+    pub fn access(&self) -> Access
+    {
+        Access::of_query::<Q>()
+    }
+}
+
+impl<'a, Q: Query> Iterator for QueryIter<'a, Q>
+{
+    type Item = Q::Item<'a>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.chunks.check_version();
+
+        loop
+        {
+            // next entity in the current chunk
+            if let Some(fetch) = self.fetch.filter(|_| self.index < self.len)
+            {
+                self.index += 1;
+
+                // SAFETY: the index is occupied, and the archetypes are borrowed for `'a`
+                return Some(unsafe { Q::get(fetch, self.index - 1) });
+            }
+
+            // next chunk
+            let (arch, chunk) = self.chunks.next()?;
+
+            self.fetch = Some(unsafe { Q::fetch(arch, chunk) });
+            self.index = 0;
+            self.len = chunk.len();
+        }
+    }
+}
+
+impl<Q: ChunkQuery> QueryChunks<'_, Q>
+{
+    /// components accessed by this query, see `Access::of_query`
+    pub fn access(&self) -> Access
+    {
+        Access::of_query::<Q>()
+    }
+}
+
+impl<'a, Q: ChunkQuery> Iterator for QueryChunks<'a, Q>
+{
+    type Item = (&'a [Entity], Q::Slice<'a>);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.chunks.check_version();
+
+        let (arch, chunk) = self.chunks.next()?;
+
+        // SAFETY: each chunk is yielded once, and the archetypes are borrowed for `'a`
+        Some(unsafe { (chunk.entities(), Q::slice(Q::fetch(arch, chunk), chunk.len())) })
+    }
+}
+
+impl<Q: Query> MatchingChunks<'_, Q>
+{
+    /// see `QueryIter::tracking`
+    fn check_version(&self)
+    {
+        if let Some((version, created)) = self.version
+        {
+            debug_assert!(version.load(Ordering::Relaxed) == created, "scene was structurally changed while being queried!");
+        }
+    }
+}
+
+impl<'a, Q: Query> Iterator for MatchingChunks<'a, Q>
+{
+    type Item = (&'a Archetype, &'a ArchetypeChunk);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        loop
+        {
+            // next non-empty chunk in the current archetype, that passes the filters
+            let last_run = self.last_run;
+
+            if let Some(next) = self.arch
+                .as_mut()
+                .and_then(|(arch, chunks)| chunks
+                    .find(|chunk| !chunk.is_empty() && Q::filter_chunk(chunk, last_run))
+                    .map(|chunk| (*arch, chunk)))
+            {
+                return Some(next);
+            }
+
+            // next non-empty matching archetype
+            let arch = match &mut self.archs
+            {
+                ArchetypeIter::All(archs) => archs.find(|arch| !arch.is_empty() && Q::matches(arch))?,
+                ArchetypeIter::Cached(archs, matches) => matches
+                    .map(|i| &archs[*i])
+                    .find(|arch| !arch.is_empty())?,
+            };
+
+            self.guard.acquire(arch);
+            self.arch = Some((arch, arch.chunks().iter()));
+        }
+    }
+}
+
+/// call `f` with the item of every entity matching `Q` within `archs`, spreading
+/// their chunks across the threads of `pool`. chunks unchanged since `last_run` are
+/// skipped by change filters, see `QueryIter::new`
+///
+/// # Safety
+/// `archs` must be exclusively borrowed for the duration of the call if `Q` accesses
+/// any component mutably
+pub(crate) unsafe fn par_for_each<Q: Query, F>(archs: &[Archetype], last_run: u64, pool: &dyn TaskPool, f: F)
+where
+    F: for<'a> Fn(Q::Item<'a>) + Sync
+{
+    let mut guard = ColumnGuard::new(check_access::<Q>());
+
+    // work items, which are every non-empty chunk that passes the filters
+    let chunks = archs
+        .iter()
+        .filter(|arch| !arch.is_empty() && Q::matches(arch))
+        .inspect(|arch| guard.acquire(arch))
+        .flat_map(|arch| arch
+            .chunks()
+            .iter()
+            .filter(|chunk| !chunk.is_empty() && Q::filter_chunk(chunk, last_run))
+            .map(move |chunk| ChunkRef(arch, chunk)))
+        .collect::<Vec<_>>();
+
+    pool.for_each(chunks.len(), &|i|
+    {
+        let ChunkRef(arch, chunk) = chunks[i];
+        // SAFETY: each chunk is fetched by a single thread
+        let fetch = Q::fetch(arch, chunk);
+
+        for index in 0..chunk.len()
+        {
+            f(Q::get(fetch, index));
+        }
+    });
+}
+
+/// a chunk to be fetched on another thread, see `par_for_each`
+#[derive(Clone, Copy)]
+struct ChunkRef<'a>(&'a Archetype, &'a ArchetypeChunk);
+
+// SAFETY: chunks are only mutated through their columns, which are `Send + Sync`
+// components, and every work item is a different chunk
+unsafe impl Send for ChunkRef<'_> { }
+unsafe impl Sync for ChunkRef<'_> { }
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::{ OnceLock, RwLock };
+
+use crate::{ CmpId, CmpMeta, Component };
+
+/// the process-wide component registry, used by tools and deserializers to go
+/// from a component's name or ID(ie. read from a save file) back to its
+/// meta-data.
+///
+/// component types are registered once and never unregistered, so their
+/// meta-data lives for the rest of the program. with the `inventory` feature,
+/// every non-generic component type in the final binary is registered on
+/// first access, see `registered_components`
+pub struct Registry;
+
+/// state behind the registry's lock
+#[derive(Default)]
+struct RegistryInner
+{
+    /// maps a component's ID to its meta-data
+    ids: HashMap<CmpId, &'static CmpMeta>,
+    /// maps a component's name to its meta-data
+    names: HashMap<&'static str, &'static CmpMeta>,
+}
+
+impl Registry
+{
+    /// register a component type, returning its meta-data. registering the same
+    /// type more than once is a no-op
+    pub fn register<T: Component>() -> &'static CmpMeta
+    {
+        Self::register_meta(T::META)
+    }
+
+    /// see `Registry::register`
+    ///
+    /// registers a component type knowing only its meta-data, ie. one defined at
+    /// runtime(see `CmpMeta::new_dynamic`). if several component types share a
+    /// name, the first one registered is resolved by `Registry::by_name`
+    ///
+    /// panics if another component type was registered with the same ID
+    pub fn register_meta(meta: CmpMeta) -> &'static CmpMeta
+    {
+        let mut inner = Self::inner()
+            .write()
+            .unwrap();
+
+        // already registered
+        if let Some(prev) = inner.ids.get(&meta.id()).copied()
+        {
+            // don't poison the lock when panicking
+            drop(inner);
+            assert_same_type(prev, &meta);
+
+            return prev;
+        }
+        let meta: &'static CmpMeta = Box::leak(Box::new(meta));
+
+        inner.ids.insert(meta.id(), meta);
+        inner.names.entry(meta.name()).or_insert(meta);
+
+        meta
+    }
+
+    /// get the meta-data of a registered component type, by name
+    pub fn by_name(name: &str) -> Option<&'static CmpMeta>
+    {
+        Self::inner()
+            .read()
+            .unwrap()
+            .names
+            .get(name)
+            .copied()
+    }
+
+    /// get the meta-data of a registered component type, by ID
+    pub fn by_id(id: CmpId) -> Option<&'static CmpMeta>
+    {
+        Self::inner()
+            .read()
+            .unwrap()
+            .ids
+            .get(&id)
+            .copied()
+    }
+
+    /// get the lazily initialized registry state
+    fn inner() -> &'static RwLock<RegistryInner>
+    {
+        static INNER: OnceLock<RwLock<RegistryInner>> = OnceLock::new();
+
+        INNER.get_or_init(|| RwLock::new(RegistryInner::submitted()))
+    }
+}
+
+impl RegistryInner
+{
+    /// registry state with every component type submitted at compile-time
+    #[cfg(feature = "inventory")]
+    fn submitted() -> Self
+    {
+        let mut inner = Self::default();
+
+        for meta in registered_components()
+        {
+            if let Some(prev) = inner.ids.get(&meta.id())
+            {
+                assert_same_type(prev, meta);
+                continue;
+            }
+            inner.ids.insert(meta.id(), meta);
+            inner.names.entry(meta.name()).or_insert(meta);
+        }
+        inner
+    }
+
+    /// see the other `RegistryInner::submitted`
+    #[cfg(not(feature = "inventory"))]
+    fn submitted() -> Self
+    {
+        Self::default()
+    }
+}
+
+/// panics if `a` and `b` share an ID but aren't the same component type. drop
+/// functions are only compared for whether there is one, as the same type's can
+/// be instantiated at several addresses
+fn assert_same_type(a: &CmpMeta, b: &CmpMeta)
+{
+    let same = a.name() == b.name()
+        && a.size() == b.size()
+        && a.alignment() == b.alignment()
+        && a.drop_fn().is_some() == b.drop_fn().is_some();
+
+    assert!(same, "component types `{}` and `{}` share the ID {:?}!", a.name(), b.name(), a.id());
+}
+
+/// meta-data of a component type, submitted by `#[derive(Component)]`
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+pub struct CmpEntry(pub CmpMeta);
+
+#[cfg(feature = "inventory")]
+inventory::collect!(CmpEntry);
+
+/// iterate the meta-data of every non-generic `#[derive(Component)]`(and
+/// `impl_component!`) type linked into the final binary, in no particular
+/// order. these are registered automatically, see `Registry`
+#[cfg(feature = "inventory")]
+pub fn registered_components() -> impl Iterator<Item = &'static CmpMeta>
+{
+    inventory::iter::<CmpEntry>
+        .into_iter()
+        .map(|entry| &entry.0)
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::any::{ Any, TypeId };
+use std::cell::UnsafeCell;
+use std::ops::{ Deref, DerefMut };
+
+/// marker for the types meant to be stored in `Resources`, implemented
+/// through `#[derive(Resource)]`. any `'static` type can be a resource, so
+/// this only documents intent and gives the type a human-readable name
+pub trait Resource: 'static
+{
+    /// human-readable name of this type of resource
+    const NAME: &'static str;
+}
+
+/// a container for singleton, non-entity data, keyed by type.
+///
+/// things like the input state, the asset cache or the RNG are
+/// shared by many systems but belong to no entity in particular
+#[derive(Debug, Default)]
+pub struct Resources
+{
+    /// maps a resource's type to its type-erased value, which systems running
+    /// in parallel may mutate through a shared borrow of the container. they're
+    /// `Send`, so that scenes can be built on other threads
+    map: HashMap<TypeId, Box<UnsafeCell<dyn Any + Send>>>,
+}
+
+impl Resources
+{
+    /// create a new, empty resource container
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// insert a resource, returning the previous resource of the same
+    /// type if there was one
+    pub fn insert<T: Send + 'static>(&mut self, res: T) -> Option<T>
+    {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(UnsafeCell::new(res)))
+            .map(Self::downcast)
+    }
+
+    /// get the resource of type `T`, if any
+    pub fn get<T: 'static>(&self) -> Option<&T>
+    {
+        self.map
+            .get(&TypeId::of::<T>())
+            .map(|res| unsafe { &*res.get() }.downcast_ref::<T>().unwrap())
+    }
+
+    /// get the resource of type `T`, if any
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T>
+    {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .map(|res| res.get_mut().downcast_mut::<T>().unwrap())
+    }
+
+    /// remove the resource of type `T`, returning it if there was one
+    pub fn remove<T: 'static>(&mut self) -> Option<T>
+    {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .map(Self::downcast)
+    }
+
+    /// see `Resources::get_mut`
+    ///
+    /// returns a pointer to the resource, which systems running in parallel may
+    /// dereference as long as they're the only one to access it
+    pub(crate) fn get_ptr<T: 'static>(&self) -> Option<*mut T>
+    {
+        self.map
+            .get(&TypeId::of::<T>())
+            .map(|res| res.get() as *mut T)
+    }
+
+    /// take the value out of a resource known to be of type `T`
+    fn downcast<T: 'static>(res: Box<UnsafeCell<dyn Any + Send>>) -> T
+    {
+        debug_assert!(unsafe { &*res.get() }.is::<T>());
+
+        // SAFETY: resources are keyed by their type ID
+        unsafe { Box::from_raw(Box::into_raw(res) as *mut UnsafeCell<T>) }.into_inner()
+    }
+}
+
+/// system parameter borrowing the scene's resource of type `T`, see `SystemParam`.
+/// panics when fetched if the resource doesn't exist
+pub struct Res<'a, T: 'static>(pub(crate) &'a T);
+
+/// system parameter mutably borrowing the scene's resource of type `T`, see
+/// `SystemParam`. panics when fetched if the resource doesn't exist
+pub struct ResMut<'a, T: 'static>(pub(crate) &'a mut T);
+
+impl<'a, T: 'static> Deref for Res<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.0
+    }
+}
+
+impl<'a, T: 'static> Deref for ResMut<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.0
+    }
+}
+
+impl<'a, T: 'static> DerefMut for ResMut<'a, T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.0
+    }
+}
@@ -0,0 +1,391 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{ Diagnostics, FixedTime, FixedTimestep, IntoSystem, Scene, System, Systems, TaskPool };
+use crate::sys::SharedPool;
+use crate::evt::update_events;
+use crate::tim::update_time;
+
+/// name of a stage in a `Schedule`. declare them as constants, so that a typo
+/// is a compile error rather than a missing stage:
+/// ```ignore
+/// const UPDATE: StageLabel = StageLabel::new("update");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StageLabel(&'static str);
+
+impl StageLabel
+{
+    /// create a new stage label
+    pub const fn new(name: &'static str) -> Self
+    {
+        Self(name)
+    }
+
+    /// human-readable name of this stage
+    pub const fn name(&self) -> &'static str
+    {
+        self.0
+    }
+}
+
+/// systems grouped in ordered stages, ie. input, then simulation, then render
+/// extraction. stages run one after the other, and conflicting systems within
+/// a stage run in the order they were added
+#[derive(Debug, Default)]
+pub struct Schedule
+{
+    /// stages, in execution order
+    stages: Vec<Stage>,
+    /// run before the first stage, every time
+    hooks: Vec<fn(&mut Scene)>,
+    /// see `Schedule::set_profiler`
+    profiler: Option<Profiler>,
+    /// pairs of systems, by label or name, allowed to be ambiguous. see
+    /// `Schedule::allow_ambiguity`
+    allowed: Vec<(&'static str, &'static str)>,
+    /// panic on ambiguities? see `Schedule::deny_ambiguities`
+    deny: bool,
+    /// were ambiguities checked since the last system was added?
+    checked: bool,
+    /// pool of every stage, see `Schedule::set_pool`
+    pool: Option<SharedPool>,
+}
+
+/// two systems within a stage that conflict, but aren't ordered relative to each
+/// other, see `Schedule::ambiguities`. they run in the order they were added, so
+/// reordering their registration, ie. across plugins, silently changes results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity
+{
+    /// stage of both systems
+    pub stage: StageLabel,
+    /// name of the system added first
+    pub first: String,
+    /// name of the system added second
+    pub second: String,
+    /// names of the components and resources they conflict on
+    pub conflicts: Vec<&'static str>,
+}
+
+/// callback given the duration of every system, see `Schedule::set_profiler`
+struct Profiler(Box<ProfilerFn>);
+
+/// see `Profiler`
+type ProfilerFn = dyn Fn(&str, Duration) + Send + Sync;
+
+/// a stage within a `Schedule`
+#[derive(Debug)]
+struct Stage
+{
+    label: StageLabel,
+    systems: Systems,
+    /// run criteria of fixed stages, see `Schedule::add_fixed_stage`
+    fixed: Option<FixedTimestep>,
+}
+
+impl Schedule
+{
+    /// create a new schedule, without any stage
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// add an empty stage after every other one. panics if the stage already exists
+    pub fn add_stage(&mut self, label: StageLabel) -> &mut Self
+    {
+        self.insert_stage(self.stages.len(), label, None)
+    }
+
+    /// add an empty stage after every other one, which runs at the fixed rate of
+    /// `timestep` rather than once per run, ie. for a deterministic simulation. it
+    /// catches up with the scene's `Time` resource, and stores the interpolation
+    /// factor in its `FixedTime` resource. panics if the stage already exists
+    pub fn add_fixed_stage(&mut self, label: StageLabel, timestep: FixedTimestep) -> &mut Self
+    {
+        self.insert_stage(self.stages.len(), label, Some(timestep))
+    }
+
+    /// add an empty stage right before `before`. panics if the stage already
+    /// exists, or `before` doesn't
+    pub fn add_stage_before(&mut self, label: StageLabel, before: StageLabel) -> &mut Self
+    {
+        self.insert_stage(self.index(before), label, None)
+    }
+
+    /// add an empty stage right after `after`. panics if the stage already exists,
+    /// or `after` doesn't
+    pub fn add_stage_after(&mut self, label: StageLabel, after: StageLabel) -> &mut Self
+    {
+        self.insert_stage(self.index(after) + 1, label, None)
+    }
+
+    /// add a system at the end of a stage. panics if the stage doesn't exist
+    pub fn add_system_to_stage<M, S: IntoSystem<M>>(&mut self, label: StageLabel, sys: S) -> &mut Self
+    where
+        S::System: Send + 'static
+    {
+        let i = self.index(label);
+
+        self.stages[i].systems.add(sys);
+        self.checked = false;
+        self
+    }
+
+    /// add a system with full access to the scene at the end of a stage. every
+    /// system before it in the stage is done when it runs, and none after it
+    /// starts until it's done. panics if the stage doesn't exist
+    pub fn add_exclusive_system_to_stage(&mut self, label: StageLabel, sys: impl FnMut(&mut Scene) + Send + 'static) -> &mut Self
+    {
+        let i = self.index(label);
+
+        self.stages[i].systems.add_exclusive(sys);
+        self.checked = false;
+        self
+    }
+
+    /// update the scene's `Events<T>` queue at the start of every run, before
+    /// any stage, so that events last for the frame they were sent in and the
+    /// next one. the queue itself is a resource, to insert in the scene
+    pub fn add_event<T: 'static>(&mut self) -> &mut Self
+    {
+        self.hooks.push(update_events::<T>);
+        self
+    }
+
+    /// call `profiler` with the name and wall-clock duration of every system, each
+    /// time it runs, ie. to forward them to an external profiler. it's called from
+    /// the thread running the schedule, once the system's batch is done
+    pub fn set_profiler(&mut self, profiler: impl Fn(&str, Duration) + Send + Sync + 'static) -> &mut Self
+    {
+        self.profiler = Some(Profiler(Box::new(profiler)));
+        self
+    }
+
+    /// find the pairs of systems that conflict within a stage(see
+    /// `Access::is_compatible`), but aren't ordered relative to each other, even
+    /// indirectly, by their constraints. pairs allowed by
+    /// `Schedule::allow_ambiguity` aren't reported, and neither are exclusive
+    /// systems, which are barriers on purpose
+    pub fn ambiguities(&self) -> Vec<Ambiguity>
+    {
+        let mut ambiguities = Vec::new();
+
+        for Stage { label, systems, .. } in &self.stages
+        {
+            for (i, j) in systems.ambiguities()
+            {
+                let (a, b) = (systems.get(i), systems.get(j));
+                let allowed = self.allowed
+                    .iter()
+                    .any(|(x, y)| (matches(a, x) && matches(b, y)) || (matches(a, y) && matches(b, x)));
+
+                if !allowed
+                {
+                    ambiguities.push(Ambiguity
+                    {
+                        stage: *label,
+                        first: a.name().to_owned(),
+                        second: b.name().to_owned(),
+                        conflicts: a.access().conflicts(&b.access()),
+                    });
+                }
+            }
+        }
+        ambiguities
+    }
+
+    /// don't report the systems `a` and `b` as ambiguous, in either order. each is
+    /// either the label or the name of a system, see `System::name`
+    pub fn allow_ambiguity(&mut self, a: &'static str, b: &'static str) -> &mut Self
+    {
+        self.allowed.push((a, b));
+        self
+    }
+
+    /// panic on the next run if there's any ambiguity, see `Schedule::ambiguities`,
+    /// ie. in tests or CI. ambiguities are checked again whenever systems are added
+    pub fn deny_ambiguities(&mut self, deny: bool) -> &mut Self
+    {
+        self.deny = deny;
+        self.checked = false;
+        self
+    }
+
+    /// run the systems of every stage, including those added later, on `pool`
+    /// rather than `ThreadPool::global`, see `Systems::set_pool`
+    pub fn set_pool(&mut self, pool: impl TaskPool + Send + 'static) -> &mut Self
+    {
+        self.pool = Some(SharedPool(Arc::new(pool)));
+
+        for stage in &mut self.stages
+        {
+            stage.systems.set_shared_pool(self.pool.clone());
+        }
+        self
+    }
+
+    /// stop calling the profiler set by `Schedule::set_profiler`
+    pub fn remove_profiler(&mut self) -> &mut Self
+    {
+        self.profiler = None;
+        self
+    }
+
+    /// run every stage over `scene`, in order. systems within a stage run in
+    /// parallel, when they can, see `Systems::run_parallel`. the scene's change
+    /// tick is advanced first, see `Scene::tick`, its removed components trackers
+    /// cleared, see `Scene::clear_trackers`, and its `Time` resource updated.
+    /// systems are timed if there's a profiler, see `Schedule::set_profiler`, or
+    /// the scene has an enabled `Diagnostics` resource
+    pub fn run(&mut self, scene: &mut Scene)
+    {
+        if self.deny && !self.checked
+        {
+            let ambiguities = self.ambiguities();
+
+            if !ambiguities.is_empty()
+            {
+                let list = ambiguities
+                    .iter()
+                    .map(|amb| format!("\n  {}", amb))
+                    .collect::<String>();
+
+                panic!("systems have ambiguous execution order:{}", list);
+            }
+            self.checked = true;
+        }
+        scene.tick();
+        scene.clear_trackers();
+
+        let delta = update_time(scene);
+
+        for hook in &self.hooks
+        {
+            hook(scene);
+        }
+
+        let diagnostics = scene
+            .resource::<Diagnostics>()
+            .is_some_and(Diagnostics::is_enabled);
+        let profiling = diagnostics || self.profiler.is_some();
+        let profiler = &self.profiler;
+
+        // total duration of every system this frame, for the diagnostics
+        let mut timings = Vec::<(String, Duration)>::new();
+        let mut profile = |name: &str, time: Duration|
+        {
+            if let Some(Profiler(profiler)) = profiler
+            {
+                profiler(name, time);
+            }
+            if diagnostics
+            {
+                match timings.iter_mut().find(|(sys, _)| sys == name)
+                {
+                    Some((_, total)) => *total += time,
+                    None => timings.push((name.to_owned(), time)),
+                }
+            }
+        };
+
+        for Stage { systems, fixed, .. } in &mut self.stages
+        {
+            let steps = match fixed
+            {
+                // regular stages run exactly once...
+                None => 1,
+                // ...and fixed ones as many times as needed to catch up
+                Some(fixed) =>
+                {
+                    let steps = fixed.accumulate(delta);
+
+                    scene.insert_resource(FixedTime { step: fixed.step(), steps, alpha: fixed.alpha() });
+                    steps
+                }
+            };
+            for _ in 0..steps
+            {
+                systems.run_parallel_profiled(scene, if profiling { Some(&mut profile) } else { None });
+            }
+        }
+
+        if let Some(diagnostics) = scene.resource_mut::<Diagnostics>().filter(|_| diagnostics)
+        {
+            for (name, time) in timings
+            {
+                diagnostics.record(&name, time);
+            }
+        }
+    }
+
+    /// iterate the stages of this schedule, in execution order
+    pub fn stages(&self) -> impl Iterator<Item = StageLabel> + '_
+    {
+        self.stages
+            .iter()
+            .map(|stage| stage.label)
+    }
+
+    /// get the systems of a stage, or `None` if it doesn't exist
+    pub fn stage(&self, label: StageLabel) -> Option<&Systems>
+    {
+        self.stages
+            .iter()
+            .find(|stage| stage.label == label)
+            .map(|stage| &stage.systems)
+    }
+
+    /// insert an empty stage at index `i`
+    fn insert_stage(&mut self, i: usize, label: StageLabel, fixed: Option<FixedTimestep>) -> &mut Self
+    {
+        if self.stage(label).is_some()
+        {
+            panic!("stage `{}` already exists!", label.name());
+        }
+        let mut systems = Systems::new();
+
+        systems.set_shared_pool(self.pool.clone());
+        self.stages.insert(i, Stage { label, systems, fixed });
+        self
+    }
+
+    /// get the index of a stage, panicking if it doesn't exist
+    fn index(&self, label: StageLabel) -> usize
+    {
+        self.stages
+            .iter()
+            .position(|stage| stage.label == label)
+            .unwrap_or_else(|| panic!("stage `{}` doesn't exist!", label.name()))
+    }
+}
+
+impl fmt::Debug for Profiler
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str("Profiler")
+    }
+}
+
+impl fmt::Display for Ambiguity
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "`{}` and `{}` in stage `{}` conflict on ", self.first, self.second, self.stage.name())?;
+
+        for (i, name) in self.conflicts.iter().enumerate()
+        {
+            write!(f, "{}`{}`", if i == 0 { "" } else { ", " }, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// is `label` the label or name of `sys`?
+fn matches(sys: &(dyn System + Send), label: &str) -> bool
+{
+    sys.name() == label || sys.labels().contains(&label)
+}
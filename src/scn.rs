@@ -1,41 +1,1766 @@
-use crate::{ EntityMap, Entity, ArchetypeMap, CmpSet };
+use std::collections::HashMap;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use crate::{ EntityMap, Entity, EntityLocation, Archetype, ArchetypeChunk, ArchetypeMap, ColumnGuard, CmpId, CmpMeta, CmpSet, MutComponent, StaticCmpSet, Component, EcsError, Query, ReadOnlyQuery, QueryIter, ChunkQuery, QueryChunks, Registry, Resources, CommandQueue, Commands, QueryState, IntoSystem, System, TaskPool, ChunkView, Parent, Children };
+use crate::qry::{ check_access, par_for_each };
+use crate::hsh::IdHashMap;
 
 /// a container for entities and their components.
 ///
 /// responsible for (de)spawning and querying entities which
 /// are unique to an application and thus can be moved from
 /// scene to scene.
+///
+/// scenes are `Send`, so that they can be built on other
+/// threads, then merged through `Scene::append`
 #[derive(Debug, Default)]
 pub struct Scene
 {
     entities: EntityMap,
     archetypes: ArchetypeMap,
+    /// component types registered for the `_dyn` methods
+    components: HashMap<CmpId, CmpMeta>,
+    /// singleton data shared by systems, dropped with the scene
+    resources: Resources,
+    /// structural changes queued by systems
+    commands: CommandQueue,
+    /// components removed during this frame and the previous one
+    removed: TrackerLog<(Entity, CmpId)>,
+    /// entities spawned during this frame and the previous one
+    spawned: TrackerLog<Entity>,
+    /// entities despawned during this frame and the previous one
+    despawned: TrackerLog<Entity>,
+    /// callbacks registered through `Scene::on_add` and `Scene::on_remove`
+    hooks: SceneHooks,
+    /// bumped by every structural change, see `Scene::structural_version`. it's
+    /// shared with the queries' iterators, which check it in debug builds
+    version: AtomicU64,
 }
 
 impl Scene
 {
+    /// archetypes reserved by `Scene::with_capacity`, which is more than most
+    /// scenes end up with
+    const ARCHETYPE_HINT: usize = 32;
+
+    /// create a new, empty scene with room for `entities` entities in its entity
+    /// map and for a typical number of archetypes, ie. before building a big scene.
+    /// the archetypes' chunks are still allocated as entities are spawned, see
+    /// `Archetype::reserve`
+    pub fn with_capacity(entities: usize) -> Self
+    {
+        Self
+        {
+            entities: EntityMap::with_capacity(entities),
+            archetypes: ArchetypeMap::with_capacity(Self::ARCHETYPE_HINT),
+            ..Default::default()
+        }
+    }
+
     /// spawn a single entity into this scene with the given
-    /// components
-    pub fn spawn(&mut self, cmp: impl CmpSet) -> Entity
+    /// components. panics if `cmp` contains the same component
+    /// type more than once
+    pub fn spawn<T: CmpSet>(&mut self, cmp: T) -> Entity
+    {
+        self.spawn_mut(cmp).0
+    }
+
+    /// see `Scene::spawn`
+    ///
+    /// also returns a handle to the freshly spawned entity's components, to
+    /// mutate them right away without looking the entity up again
+    pub fn spawn_mut<T: CmpSet>(&mut self, cmp: T) -> (Entity, SpawnHandle<'_>)
     {
         // alloc a new entity ID
         let ent = Entity::next(1).start;
 
+        (ent, self.spawn_as(ent, cmp))
+    }
+
+    /// see `Scene::spawn`
+    ///
+    /// also returns where the entity's components are stored, for low-level tooling
+    /// that pokes at the archetypes right away. like the pointers of `Scene::component_mut_ptr`,
+    /// the location is invalidated by any later structural change
+    pub fn spawn_located<T: CmpSet>(&mut self, cmp: T) -> (Entity, EntityLocation)
+    {
+        let (ent, handle) = self.spawn_mut(cmp);
+
+        (ent, handle.location())
+    }
+
+    /// spawn many entities into this scene, one per component set in `batch`,
+    /// and return their IDs in the same order. IDs are allocated all at once, and
+    /// none at all if `batch` is empty
+    pub fn spawn_batch<T: CmpSet>(&mut self, batch: impl IntoIterator<Item = T>) -> Vec<Entity>
+    {
+        let batch = batch
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        // nothing to spawn
+        if batch.is_empty()
+        {
+            return Vec::new();
+        }
+        let start = Entity::next(batch.len() as u64).start.id();
+
+        batch
+            .into_iter()
+            .enumerate()
+            .map(|(i, cmp)|
+            {
+                // SAFETY: the range was just allocated
+                let ent = unsafe { Entity::from_u64(start + i as u64) };
+
+                self.spawn_as(ent, cmp);
+                ent
+            })
+            .collect()
+    }
+
+    /// spawn `n` entities into this scene, calling `f` with the index of each one
+    /// to get its components, and return their IDs in order. this is `Scene::spawn_batch`
+    /// without building the component sets up front, ie. to lay out a grid of tiles.
+    /// the archetype is only looked up once, unless the sets' components differ
+    pub fn spawn_with<T: CmpSet>(&mut self, n: usize, mut f: impl FnMut(usize) -> T) -> Vec<Entity>
+    {
+        // nothing to spawn
+        if n == 0
+        {
+            return Vec::new();
+        }
+        let start = Entity::next(n as u64).start.id();
+        let mut last = None;
+
+        *self.version.get_mut() += 1;
+
+        (0..n)
+            .map(|i|
+            {
+                // SAFETY: the range was just allocated
+                let ent = unsafe { Entity::from_u64(start + i as u64) };
+                let cmp = f(i);
+
+                // get or create archetype, if it's not the previous one
+                let arch = match last.filter(|id: &usize| self.archetypes.inner()[*id].types() == cmp.instance_types())
+                {
+                    Some(id) => &mut self.archetypes.inner_mut()[id],
+                    None =>
+                    {
+                        let arch = self.archetypes.get_or_insert_dyn(cmp.instance_types(), cmp.instance_metas());
+
+                        arch.reserve(n - i);
+                        last = Some(arch.id());
+                        arch
+                    }
+                };
+                Self::spawn_into(arch, &mut self.entities, &mut self.hooks, &mut self.commands, ent, cmp);
+                self.spawned.record(ent);
+                ent
+            })
+            .collect()
+    }
+
+    /// spawn `n` entities into this scene with copies of the same components, and
+    /// return their IDs in order, ie. tiles or particles whose initial state is
+    /// uniform. unlike `Scene::spawn_batch`, no component set is built per entity:
+    /// each copy is a bitwise copy of `cmp`, straight into the archetype
+    pub fn spawn_copy<T: CmpSet + Copy>(&mut self, cmp: &T, n: usize) -> Vec<Entity>
+    {
+        self.spawn_with(n, |_| *cmp)
+    }
+
+    /// spawn an entity with the given components, knowing its freshly allocated ID
+    pub(crate) fn spawn_as<T: CmpSet>(&mut self, ent: Entity, cmp: T) -> SpawnHandle<'_>
+    {
+        *self.version.get_mut() += 1;
+
         // get or create archetype
-        let arch = self.archetypes.get_or_insert(&cmp);
+        let arch = self.archetypes.get_or_insert_dyn(cmp.instance_types(), cmp.instance_metas());
+        let loc = Self::spawn_into(arch, &mut self.entities, &mut self.hooks, &mut self.commands, ent, cmp);
 
+        self.spawned.record(ent);
+
+        SpawnHandle { arch, loc }
+    }
+
+    /// spawn an entity with the given components into `arch`, which must be made of
+    /// exactly those components
+    fn spawn_into<T: CmpSet>(arch: &mut Archetype, entities: &mut EntityMap, hooks: &mut SceneHooks, queue: &mut CommandQueue, ent: Entity, cmp: T) -> EntityLocation
+    {
         // insert entity into archetype
         let loc = arch.insert(ent);
 
         // insert components into archetype
-        //cmp.insert(arch, loc);
+        unsafe { cmp.insert(arch, loc) };
+
+        // notify the components
+        arch.on_spawn(loc, arch.types());
+        hooks.added(arch, loc, arch.types(), queue);
+
+        // cache entity location
+        entities.insert(ent, loc);
+
+        loc
+    }
+
+    /// spawn an entity without any component, to be populated incrementally
+    /// with `Scene::add`. this is the idiomatic way to get an entity ID before
+    /// knowing its components, ie. to store it in another entity's component
+    ///
+    /// the empty archetype is only created on the first call, and its chunks
+    /// only hold entity IDs
+    pub fn spawn_empty(&mut self) -> Entity
+    {
+        self.spawn(())
+    }
+
+    /// see `Scene::spawn`
+    ///
+    /// returns an error instead of panicking if `cmp` contains the same
+    /// component type more than once
+    pub fn try_spawn<T: CmpSet>(&mut self, cmp: T) -> Result<Entity, EcsError>
+    {
+        if let Some(dup) = CmpMeta::find_duplicate(cmp.instance_metas())
+        {
+            return Err(EcsError::DuplicateComponent(dup.name()));
+        }
+        Ok(self.spawn(cmp))
+    }
+
+    /// see `Scene::spawn`
+    ///
+    /// components are given as `(ID, bytes)` pairs, where every ID must have been
+    /// registered through `Scene::register_component`. panics if a byte slice isn't
+    /// exactly the size of its component
+    ///
+    /// # Safety
+    /// every byte slice must be a valid, uniquely owned `id` value, which is moved
+    /// into the scene so it must not be dropped(or spawned again) by the caller
+    pub unsafe fn spawn_dyn(&mut self, cmp: &[(CmpId, &[u8])]) -> Entity
+    {
+        let cmp = cmp
+            .iter()
+            .map(|(id, bytes)| (self.registered(*id).clone(), *bytes))
+            .collect::<Vec<_>>();
+
+        self.spawn_raw(&cmp)
+    }
+
+    /// see `Scene::spawn_dyn`
+    ///
+    /// components are given as `(meta-data, bytes)` pairs instead, so they don't need
+    /// to be registered, ie. for a custom binary format or a cross-process loader.
+    /// panics if a byte slice isn't exactly the size of its component, or if `cmp`
+    /// contains the same component type more than once
+    ///
+    /// # Safety
+    /// every byte slice must be a valid, uniquely owned `meta` value, which is moved
+    /// into the scene so it must not be dropped(or spawned again) by the caller
+    pub unsafe fn spawn_raw(&mut self, cmp: &[(CmpMeta, &[u8])]) -> Entity
+    {
+        // validate everything up front, so that no slot is left uninitialized
+        for (meta, bytes) in cmp
+        {
+            assert_eq!(bytes.len(), meta.size(), "component `{}` has the wrong size!", meta.name());
+        }
+        // components being spawned
+        let mut metas = cmp
+            .iter()
+            .map(|(meta, _)| meta.clone())
+            .collect::<Vec<_>>();
+        metas.sort();
+        if let Some(dup) = CmpMeta::find_duplicate(&metas)
+        {
+            panic!("component set contains `{}` more than once!", dup.name());
+        }
+        let types = metas
+            .iter()
+            .map(CmpMeta::id)
+            .collect::<Vec<_>>();
+
+        // alloc a new entity ID
+        let ent = Entity::next(1).start;
+
+        *self.version.get_mut() += 1;
+
+        // get or create archetype
+        let arch = self.archetypes.get_or_insert_dyn(&types, &metas);
+
+        // insert entity into archetype
+        let loc = arch.insert(ent);
+
+        // insert components into archetype
+        for (meta, bytes) in cmp
+        {
+            unsafe { arch.write_dyn(loc, meta.id(), bytes) };
+        }
+
+        // notify the components
+        arch.on_spawn(loc, arch.types());
+        self.hooks.added(arch, loc, arch.types(), &mut self.commands);
 
         // cache entity location
         self.entities.insert(ent, loc);
+        self.spawned.record(ent);
 
         // return the entity
         ent
     }
+
+    /// move every entity of `other` into this scene, with its components and its
+    /// ID, leaving `other` without any entity. its resources stay where they are.
+    /// meant for scenes built on other threads, ie. with `Entity::reserve_block`
+    ///
+    /// the entities are recorded as spawned in this scene, and the `Scene::on_add`
+    /// hooks of this scene are called, but not `Component::on_spawn` again. panics
+    /// if an entity is in both scenes, before anything is moved
+    pub fn append(&mut self, other: &mut Scene)
+    {
+        // entities are unique across scenes, unless they were forged
+        for arch in other.archetypes.inner()
+        {
+            for ent in arch.chunks().iter().flat_map(ArchetypeChunk::entities)
+            {
+                assert!(!self.entities.contains(*ent), "entity {:?} is in both scenes!", ent);
+            }
+        }
+        *self.version.get_mut() += 1;
+        *other.version.get_mut() += 1;
+
+        for src in other.archetypes.inner_mut()
+        {
+            if src.is_empty()
+            {
+                continue;
+            }
+            // get or create the same archetype
+            let metas = src
+                .types()
+                .iter()
+                .map(|id| src.meta().get_dyn(*id).0.clone())
+                .collect::<Vec<_>>();
+            let dst = self.archetypes.get_or_insert_dyn(src.types(), &metas);
+
+            dst.reserve(src.len());
+
+            for chunk in 0..src.chunks().len()
+            {
+                // from the back, so that no entity has to fill a gap
+                while let Some(&ent) = src.chunks()[chunk].entities().last()
+                {
+                    let loc = EntityLocation::new(src.id(), chunk, src.chunks()[chunk].entities().len() - 1);
+                    let dst_loc = dst.insert(ent);
+
+                    // SAFETY: both archetypes have the same components, which are all moved
+                    unsafe { src.migrate(loc, dst, dst_loc, &[]) };
+
+                    self.hooks.added(dst, dst_loc, dst.types(), &mut self.commands);
+                    self.entities.insert(ent, dst_loc);
+                    self.spawned.record(ent);
+                    other.entities.remove(ent);
+                }
+            }
+        }
+    }
+
+    /// get or create the archetype made of exactly the components in `types`, allocating
+    /// room for `entity_count` more entities up front. meant for deserialization, which
+    /// knows every archetype's population: entities can then be spawned straight into it
+    /// with `Scene::spawn_dyn`, rather than one component at a time through `Scene::add`
+    ///
+    /// both `types` and `metas` MUST be sorted via their `Ord` traits, and describe the
+    /// same components
+    pub fn rebuild_archetype(&mut self, types: &[CmpId], metas: &[CmpMeta], entity_count: usize) -> &mut Archetype
+    {
+        debug_assert!(types.iter().copied().eq(metas.iter().map(CmpMeta::id)), "component types and meta-data don't match!");
+
+        *self.version.get_mut() += 1;
+
+        let arch = self.archetypes.get_or_insert_dyn(types, metas);
+
+        arch.reserve(entity_count);
+        arch
+    }
+
+    /// spawn a copy of an existing entity, cloning all of its components.
+    /// returns `None` if the entity isn't in this scene or one of its
+    /// components isn't cloneable(see `#[component(clone)]`)
+    pub fn clone_entity(&mut self, ent: Entity) -> Option<Entity>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        let arch = &mut self.archetypes.inner_mut()[loc.archetype()];
+
+        // entity can't be cloned
+        if !arch.is_cloneable()
+        {
+            return None;
+        }
+
+        // alloc a new entity ID
+        let copy = Entity::next(1).start;
+
+        *self.version.get_mut() += 1;
+
+        // insert entity into the same archetype, then clone its components
+        let copy_loc = arch.insert(copy);
+
+        unsafe { arch.clone_row(loc, arch, copy_loc) };
+
+        // notify the components
+        arch.on_spawn(copy_loc, arch.types());
+        self.hooks.added(arch, copy_loc, arch.types(), &mut self.commands);
+
+        // cache entity location
+        self.entities.insert(copy, copy_loc);
+        self.spawned.record(copy);
+
+        Some(copy)
+    }
+
+    /// spawn a copy of an entity from another scene(ie. a prefab "template"),
+    /// cloning all of its components into this scene. returns `None` if the
+    /// entity isn't in `template` or one of its components isn't cloneable.
+    ///
+    /// panics if one of the entity's components is dynamic(see `CmpMeta::new_dynamic`)
+    /// and isn't registered in this scene
+    pub fn instantiate(&mut self, template: &Scene, ent: Entity) -> Option<Entity>
+    {
+        // get location
+        let loc = template.entities.get(ent);
+
+        // entity isn't in the template scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        let src = &template.archetypes.inner()[loc.archetype()];
+
+        // entity can't be cloned
+        if !src.is_cloneable()
+        {
+            return None;
+        }
+
+        // components of the entity, which must all be known to this scene
+        let types = src.types();
+        let metas = types
+            .iter()
+            .map(|id| src.meta().get_dyn(*id).0.clone())
+            .collect::<Vec<_>>();
+
+        if let Some(meta) = metas.iter().find(|meta| meta.id().is_dynamic() && self.component(meta.id()).is_none())
+        {
+            panic!("cannot instantiate an entity with component `{}`, which isn't registered in this scene!", meta.name());
+        }
+
+        // alloc a new entity ID
+        let copy = Entity::next(1).start;
+
+        *self.version.get_mut() += 1;
+
+        // get or create archetype
+        let dst = self.archetypes.get_or_insert_dyn(types, &metas);
+
+        // insert entity into archetype, then clone its components
+        let dst_loc = dst.insert(copy);
+
+        unsafe { src.clone_row(loc, dst, dst_loc) };
+
+        // notify the components
+        dst.on_spawn(dst_loc, dst.types());
+        self.hooks.added(dst, dst_loc, dst.types(), &mut self.commands);
+
+        // cache entity location
+        self.entities.insert(copy, dst_loc);
+        self.spawned.record(copy);
+
+        Some(copy)
+    }
+
+    /// despawn a single entity from this scene, dropping all of its
+    /// components. returns false if the entity isn't in this scene
+    pub fn despawn(&mut self, ent: Entity) -> bool
+    {
+        // get location
+        let mut loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return false;
+        }
+        // take it out of the hierarchy, which may move it
+        let meta = self.archetypes.inner()[loc.archetype()].meta();
+        let (parent, children) = (meta.contains::<Parent>(), meta.contains::<Children>());
+        if parent || children
+        {
+            self.detach(ent, parent, children);
+
+            loc = self.entities.get(ent);
+        }
+        *self.version.get_mut() += 1;
+
+        let arch = &mut self.archetypes.inner_mut()[loc.archetype()];
+
+        // notify the components
+        arch.on_despawn(loc, arch.types());
+        self.hooks.removed(arch, loc, arch.types(), &mut self.commands);
+        self.removed.record_all(arch.types().iter().map(|id| (ent, *id)));
+        self.despawned.record(ent);
+
+        // remove from archetype, and fix up the location of the entity that filled its gap
+        if let Some(moved) = arch.remove(loc)
+        {
+            self.entities.insert(moved, loc);
+        }
+        self.entities.remove(ent);
+
+        true
+    }
+
+    /// add components to an existing entity, moving it to another archetype.
+    /// components that the entity already has are overwritten. returns false
+    /// if the entity isn't in this scene
+    pub fn add<T: CmpSet>(&mut self, ent: Entity, cmp: T) -> bool
+    {
+        let metas = cmp.instance_metas().to_vec();
+
+        self.add_raw(ent, metas, |arch, loc| unsafe { cmp.insert(arch, loc) })
+    }
+
+    /// see `Scene::add`
+    ///
+    /// components are given as `(ID, bytes)` pairs, where every ID must have been
+    /// registered through `Scene::register_component`. panics if a byte slice isn't
+    /// exactly the size of its component
+    ///
+    /// # Safety
+    /// every byte slice must be a valid, uniquely owned `id` value, which is moved
+    /// into the scene so it must not be dropped(or added again) by the caller
+    pub unsafe fn add_dyn(&mut self, ent: Entity, cmp: &[(CmpId, &[u8])]) -> bool
+    {
+        let mut metas = cmp
+            .iter()
+            .map(|(id, _)| self.registered(*id).clone())
+            .collect::<Vec<_>>();
+
+        // validate everything up front, before the entity is moved to its new archetype
+        for (meta, (_, bytes)) in metas.iter().zip(cmp)
+        {
+            assert_eq!(bytes.len(), meta.size(), "component `{}` has the wrong size!", meta.name());
+        }
+        metas.sort();
+
+        self.add_raw(ent, metas, |arch, loc| for (id, bytes) in cmp
+        {
+            unsafe { arch.write_dyn(loc, *id, bytes) }
+        })
+    }
+
+    /// add a default-constructed `T` component to an existing entity, see
+    /// `Scene::add`. unlike the latter, a `T` that the entity already has is
+    /// left untouched
+    pub fn add_default<T: Component + Default>(&mut self, ent: Entity) -> bool
+    {
+        if self.has::<T>(ent)
+        {
+            return true;
+        }
+        self.add(ent, T::default())
+    }
+
+    /// add a tag(see `tags!`) to an existing entity, see `Scene::add_default`
+    pub fn tag<T: Component + Default>(&mut self, ent: Entity) -> bool
+    {
+        self.add_default::<T>(ent)
+    }
+
+    /// add a default-constructed component to an existing entity, knowing only
+    /// its meta-data, see `Scene::add_default`. returns false if the entity isn't
+    /// in this scene or the component type has no default function(see
+    /// `#[component(default)]`)
+    pub fn add_default_dyn(&mut self, ent: Entity, meta: &CmpMeta) -> bool
+    {
+        if meta.default_fn().is_none()
+        {
+            return false;
+        }
+        let id = meta.id();
+
+        // already has it
+        if self.location(ent).is_some_and(|loc| self.archetypes.inner()[loc.archetype()].meta().contains_dyn(id))
+        {
+            return true;
+        }
+
+        self.add_raw(ent, vec![meta.clone()], |arch, loc| unsafe { arch.write_default_dyn(loc, id) })
+    }
+
+    /// see `Scene::add`
+    ///
+    /// `metas` MUST be sorted via its `Ord` trait, and `insert` must write every
+    /// one of those components into the given archetype and location
+    fn add_raw(&mut self, ent: Entity, mut metas: Vec<CmpMeta>, insert: impl FnOnce(&mut Archetype, EntityLocation)) -> bool
+    {
+        // components being added must be unique
+        if let Some(dup) = CmpMeta::find_duplicate(&metas)
+        {
+            panic!("component set contains `{}` more than once!", dup.name());
+        }
+
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return false;
+        }
+        let src = &self.archetypes.inner()[loc.archetype()];
+
+        // components being added, and those among them that the entity already has
+        let added = metas
+            .iter()
+            .map(CmpMeta::id)
+            .collect::<Vec<_>>();
+        let overwrite = added
+            .iter()
+            .filter(|id| src.types().binary_search(id).is_ok())
+            .copied()
+            .collect::<Vec<_>>();
+
+        // components of the entity, after being added
+        metas.extend(src
+            .types()
+            .iter()
+            .filter(|id| !overwrite.contains(id))
+            .map(|id| src.meta().get_dyn(*id).0.clone()));
+        metas.sort();
+        let types = metas
+            .iter()
+            .map(CmpMeta::id)
+            .collect::<Vec<_>>();
+
+        // notify the components being overwritten
+        src.on_despawn(loc, &overwrite);
+        self.hooks.removed(src, loc, &overwrite, &mut self.commands);
+        self.removed.record_all(overwrite.iter().map(|id| (ent, *id)));
+
+        *self.version.get_mut() += 1;
+
+        // get or create archetype
+        let dst = self.archetypes.get_or_insert_dyn(&types, &metas).id();
+
+        // entity already has every component, overwrite them in place
+        if dst == loc.archetype()
+        {
+            let arch = &mut self.archetypes.inner_mut()[dst];
+
+            unsafe { arch.drop_components(loc, &overwrite) };
+            insert(arch, loc);
+
+            arch.on_spawn(loc, &added);
+            self.hooks.added(arch, loc, &added, &mut self.commands);
+
+            return true;
+        }
+        let (src, dst) = self.archetypes.pair_mut(loc.archetype(), dst);
+
+        // move entity to its new archetype, then insert the new components
+        let dst_loc = dst.insert(ent);
+        let moved = unsafe { src.migrate(loc, dst, dst_loc, &overwrite) };
+
+        insert(dst, dst_loc);
+
+        // notify the components
+        dst.on_spawn(dst_loc, &added);
+        self.hooks.added(dst, dst_loc, &added, &mut self.commands);
+
+        // cache entity locations
+        if let Some(moved) = moved
+        {
+            self.entities.insert(moved, loc);
+        }
+        self.entities.insert(ent, dst_loc);
+
+        true
+    }
+
+    /// add the components returned by `f` to every entity matching `Q`, see `Scene::add`.
+    /// unlike calling the latter for every entity, the destination of each archetype
+    /// matching `Q` is looked up once, then all of its entities are moved there in one
+    /// pass. returns the number of entities the components were added to
+    pub fn add_to_all<Q: Query, T: StaticCmpSet>(&mut self, mut f: impl FnMut(Entity) -> T) -> usize
+    {
+        let metas = T::metas();
+        let added = T::types();
+
+        // components being added must be unique
+        if let Some(dup) = CmpMeta::find_duplicate(metas)
+        {
+            panic!("component set contains `{}` more than once!", dup.name());
+        }
+
+        // archetypes created along the way might match `Q` too, so list them first. so
+        // do their entities, as those of one archetype might be moved to another that's
+        // yet to be processed. last entities first, so that none of the others fill the
+        // gaps they leave
+        let srcs = self.archetypes
+            .inner()
+            .iter()
+            .filter(|arch| !arch.is_empty() && Q::matches(arch))
+            .map(|arch| (arch.id(), arch
+                .chunks()
+                .iter()
+                .flat_map(ArchetypeChunk::entities)
+                .rev()
+                .copied()
+                .collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        let mut n = 0;
+        for (src, ents) in srcs
+        {
+            let arch = &self.archetypes.inner()[src];
+
+            // components being added that the entities already have
+            let overwrite = added
+                .iter()
+                .filter(|id| arch.types().binary_search(id).is_ok())
+                .copied()
+                .collect::<Vec<_>>();
+
+            // components of the entities, after being added
+            let mut dst_metas = metas.to_vec();
+            dst_metas.extend(arch
+                .types()
+                .iter()
+                .filter(|id| !overwrite.contains(id))
+                .map(|id| arch.meta().get_dyn(*id).0.clone()));
+            dst_metas.sort();
+            let types = dst_metas
+                .iter()
+                .map(CmpMeta::id)
+                .collect::<Vec<_>>();
+
+            *self.version.get_mut() += 1;
+
+            // get or create archetype, with room for every entity
+            let dst = self.archetypes.get_or_insert_dyn(&types, &dst_metas).id();
+
+            n += ents.len();
+            if dst != src
+            {
+                self.archetypes.inner_mut()[dst].reserve(ents.len());
+            }
+            for ent in ents
+            {
+                let cmp = f(ent);
+                let loc = self.entities.get(ent);
+
+                assert_eq!(cmp.instance_types(), added, "`add_to_all` can't add components that aren't known statically!");
+
+                // notify the components being overwritten
+                self.archetypes.inner()[src].on_despawn(loc, &overwrite);
+                self.hooks.removed(&self.archetypes.inner()[src], loc, &overwrite, &mut self.commands);
+                self.removed.record_all(overwrite.iter().map(|id| (ent, *id)));
+
+                // entities already have every component, overwrite them in place
+                if dst == src
+                {
+                    let arch = &mut self.archetypes.inner_mut()[src];
+
+                    unsafe
+                    {
+                        arch.drop_components(loc, &overwrite);
+                        cmp.insert(arch, loc);
+                    }
+                    arch.on_spawn(loc, added);
+                    self.hooks.added(arch, loc, added, &mut self.commands);
+
+                    continue;
+                }
+                let (src, dst) = self.archetypes.pair_mut(src, dst);
+
+                // move entity to its new archetype, then insert the new components
+                let dst_loc = dst.insert(ent);
+                let moved = unsafe { src.migrate(loc, dst, dst_loc, &overwrite) };
+
+                unsafe { cmp.insert(dst, dst_loc) };
+
+                // notify the components
+                dst.on_spawn(dst_loc, added);
+                self.hooks.added(dst, dst_loc, added, &mut self.commands);
+
+                // cache entity locations
+                if let Some(moved) = moved
+                {
+                    self.entities.insert(moved, loc);
+                }
+                self.entities.insert(ent, dst_loc);
+            }
+        }
+        n
+    }
+
+    /// remove the components in `T` from an existing entity, moving it to another
+    /// archetype. returns false if the entity isn't in this scene or doesn't have
+    /// every component in `T`
+    pub fn remove<T: StaticCmpSet>(&mut self, ent: Entity) -> bool
+    {
+        // get location
+        let mut loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return false;
+        }
+        let src = &self.archetypes.inner()[loc.archetype()];
+
+        // components being removed
+        let removed = T::types();
+
+        // entity doesn't have them all
+        if !removed.iter().all(|id| src.types().binary_search(id).is_ok())
+        {
+            return false;
+        }
+        // nothing to remove
+        if removed.is_empty()
+        {
+            return true;
+        }
+        // take it out of the hierarchy, which may move it
+        let (parent, children) = (removed.contains(&Parent::ID), removed.contains(&Children::ID));
+        if parent || children
+        {
+            self.detach(ent, parent, children);
+
+            loc = self.entities.get(ent);
+        }
+        let src = &self.archetypes.inner()[loc.archetype()];
+
+        // components of the entity, after being removed
+        let types = src
+            .types()
+            .iter()
+            .filter(|id| !removed.contains(id))
+            .copied()
+            .collect::<Vec<CmpId>>();
+        let metas = types
+            .iter()
+            .map(|id| src.meta().get_dyn(*id).0.clone())
+            .collect::<Vec<_>>();
+
+        // notify the components being removed
+        src.on_despawn(loc, removed);
+        self.hooks.removed(src, loc, removed, &mut self.commands);
+        self.removed.record_all(removed.iter().map(|id| (ent, *id)));
+
+        *self.version.get_mut() += 1;
+
+        // get or create archetype
+        let dst = self.archetypes.get_or_insert_dyn(&types, &metas).id();
+        let (src, dst) = self.archetypes.pair_mut(loc.archetype(), dst);
+
+        // move entity to its new archetype, dropping the removed components
+        let dst_loc = dst.insert(ent);
+        let moved = unsafe { src.migrate(loc, dst, dst_loc, &[]) };
+
+        // cache entity locations
+        if let Some(moved) = moved
+        {
+            self.entities.insert(moved, loc);
+        }
+        self.entities.insert(ent, dst_loc);
+
+        true
+    }
+
+    /// reclaim the memory of every archetype that's empty of entities, ie. a rare
+    /// combination of components that only existed briefly. archetypes themselves
+    /// are kept, since their index is part of `EntityLocation`, and reallocate
+    /// chunks when entities are moved back in. returns the number of archetypes
+    /// whose chunks were deallocated
+    pub fn prune_empty_archetypes(&mut self) -> usize
+    {
+        self.archetypes
+            .inner_mut()
+            .iter_mut()
+            .map(Archetype::release_if_empty)
+            .filter(|released| *released)
+            .count()
+    }
+
+    /// get the entity with the ID `id`, ie. received over the network, if it's alive
+    /// within this scene. this is the checked counterpart of `Entity::from_u64`
+    pub fn entity(&self, id: u64) -> Option<Entity>
+    {
+        // SAFETY: the entity is only returned if it was spawned into this scene
+        let ent = unsafe { Entity::from_u64(id) };
+
+        self.location(ent).map(|_| ent)
+    }
+
+    /// get the storage location of an entity's components, or `None` if it
+    /// isn't in this scene. it's invalidated by the next structural change
+    pub fn location(&self, ent: Entity) -> Option<EntityLocation>
+    {
+        Some(self.entities.get(ent)).filter(|loc| *loc != EntityLocation::NULL)
+    }
+
+    /// get the entity stored at a location, or `None` if there's none, ie. the
+    /// location is out of bounds. this is the inverse of `Scene::location`, for
+    /// code working with locations, ie. chunk by chunk
+    pub fn entity_at_location(&self, loc: EntityLocation) -> Option<Entity>
+    {
+        self.archetypes
+            .inner()
+            .get(loc.archetype())?
+            .chunks()
+            .get(loc.chunk())?
+            .entities()
+            .get(loc.index())
+            .copied()
+    }
+
+    /// get an entity's `T` component. returns `None` if the entity isn't
+    /// in this scene or doesn't have that component
+    pub fn get<T: Component>(&self, ent: Entity) -> Option<&T>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        self.archetypes.inner()[loc.archetype()].get(loc)
+    }
+
+    /// does an entity have a `T` component? returns false if the entity isn't
+    /// in this scene
+    pub fn has<T: Component>(&self, ent: Entity) -> bool
+    {
+        self.location(ent)
+            .is_some_and(|loc| self.archetypes.inner()[loc.archetype()].meta().contains::<T>())
+    }
+
+    /// see `Scene::get`
+    ///
+    /// returns an error telling apart an entity that isn't in this scene from one
+    /// that doesn't have that component
+    pub fn try_get<T: Component>(&self, ent: Entity) -> Result<&T, EcsError>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return Err(EcsError::EntityNotFound(ent));
+        }
+        self.archetypes.inner()[loc.archetype()]
+            .get(loc)
+            .ok_or(EcsError::ComponentMissing(ent, T::NAME))
+    }
+
+    /// get an entity's `T` component. returns `None` if the entity isn't
+    /// in this scene or doesn't have that component
+    pub fn get_mut<T: Component>(&mut self, ent: Entity) -> Option<&mut T>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        self.archetypes.inner_mut()[loc.archetype()].get_mut(loc)
+    }
+
+    /// get several components of an entity at once, looking it up only once, ie.
+    /// `(&Pos, &Vel)` or `(&Pos, Option<&Vel>)`. returns `None` if the entity isn't
+    /// in this scene or doesn't match `Q`
+    pub fn get_components<Q: ReadOnlyQuery>(&self, ent: Entity) -> Option<Q::Item<'_>>
+    {
+        // SAFETY: the scene is borrowed, and `Q` only reads from it
+        unsafe { self.get_components_raw::<Q>(ent) }
+    }
+
+    /// see `Scene::get_components`
+    ///
+    /// panics if `Q` accesses the same component mutably more than once, like
+    /// `Scene::query`
+    pub fn get_components_mut<Q: Query>(&mut self, ent: Entity) -> Option<Q::Item<'_>>
+    {
+        check_access::<Q>();
+
+        // SAFETY: the scene is exclusively borrowed, and `Q`'s accesses are disjoint
+        unsafe { self.get_components_raw::<Q>(ent) }
+    }
+
+    /// see `Scene::get_components`
+    ///
+    /// # Safety
+    /// `Q`'s items must not alias any other reference to the scene's components
+    unsafe fn get_components_raw<Q: Query>(&self, ent: Entity) -> Option<Q::Item<'_>>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        let arch = &self.archetypes.inner()[loc.archetype()];
+
+        // entity is missing some components
+        if !Q::matches(arch)
+        {
+            return None;
+        }
+        let chunk = &arch.chunks()[loc.chunk()];
+
+        Some(Q::get(Q::fetch(arch, chunk), loc.index()))
+    }
+
+    /// call `f` with every entity that has a `T` component, and that component.
+    /// the scene can't be changed while it's iterated, so `f` is also given a
+    /// `Commands` buffer, ie. to despawn entities, applied once iteration is done
+    pub fn for_each_mut<T: MutComponent>(&mut self, mut f: impl FnMut(Entity, &mut T, &mut Commands))
+    {
+        let mut cmds = Commands::new(&mut self.commands);
+
+        for arch in self.archetypes.inner_mut()
+        {
+            // archetype doesn't have `T`
+            if !arch.meta().contains::<T>()
+            {
+                continue;
+            }
+            for chunk in arch
+            {
+                let (ents, cmps) = chunk.entities_components_mut::<T>();
+
+                for (ent, cmp) in ents.iter().zip(cmps)
+                {
+                    f(*ent, cmp, &mut cmds);
+                }
+            }
+        }
+        self.apply_commands();
+    }
+
+    /// iterate the components of every entity matching `Q`, ie. `(Entity, &mut Pos, &Vel)`.
+    /// panics if `Q` accesses the same component mutably more than once
+    pub fn query<Q: Query>(&mut self) -> QueryIter<'_, Q>
+    {
+        QueryIter::new(self.archetypes.inner(), 0).tracking(&self.version)
+    }
+
+    /// see `Scene::query`
+    ///
+    /// iterates every matching chunk at once instead, yielding its entities along
+    /// with its components as parallel slices, ie. `(&[Entity], (&mut [Pos], &[Vel]))`
+    /// for `(&mut Pos, &Vel)`, so that inner loops over plain slices get vectorized
+    pub fn query_chunks<Q: ChunkQuery>(&mut self) -> QueryChunks<'_, Q>
+    {
+        self.query::<Q>().into_chunks()
+    }
+
+    /// see `Scene::query_chunks`
+    ///
+    /// calls `f` with the entities and components of every matching chunk
+    pub fn for_each_chunk<Q: ChunkQuery>(&mut self, mut f: impl for<'a> FnMut(&'a [Entity], Q::Slice<'a>))
+    {
+        for (ents, cmps) in self.query_chunks::<Q>()
+        {
+            f(ents, cmps);
+        }
+    }
+
+    /// see `Scene::structural_version`, shared with the queries' iterators
+    pub(crate) fn version(&self) -> &AtomicU64
+    {
+        &self.version
+    }
+
+    /// create a cache of the archetypes matching `Q`, to iterate the same query
+    /// every frame without matching every archetype again, see `QueryState::iter`
+    pub fn query_state<Q: Query>(&self) -> QueryState<Q>
+    {
+        let mut state = QueryState::new();
+
+        state.update(&self.archetypes);
+        state
+    }
+
+    /// see `Scene::query`
+    ///
+    /// iterates the chunks containing every component in `B`, as a struct of slices
+    /// per chunk(see `ChunkView`), ie. `BodyView { pos: &mut [Pos], vel: &mut [Vel] }`
+    /// for a `#[derive(Bundle)] struct Body`. panics if `B` contains the same component
+    /// type more than once
+    pub fn view<B: ChunkView>(&mut self) -> impl Iterator<Item = B::View<'_>>
+    {
+        if let Some(dup) = CmpMeta::find_duplicate(B::metas())
+        {
+            panic!("component set contains `{}` more than once!", dup.name());
+        }
+        let ids = B::types();
+        let mut guard = ColumnGuard::new(B::metas().iter().map(|meta| (meta.clone(), true)).collect());
+
+        self.archetypes
+            .inner()
+            .iter()
+            .filter(move |arch| ids.iter().all(|id| arch.meta().contains_dyn(*id)))
+            .inspect(move |arch| guard.acquire(arch))
+            .flat_map(|arch| arch.chunks().iter().filter(|chunk| !chunk.is_empty()))
+            // SAFETY: the scene is exclusively borrowed, and every chunk is viewed once
+            .map(|chunk| unsafe { B::view(chunk) })
+    }
+
+    /// see `Scene::query`
+    ///
+    /// calls `f` with the components of every entity matching `Q`, spreading the
+    /// matching chunks across the threads of `pool`, ie. a `ScopedPool`
+    pub fn par_for_each<Q: Query, F>(&mut self, pool: &dyn TaskPool, f: F)
+    where
+        F: for<'a> Fn(Q::Item<'a>) + Sync
+    {
+        // SAFETY: the scene is exclusively borrowed
+        unsafe { par_for_each::<Q, F>(self.archetypes.inner(), 0, pool, f) }
+    }
+
+    /// see `Scene::query`
+    ///
+    /// change filters like `Changed<T>` and `Added<T>` only yield the entities that
+    /// changed after the `last_run` tick, which is usually the one returned by
+    /// `Scene::tick` the last time the same code ran
+    pub fn query_since<Q: Query>(&mut self, last_run: u64) -> QueryIter<'_, Q>
+    {
+        QueryIter::new(self.archetypes.inner(), last_run).tracking(&self.version)
+    }
+
+    /// advance this scene's change tick, returning the previous one. changes made
+    /// from now on are stamped with a later tick, so passing the returned tick to
+    /// `Scene::query_since` finds them. `Schedule::run` calls this once per run
+    pub fn tick(&mut self) -> u64
+    {
+        self.archetypes.advance_tick()
+    }
+
+    /// the tick that changes are currently stamped with, see `Scene::tick`
+    #[inline]
+    pub fn change_tick(&self) -> u64
+    {
+        self.archetypes.change_tick()
+    }
+
+    /// iterate the entities whose `T` component was removed, whether through
+    /// `Scene::remove`, `Scene::despawn` or overwritten by `Scene::add`, during
+    /// this frame or the previous one. the renderer, for example, releases the
+    /// GPU resources of removed meshes with it. see `Scene::clear_trackers`
+    pub fn removed<T: Component>(&self) -> impl Iterator<Item = Entity> + '_
+    {
+        self.removed
+            .iter()
+            .filter(|(_, id)| *id == T::ID)
+            .map(|(ent, _)| *ent)
+    }
+
+    /// iterate the entities spawned during this frame or the previous one, in
+    /// the order they were spawned, whether one at a time or in batches, ie. to
+    /// replicate them over the network. they may have been despawned since. see
+    /// `Scene::clear_trackers`
+    pub fn spawned(&self) -> impl Iterator<Item = Entity> + '_
+    {
+        self.spawned.iter().copied()
+    }
+
+    /// iterate the entities despawned during this frame or the previous one, in
+    /// the order they were despawned, see `Scene::spawned`
+    pub fn despawned(&self) -> impl Iterator<Item = Entity> + '_
+    {
+        self.despawned.iter().copied()
+    }
+
+    /// start a new frame for `Scene::removed`, `Scene::spawned` and
+    /// `Scene::despawned`, forgetting the changes made before the previous call.
+    /// `Schedule::run` calls this once per run, so that changes are seen by
+    /// systems running after them that frame and by those running before them
+    /// the next
+    pub fn clear_trackers(&mut self)
+    {
+        self.removed.update();
+        self.spawned.update();
+        self.despawned.update();
+    }
+
+    /// call `f` every time a `T` component is inserted into this scene, right
+    /// after it's inserted, whether it's through `Scene::spawn`, `Scene::add` or
+    /// any of their variants. it's also called for components that overwrite
+    /// existing ones, after the latter's `Scene::on_remove` hooks. unlike
+    /// `Component::on_spawn`, it's registered per scene and may capture state,
+    /// ie. a physics engine's handle
+    ///
+    /// hooks can't change the scene's structure while it's being changed, so
+    /// they're given `Commands` instead, applied with the scene's other commands,
+    /// see `Scene::apply_commands`
+    pub fn on_add<T: Component>(&mut self, mut f: impl FnMut(Entity, &mut T, &mut Commands) + Send + Sync + 'static) -> &mut Self
+    {
+        // SAFETY: hooks are only called with pointers to `T` components
+        self.hooks.add.entry(T::ID).or_default().push(Box::new(move |ent, ptr, cmds| f(ent, unsafe { &mut *ptr.cast::<T>() }, cmds)));
+        self
+    }
+
+    /// call `f` every time a `T` component is dropped from this scene, right
+    /// before it's dropped, whether it's through `Scene::despawn`, `Scene::remove`,
+    /// or overwritten by `Scene::add`. see `Scene::on_add`
+    pub fn on_remove<T: Component>(&mut self, mut f: impl FnMut(Entity, &mut T, &mut Commands) + Send + Sync + 'static) -> &mut Self
+    {
+        // SAFETY: hooks are only called with pointers to `T` components
+        self.hooks.remove.entry(T::ID).or_default().push(Box::new(move |ent, ptr, cmds| f(ent, unsafe { &mut *ptr.cast::<T>() }, cmds)));
+        self
+    }
+
+    /// call `f` with the ID and component types of every archetype created in
+    /// this scene from now on, see `ArchetypeMap::on_new_archetype`. archetypes
+    /// are created by structural changes, so `f` can't access the scene
+    pub fn on_new_archetype(&mut self, f: impl FnMut(usize, &[CmpId]) + Send + Sync + 'static) -> &mut Self
+    {
+        self.archetypes.on_new_archetype(f);
+        self
+    }
+
+    /// run a single system over this scene, then apply the commands it
+    /// issued. see `Systems` to run many
+    pub fn run<M>(&mut self, sys: impl IntoSystem<M>)
+    {
+        sys.into_system().run(self);
+
+        self.apply_commands();
+    }
+
+    /// apply the commands issued by systems so far, in order
+    pub fn apply_commands(&mut self)
+    {
+        // commands can issue more commands
+        while !self.commands.is_empty()
+        {
+            for cmd in self.commands.take()
+            {
+                cmd(self);
+            }
+        }
+    }
+
+    /// get every archetype in the scene behind `scene`, without borrowing the rest
+    /// of it: systems running in parallel(see `System::run_shared`) only ever
+    /// borrow the fields they access
+    ///
+    /// # Safety
+    /// `scene` must be valid for `'a`, and the archetypes not mutably borrowed
+    pub(crate) unsafe fn archetypes_raw<'a>(scene: *const Scene) -> &'a [Archetype]
+    {
+        (*scene).archetypes.inner()
+    }
+
+    /// get the resources of the scene behind `scene`, see `Scene::archetypes_raw`
+    ///
+    /// # Safety
+    /// `scene` must be valid for `'a`, and the resources not mutably borrowed
+    pub(crate) unsafe fn resources_raw<'a>(scene: *const Scene) -> &'a Resources
+    {
+        &(*scene).resources
+    }
+
+    /// get the command queue of the scene behind `scene`, see `Scene::archetypes_raw`
+    ///
+    /// # Safety
+    /// `scene` must be valid for `'a`, and the queue not borrowed anywhere else
+    pub(crate) unsafe fn commands_raw<'a>(scene: *mut Scene) -> &'a mut CommandQueue
+    {
+        &mut (*scene).commands
+    }
+
+    /// see `Scene::query`
+    ///
+    /// also returns this scene's resources, which are stored apart from the
+    /// components and can thus be borrowed while the query is iterated
+    pub fn query_with_resources<Q: Query>(&mut self) -> (QueryIter<'_, Q>, &mut Resources)
+    {
+        (QueryIter::new(self.archetypes.inner(), 0).tracking(&self.version), &mut self.resources)
+    }
+
+    /// insert a resource into this scene, returning the previous resource of the
+    /// same type if there was one. see `Resources`
+    pub fn insert_resource<T: Send + 'static>(&mut self, res: T) -> Option<T>
+    {
+        self.resources.insert(res)
+    }
+
+    /// get this scene's resource of type `T`, if any
+    pub fn resource<T: 'static>(&self) -> Option<&T>
+    {
+        self.resources.get()
+    }
+
+    /// get this scene's resource of type `T`, if any
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T>
+    {
+        self.resources.get_mut()
+    }
+
+    /// remove this scene's resource of type `T`, returning it if there was one
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T>
+    {
+        self.resources.remove()
+    }
+
+    /// get every resource within this scene
+    #[inline]
+    pub fn resources(&self) -> &Resources
+    {
+        &self.resources
+    }
+
+    /// get every resource within this scene
+    #[inline]
+    pub fn resources_mut(&mut self) -> &mut Resources
+    {
+        &mut self.resources
+    }
+
+    /// overwrite an entity's `T` component, only if `value` differs from it.
+    /// returns true if it was written, or false if it was identical or the entity
+    /// isn't in this scene or doesn't have that component
+    pub fn set_if_changed<T: Component + PartialEq>(&mut self, ent: Entity, value: T) -> bool
+    {
+        // compare immutably first, so identical values don't count as a change
+        if !self.get::<T>(ent).is_some_and(|cmp| *cmp != value)
+        {
+            return false;
+        }
+        if let Some(cmp) = self.get_mut::<T>(ent)
+        {
+            *cmp = value;
+        }
+        true
+    }
+
+    /// overwrite the existing `T` component of many entities, ie. after deserializing.
+    /// entities that aren't in this scene or don't have that component are skipped.
+    /// returns the number of components written
+    pub fn set_many<T: Component, I: IntoIterator<Item = (Entity, T)>>(&mut self, iter: I) -> usize
+    {
+        let mut n = 0;
+        // (ID, has `T`) of the last archetype seen, as consecutive entities tend to share one
+        let mut last = (usize::MAX, false);
+
+        for (ent, cmp) in iter
+        {
+            // get location
+            let loc = self.entities.get(ent);
+
+            // entity isn't in this scene
+            if loc == EntityLocation::NULL
+            {
+                continue;
+            }
+            let arch = &mut self.archetypes.inner_mut()[loc.archetype()];
+
+            if last.0 != loc.archetype()
+            {
+                last = (loc.archetype(), arch.meta().contains::<T>());
+            }
+            // entity has the component
+            if last.1 && arch.set(loc, cmp)
+            {
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// get the bytes of an entity's `id` component. returns `None` if the entity
+    /// isn't in this scene or doesn't have that component
+    ///
+    /// the bytes of padding are uninitialized: only components without padding can
+    /// be read back as a `&[u8]`
+    pub fn get_dyn(&self, ent: Entity, id: CmpId) -> Option<&[MaybeUninit<u8>]>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        let arch = &self.archetypes.inner()[loc.archetype()];
+
+        // entity doesn't have the component
+        if arch.types().binary_search(&id).is_err()
+        {
+            return None;
+        }
+        Some(arch.get_dyn(loc, id))
+    }
+
+    /// iterate every entity in this scene along with the raw bytes of each of its
+    /// components, as a format-agnostic snapshot for a custom serializer, a network
+    /// replicator or a debugger. `Scene::spawn_raw` is the other way around, though
+    /// only for components that can be copied bit for bit, ie. without heap data
+    ///
+    /// the bytes include those of padding, which are uninitialized: only components
+    /// without padding can be read back as a `&[u8]`
+    pub fn dump_raw(&self) -> impl Iterator<Item = (Entity, RawComponents<'_>)>
+    {
+        self.archetypes
+            .inner()
+            .iter()
+            .flat_map(|arch| arch.chunks().iter().map(move |chunk| (arch, chunk)))
+            .flat_map(|(arch, chunk)|
+            {
+                // `(ID, size, column)` of every component in the chunk
+                let columns = arch
+                    .types()
+                    .iter()
+                    .map(|id| (*id, arch.meta().get_dyn(*id).0.size(), chunk.components_dyn(*id)))
+                    .collect::<Vec<_>>();
+
+                chunk
+                    .entities()
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, ent)|
+                    {
+                        let cmp = columns
+                            .iter()
+                            .map(|(id, size, column)| (*id, &column[i * size..(i + 1) * size]))
+                            .collect();
+
+                        (*ent, cmp)
+                    })
+            })
+    }
+
+    /// get a raw pointer to an entity's `id` component, along with the scene's
+    /// `Scene::structural_version` at the time. returns `None` if the entity
+    /// isn't in this scene or doesn't have that component
+    ///
+    /// meant for FFI hosts, which can cache the pointer for as long as the
+    /// version stays the same: any structural change may move the component
+    pub fn component_mut_ptr(&mut self, ent: Entity, id: CmpId) -> Option<(*mut u8, u64)>
+    {
+        // get location
+        let loc = self.entities.get(ent);
+
+        // entity isn't in this scene
+        if loc == EntityLocation::NULL
+        {
+            return None;
+        }
+        let arch = &self.archetypes.inner()[loc.archetype()];
+
+        // entity doesn't have the component
+        if arch.types().binary_search(&id).is_err()
+        {
+            return None;
+        }
+        // writes through the pointer can't be tracked, so count it as a change now
+        arch.chunks()[loc.chunk()].mark_changed(id);
+
+        Some((arch.slot_dyn(loc, id), self.structural_version()))
+    }
+
+    /// counter bumped by every structural change to this scene, ie. (de)spawning
+    /// an entity or adding/removing its components, after which pointers to
+    /// components and `EntityLocation`s may be dangling
+    pub fn structural_version(&self) -> u64
+    {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// register a component type with this scene, so that it can be referenced
+    /// by ID(or name) in the `_dyn` methods. this is required for component types
+    /// defined at runtime, see `CmpMeta::new_dynamic`, unless they're registered
+    /// globally in the `Registry`
+    pub fn register_component(&mut self, meta: CmpMeta) -> CmpId
+    {
+        let id = meta.id();
+
+        self.components.insert(id, meta);
+
+        id
+    }
+
+    /// get the meta-data of a component type registered in this scene or in the
+    /// global `Registry`, by ID
+    pub fn component(&self, id: CmpId) -> Option<&CmpMeta>
+    {
+        self.components
+            .get(&id)
+            .or_else(|| Registry::by_id(id))
+    }
+
+    /// get the meta-data of a component type registered in this scene or in the
+    /// global `Registry`, by name
+    pub fn component_by_name(&self, name: &str) -> Option<&CmpMeta>
+    {
+        self.components
+            .values()
+            .find(|meta| meta.name() == name)
+            .or_else(|| Registry::by_name(name))
+    }
+
+    /// get the meta-data of a registered component type, panicking if it
+    /// isn't registered
+    fn registered(&self, id: CmpId) -> &CmpMeta
+    {
+        self.component(id)
+            .unwrap_or_else(|| panic!("component {:?} isn't registered in this scene!", id))
+    }
+
+    /// checks the internal consistency of this scene, panicking with a description
+    /// of the first problem found. every entity stored in an archetype must be mapped
+    /// to that location, and vice versa. this is slow, and meant for tests and debugging
+    pub fn validate(&self)
+    {
+        let mut len = 0;
+
+        for arch in self.archetypes.inner()
+        {
+            arch.validate();
+
+            for (c, chunk) in arch.chunks().iter().enumerate()
+            {
+                for (i, ent) in chunk.entities().iter().enumerate()
+                {
+                    let loc = EntityLocation::new(arch.id(), c, i);
+
+                    assert_eq!(self.entities.get(*ent), loc, "{} is stored at {} but mapped elsewhere!", ent, loc);
+                }
+                len += chunk.len();
+            }
+        }
+        assert_eq!(self.entities.len(), len, "entity map and archetypes disagree on the number of entities!");
+    }
+
+    /// get every archetype within this scene
+    #[inline]
+    pub fn archetypes(&self) -> &ArchetypeMap
+    {
+        &self.archetypes
+    }
+
+    /// get every archetype within this scene
+    #[inline]
+    pub(crate) fn archetypes_mut(&mut self) -> &mut ArchetypeMap
+    {
+        &mut self.archetypes
+    }
+
+    /// iterate the archetypes within this scene that contain the component type
+    /// `id`, to mutate their components in bulk. see `ArchetypeMap::iter_containing`
+    /// for the immutable version
+    pub fn archetypes_containing_mut(&mut self, id: CmpId) -> impl Iterator<Item = &mut Archetype>
+    {
+        self.archetypes.iter_containing_mut(id)
+    }
+
+    /// get an archetype by its ID(see `EntityLocation::archetype`), or `None`
+    /// if it's out of range
+    pub fn archetype_by_id(&self, id: usize) -> Option<&Archetype>
+    {
+        self.archetypes.get(id)
+    }
+
+    /// get the archetype made of exactly the components in `T`, if any
+    /// entity with that combination of components was ever spawned
+    pub fn archetype<T: StaticCmpSet>(&self) -> Option<&Archetype>
+    {
+        let id = self.archetypes.find(T::types())?;
+
+        Some(&self.archetypes.inner()[id])
+    }
+
+    /// get the archetype made of exactly the components in `T`, if any
+    /// entity with that combination of components was ever spawned.
+    ///
+    /// useful to mutate the components of many entities in bulk, when
+    /// they're known to share one archetype
+    pub fn archetype_mut<T: StaticCmpSet>(&mut self) -> Option<&mut Archetype>
+    {
+        let id = self.archetypes.find(T::types())?;
+
+        Some(&mut self.archetypes.inner_mut()[id])
+    }
+}
+
+/// a handle to the components of a freshly spawned entity, see `Scene::spawn_mut`
+#[derive(Debug)]
+pub struct SpawnHandle<'a>
+{
+    /// archetype the entity was spawned in
+    arch: &'a mut Archetype,
+    /// location of the entity within `arch`
+    loc: EntityLocation,
+}
+
+impl SpawnHandle<'_>
+{
+    /// get the location of the freshly spawned entity, which is invalidated by
+    /// the next structural change to the scene
+    #[inline]
+    pub fn location(&self) -> EntityLocation
+    {
+        self.loc
+    }
+
+    /// get the freshly spawned entity's `T` component, or `None` if it
+    /// doesn't have that component
+    pub fn get<T: Component>(&self) -> Option<&T>
+    {
+        self.arch.get(self.loc)
+    }
+
+    /// get the freshly spawned entity's `T` component, or `None` if it
+    /// doesn't have that component
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T>
+    {
+        self.arch.get_mut(self.loc)
+    }
+}
+
+/// double-buffered log of a scene's structural changes, ie. the components
+/// removed from it(see `Scene::removed`), kept for this frame and the previous one
+#[derive(Debug)]
+struct TrackerLog<T>
+{
+    /// changes during the previous frame
+    old: Vec<T>,
+    /// changes during this frame
+    new: Vec<T>,
+}
+
+impl<T> TrackerLog<T>
+{
+    /// log a change
+    fn record(&mut self, change: T)
+    {
+        self.new.push(change);
+    }
+
+    /// log many changes
+    fn record_all(&mut self, changes: impl IntoIterator<Item = T>)
+    {
+        self.new.extend(changes);
+    }
+
+    /// swap the buffers, forgetting the changes logged before the previous update
+    fn update(&mut self)
+    {
+        std::mem::swap(&mut self.old, &mut self.new);
+
+        self.new.clear();
+    }
+
+    /// iterate the changes, oldest first
+    fn iter(&self) -> impl Iterator<Item = &T> + '_
+    {
+        self.old
+            .iter()
+            .chain(&self.new)
+    }
+}
+
+impl<T> Default for TrackerLog<T>
+{
+    fn default() -> Self
+    {
+        Self { old: Vec::new(), new: Vec::new() }
+    }
+}
+
+/// ID and bytes of every component of an entity, see `Scene::dump_raw`
+pub type RawComponents<'a> = Vec<(CmpId, &'a [MaybeUninit<u8>])>;
+
+/// callback registered through `Scene::on_add` or `Scene::on_remove`, given a
+/// pointer to the component
+type SceneHook = Box<dyn FnMut(Entity, *mut u8, &mut Commands) + Send + Sync>;
+
+/// callbacks registered through `Scene::on_add` and `Scene::on_remove`, per
+/// component type
+#[derive(Default)]
+struct SceneHooks
+{
+    add: IdHashMap<CmpId, Vec<SceneHook>>,
+    remove: IdHashMap<CmpId, Vec<SceneHook>>,
+}
+
+impl SceneHooks
+{
+    /// call the `Scene::on_add` hooks of the components `ids`, which were just
+    /// inserted for the entity at `loc`
+    fn added(&mut self, arch: &Archetype, loc: EntityLocation, ids: &[CmpId], queue: &mut CommandQueue)
+    {
+        Self::call(&mut self.add, arch, loc, ids, queue);
+    }
+
+    /// call the `Scene::on_remove` hooks of the components `ids`, which are about
+    /// to be dropped from the entity at `loc`
+    fn removed(&mut self, arch: &Archetype, loc: EntityLocation, ids: &[CmpId], queue: &mut CommandQueue)
+    {
+        Self::call(&mut self.remove, arch, loc, ids, queue);
+    }
+
+    /// call the hooks of the components `ids` of the entity at `loc`
+    fn call(hooks: &mut IdHashMap<CmpId, Vec<SceneHook>>, arch: &Archetype, loc: EntityLocation, ids: &[CmpId], queue: &mut CommandQueue)
+    {
+        // most scenes don't have any hook
+        if hooks.is_empty()
+        {
+            return;
+        }
+        let ent = arch.chunks()[loc.chunk()].entities()[loc.index()];
+        let mut cmds = Commands::new(queue);
+
+        for id in ids
+        {
+            for hook in hooks.get_mut(id).into_iter().flatten()
+            {
+                hook(ent, arch.slot_dyn(loc, *id), &mut cmds);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for SceneHooks
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("SceneHooks")
+            .field("add", &self.add.values().map(Vec::len).sum::<usize>())
+            .field("remove", &self.remove.values().map(Vec::len).sum::<usize>())
+            .finish()
+    }
 }
 
 impl std::fmt::Display for Scene
@@ -0,0 +1,163 @@
+//! serialization of components through their type-erased meta-data, behind the
+//! `serde` feature
+
+use std::alloc::Layout;
+
+use serde::ser::{ Error as _, SerializeMap };
+use serde::de::{ DeserializeOwned, Error as _ };
+
+use crate::{ CmpId, EcsError, Entity, Scene };
+
+/// function pointer to view a certain type as a serializable trait object, given
+/// a void ptr
+pub type SerializeFn = unsafe fn(*const u8) -> *const dyn erased_serde::Serialize;
+
+/// function pointer to deserialize a certain type, given a void ptr to the
+/// (uninitialized) destination
+pub type DeserializeFn = for<'de> unsafe fn(*mut u8, &mut dyn erased_serde::Deserializer<'de>) -> Result<(), erased_serde::Error>;
+
+/// views a certain type as a serializable trait object given a void ptr. used in
+/// `CmpMeta::with_serde`, as it is a `SerializeFn` type
+///
+/// # Safety
+/// `src` must point to a valid `T`, which outlives the returned pointer
+pub unsafe fn serialize_ptr<T: serde::Serialize + 'static>(src: *const u8) -> *const dyn erased_serde::Serialize
+{
+    src.cast::<T>() as *const dyn erased_serde::Serialize
+}
+
+/// deserializes a certain type given a void ptr to the destination. used in
+/// `CmpMeta::with_serde`, as it is a `DeserializeFn` type. `dst` is left
+/// uninitialized if deserialization fails
+///
+/// # Safety
+/// `dst` must point to a properly aligned, uninitialized slot for a `T`
+pub unsafe fn deserialize_ptr<'de, T: DeserializeOwned>(dst: *mut u8, de: &mut dyn erased_serde::Deserializer<'de>) -> Result<(), erased_serde::Error>
+{
+    dst.cast::<T>().write(erased_serde::deserialize::<T>(de)?);
+
+    Ok(())
+}
+
+/// what to do with the components that can't be serialized(see `#[component(serde)]`),
+/// when serializing an entity as a whole
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Unserializable
+{
+    /// leave them out
+    Skip,
+    /// fail with `EcsError::NotSerializable`
+    Error,
+}
+
+impl Scene
+{
+    /// serialize an entity's `id` component, which must have been derived with
+    /// `#[component(serde)]`
+    pub fn serialize_component_dyn<S: serde::Serializer>(&self, ent: Entity, id: CmpId, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        // get location
+        let loc = self
+            .location(ent)
+            .ok_or_else(|| S::Error::custom(EcsError::EntityNotFound(ent)))?;
+        let arch = &self.archetypes().inner()[loc.archetype()];
+
+        // entity doesn't have the component
+        if !arch.meta().contains_dyn(id)
+        {
+            let name = self.component(id).map_or("?", |meta| meta.name());
+
+            return Err(S::Error::custom(EcsError::ComponentMissing(ent, name)));
+        }
+        let meta = &arch.meta().get_dyn(id).0;
+        let serialize = meta
+            .serialize_fn()
+            .ok_or_else(|| S::Error::custom(EcsError::NotSerializable(meta.name())))?;
+
+        // SAFETY: the component is alive for as long as `self` is borrowed
+        erased_serde::serialize(unsafe { &*serialize(arch.get_dyn(loc, id).as_ptr() as *const u8) }, serializer)
+    }
+
+    /// deserialize a component into an existing entity, see `Scene::add`. the
+    /// component type must have been derived with `#[component(serde)]` and be
+    /// registered in this scene or the global `Registry`
+    pub fn deserialize_component_dyn<'de, D: serde::Deserializer<'de>>(&mut self, ent: Entity, id: CmpId, deserializer: D) -> Result<(), D::Error>
+    {
+        let meta = self
+            .component(id)
+            .cloned()
+            .ok_or_else(|| D::Error::custom(EcsError::UnknownComponent(id)))?;
+        let deserialize = meta
+            .deserialize_fn()
+            .ok_or_else(|| D::Error::custom(EcsError::NotSerializable(meta.name())))?;
+
+        // entity isn't in this scene
+        if self.location(ent).is_none()
+        {
+            return Err(D::Error::custom(EcsError::EntityNotFound(ent)));
+        }
+
+        // deserialize into a temporary first, so that the entity never ends up with
+        // an uninitialized component if it fails
+        let layout = Layout::from_size_align(meta.size(), meta.alignment()).unwrap();
+        let buf = match layout.size()
+        {
+            0 => layout.align() as *mut u8,
+            _ => unsafe { std::alloc::alloc(layout) },
+        };
+        let res = unsafe { deserialize(buf, &mut <dyn erased_serde::Deserializer>::erase(deserializer)) };
+
+        // move the component into the scene
+        if res.is_ok()
+        {
+            // SAFETY: the component was just deserialized, and `buf` is freed without dropping it
+            unsafe { self.add_dyn(ent, &[(id, std::slice::from_raw_parts(buf, layout.size()))]) };
+        }
+        if layout.size() > 0
+        {
+            unsafe { std::alloc::dealloc(buf, layout) };
+        }
+        res.map_err(D::Error::custom)
+    }
+
+    /// serialize an entity as a map of its components' names to their values.
+    /// components that can't be serialized are handled as per `unserializable`
+    pub fn serialize_entity<S: serde::Serializer>(&self, ent: Entity, serializer: S, unserializable: Unserializable) -> Result<S::Ok, S::Error>
+    {
+        // get location
+        let loc = self
+            .location(ent)
+            .ok_or_else(|| S::Error::custom(EcsError::EntityNotFound(ent)))?;
+        let arch = &self.archetypes().inner()[loc.archetype()];
+
+        // components to serialize
+        let metas = arch
+            .types()
+            .iter()
+            .map(|id| &arch.meta().get_dyn(*id).0)
+            .collect::<Vec<_>>();
+
+        if unserializable == Unserializable::Error
+        {
+            if let Some(meta) = metas.iter().find(|meta| meta.serialize_fn().is_none())
+            {
+                return Err(S::Error::custom(EcsError::NotSerializable(meta.name())));
+            }
+        }
+        let metas = metas
+            .into_iter()
+            .filter_map(|meta| meta.serialize_fn().map(|f| (meta, f)))
+            .collect::<Vec<_>>();
+
+        let mut map = serializer.serialize_map(Some(metas.len()))?;
+
+        for (meta, serialize) in metas
+        {
+            // SAFETY: the component is alive for as long as `self` is borrowed
+            let cmp = unsafe { &*serialize(arch.get_dyn(loc, meta.id()).as_ptr() as *const u8) };
+
+            map.serialize_entry(meta.name(), cmp)?;
+        }
+        map.end()
+    }
+}
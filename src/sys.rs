@@ -0,0 +1,1203 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::ops::{ Deref, DerefMut };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+use crate::{ Archetype, CmpId, CmpMeta, CmpSet, StaticCmpSet, Entity, Query, QueryIter, ChunkQuery, QueryChunks, Res, ResMut, Scene, TaskPool, ThreadPool };
+use crate::qry::par_for_each;
+
+/// logic run over a scene, ie. every frame
+///
+/// systems declare what they access through `System::access`, so that they
+/// can be scheduled safely. functions whose parameters are all `SystemParam`s
+/// turn into one through `IntoSystem`(or `#[system]`), and closures taking a
+/// `&mut Scene` are systems too. see `Scene::run` to run one
+///
+/// # Safety
+/// `System::access` must report everything that `System::run_shared` borrows,
+/// because `Systems::run_parallel` runs systems with compatible accesses at the
+/// same time, over the same scene. the default access(see `Access::exclusive`)
+/// is always sound
+pub unsafe trait System
+{
+    /// run this system over `scene`
+    fn run(&mut self, scene: &mut Scene);
+
+    /// see `System::run`
+    ///
+    /// runs this system while other systems may be running over the same scene,
+    /// on other threads. implementations must only borrow the parts of the scene
+    /// reported by `System::access`, and never the whole scene unless their access
+    /// is exclusive
+    ///
+    /// # Safety
+    /// `scene` must be valid for the duration of the call, and the systems running
+    /// alongside this one must have compatible accesses(see `Access::is_compatible`)
+    unsafe fn run_shared(&mut self, scene: *mut Scene)
+    {
+        self.run(&mut *scene)
+    }
+
+    /// human-readable name of this system, for diagnostics
+    fn name(&self) -> &str;
+
+    /// components and resources accessed by this system. defaults to the
+    /// whole scene, see `Access::exclusive`
+    fn access(&self) -> Access
+    {
+        Access::exclusive()
+    }
+
+    /// labels of this system, which other systems are ordered around. see
+    /// `IntoLabeledSystem`
+    fn labels(&self) -> &[&'static str]
+    {
+        &[]
+    }
+
+    /// labels of the systems this one must run before
+    fn runs_before(&self) -> &[&'static str]
+    {
+        &[]
+    }
+
+    /// labels of the systems this one must run after
+    fn runs_after(&self) -> &[&'static str]
+    {
+        &[]
+    }
+}
+
+/// closures taking the whole scene are systems, which access everything
+unsafe impl<F: FnMut(&mut Scene)> System for F
+{
+    fn run(&mut self, scene: &mut Scene)
+    {
+        self(scene)
+    }
+
+    fn name(&self) -> &str
+    {
+        std::any::type_name::<F>()
+    }
+}
+
+/// callback given the name and wall-clock duration of a system, see
+/// `Systems::run_parallel_profiled`
+pub(crate) type Profile<'a> = dyn FnMut(&str, Duration) + 'a;
+
+/// a list of systems, run in the order they were added unless their labels say
+/// otherwise(see `IntoLabeledSystem`). see `Systems::run_parallel` to run those
+/// that don't conflict at the same time
+#[derive(Default)]
+pub struct Systems
+{
+    /// pool that systems run on in parallel, `ThreadPool::global` unless it's set
+    pool: Option<SharedPool>,
+    /// systems, in registration order
+    systems: Vec<Box<dyn System + Send>>,
+    /// indices of the systems, in execution order. empty until it's first needed
+    order: Vec<usize>,
+    /// indices of the systems that can run in parallel, in execution order. empty
+    /// until it's first needed
+    batches: Vec<Vec<usize>>,
+}
+
+impl Systems
+{
+    /// create a new, empty list of systems
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// run systems in parallel on `pool` rather than `ThreadPool::global`, see
+    /// `Systems::run_parallel`
+    pub fn set_pool(&mut self, pool: impl TaskPool + Send + 'static) -> &mut Self
+    {
+        self.pool = Some(SharedPool(Arc::new(pool)));
+        self
+    }
+
+    /// see `Systems::set_pool`, with a pool shared by other lists of systems
+    pub(crate) fn set_shared_pool(&mut self, pool: Option<SharedPool>)
+    {
+        self.pool = pool;
+    }
+
+    /// add a system to the end of this list
+    pub fn add<M, S: IntoSystem<M>>(&mut self, sys: S) -> &mut Self
+    where
+        S::System: Send + 'static
+    {
+        self.systems.push(Box::new(sys.into_system()));
+        self.order.clear();
+        self.batches.clear();
+        self
+    }
+
+    /// add a system with full access to the scene at the end of this list, ie.
+    /// to load a level. see `Systems::run_parallel`, where it acts as a barrier
+    pub fn add_exclusive(&mut self, sys: impl FnMut(&mut Scene) + Send + 'static) -> &mut Self
+    {
+        self.add(sys)
+    }
+
+    /// run every system over `scene`, in registration order unless ordering
+    /// constraints say otherwise. commands issued by a system are applied before
+    /// the next one runs. panics if the ordering constraints are cyclic
+    pub fn run(&mut self, scene: &mut Scene)
+    {
+        self.build();
+
+        for i in &self.order
+        {
+            self.systems[*i].run(scene);
+            scene.apply_commands();
+        }
+    }
+
+    /// see `Systems::run`
+    ///
+    /// systems whose accesses are compatible run at the same time, on the threads
+    /// of a pool(see `Systems::set_pool`), while conflicting ones still run in order. ordering constraints
+    /// only hold back the systems they apply to. systems with exclusive access are
+    /// barriers: they run alone, after every system ordered before them and before
+    /// every system ordered after them. commands are applied once every batch of
+    /// parallel systems is done
+    pub fn run_parallel(&mut self, scene: &mut Scene)
+    {
+        self.run_parallel_profiled(scene, None);
+    }
+
+    /// see `Systems::run_parallel`. if there's a `profile` callback, it's given
+    /// the wall-clock duration of every system, once its batch is done
+    pub(crate) fn run_parallel_profiled(&mut self, scene: &mut Scene, mut profile: Option<&mut Profile>)
+    {
+        self.build();
+
+        let timed = profile.is_some();
+        let mut systems = self.systems
+            .iter_mut()
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        let pool: &dyn TaskPool = match &self.pool
+        {
+            Some(SharedPool(pool)) => &**pool,
+            None => ThreadPool::global(),
+        };
+
+        for batch in &self.batches
+        {
+            let ptr = ScenePtr(scene);
+            let batch = batch
+                .iter()
+                .map(|i| Mutex::new((systems[*i].take().unwrap(), None)))
+                .collect::<Vec<_>>();
+
+            // SAFETY: systems within a batch have compatible accesses
+            let run = |(sys, time): &mut (&mut Box<dyn System + Send>, Option<Duration>)|
+            {
+                let start = if timed { Some(Instant::now()) } else { None };
+                unsafe { sys.run_shared(ptr.get()) };
+
+                *time = start.map(|start| start.elapsed());
+            };
+            match batch.len()
+            {
+                1 => run(&mut batch[0].lock().unwrap()),
+                n => pool.for_each(n, &|i| run(&mut batch[i].lock().unwrap())),
+            }
+            scene.apply_commands();
+
+            if let Some(profile) = &mut profile
+            {
+                for sys in batch
+                {
+                    let (sys, time) = sys.into_inner().unwrap();
+
+                    profile(sys.name(), time.unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    /// sort the systems and group them in batches, unless it's already done
+    fn build(&mut self)
+    {
+        if self.order.len() == self.systems.len()
+        {
+            return;
+        }
+        let preds = self.predecessors();
+
+        self.order = self.sort(&preds);
+        self.batches = self.build_batches(&preds);
+    }
+
+    /// list the systems that must run before each system, according to their
+    /// ordering constraints
+    fn predecessors(&self) -> Vec<Vec<usize>>
+    {
+        let mut preds = vec![Vec::new(); self.systems.len()];
+        let labeled = |label| (0..self.systems.len()).filter(move |j| self.systems[*j].labels().contains(&label));
+
+        for (i, sys) in self.systems.iter().enumerate()
+        {
+            // constraints on labels that no system has are fine, ie. for optional plugins
+            for label in sys.runs_after()
+            {
+                preds[i].extend(labeled(*label).filter(|j| *j != i));
+            }
+            for label in sys.runs_before()
+            {
+                for j in labeled(*label).filter(|j| *j != i)
+                {
+                    preds[j].push(i);
+                }
+            }
+        }
+        preds
+    }
+
+    /// sort the systems topologically, breaking ties by registration order. panics
+    /// with the systems involved if the ordering constraints are cyclic
+    fn sort(&self, preds: &[Vec<usize>]) -> Vec<usize>
+    {
+        let mut order = Vec::with_capacity(preds.len());
+        let mut done = vec![false; preds.len()];
+
+        while order.len() < preds.len()
+        {
+            let next = (0..preds.len()).find(|i| !done[*i] && preds[*i].iter().all(|j| done[*j]));
+
+            match next
+            {
+                Some(i) =>
+                {
+                    done[i] = true;
+                    order.push(i);
+                }
+                None => panic!("systems have cyclic ordering constraints: {}!", self.describe_cycle(preds, &done)),
+            }
+        }
+        order
+    }
+
+    /// find a cycle among the systems that couldn't be sorted, which all have a
+    /// predecessor that couldn't be sorted either, and describe it in execution order
+    fn describe_cycle(&self, preds: &[Vec<usize>], done: &[bool]) -> String
+    {
+        let mut path = Vec::new();
+        let mut i = (0..preds.len()).find(|i| !done[*i]).unwrap();
+
+        while !path.contains(&i)
+        {
+            path.push(i);
+            i = *preds[i].iter().find(|j| !done[**j]).unwrap();
+        }
+        let start = path.iter().position(|j| *j == i).unwrap();
+
+        path[start..]
+            .iter()
+            .rev()
+            .chain(path.last())
+            .map(|i| format!("`{}`", self.systems[*i].name()))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+
+    /// group the sorted systems in batches that can run in parallel: each system runs
+    /// in the batch following the last one with a system it conflicts with, or must
+    /// run after
+    fn build_batches(&self, preds: &[Vec<usize>]) -> Vec<Vec<usize>>
+    {
+        let access = self.systems
+            .iter()
+            .map(|sys| sys.access())
+            .collect::<Vec<_>>();
+        let mut batches = Vec::<Vec<usize>>::new();
+        let mut batch_of = vec![0; access.len()];
+
+        for (n, i) in self.order.iter().enumerate()
+        {
+            let batch = self.order[..n]
+                .iter()
+                .filter(|j| !access[*i].is_compatible(&access[**j]) || preds[*i].contains(j))
+                .map(|j| batch_of[*j] + 1)
+                .max()
+                .unwrap_or(0);
+
+            if batch == batches.len()
+            {
+                batches.push(Vec::new());
+            }
+            batches[batch].push(*i);
+            batch_of[*i] = batch;
+        }
+        batches
+    }
+
+    /// find the pairs of systems(indices, in registration order) that conflict but
+    /// aren't ordered relative to each other by their constraints, even indirectly,
+    /// so which one runs first only depends on the order they were added in.
+    /// exclusive systems are barriers on purpose, and are never reported
+    pub(crate) fn ambiguities(&self) -> Vec<(usize, usize)>
+    {
+        let preds = self.predecessors();
+        let access = self.systems
+            .iter()
+            .map(|sys| sys.access())
+            .collect::<Vec<_>>();
+
+        // every system that must run before each system, directly or not
+        let before = (0..preds.len())
+            .map(|i|
+            {
+                let mut seen = vec![false; preds.len()];
+                let mut stack = preds[i].clone();
+
+                while let Some(j) = stack.pop()
+                {
+                    if !seen[j]
+                    {
+                        seen[j] = true;
+                        stack.extend_from_slice(&preds[j]);
+                    }
+                }
+                seen
+            })
+            .collect::<Vec<_>>();
+
+        let mut pairs = Vec::new();
+        for i in 0..access.len()
+        {
+            for j in (i + 1)..access.len()
+            {
+                if access[i].is_exclusive() || access[j].is_exclusive()
+                {
+                    continue;
+                }
+                if !access[i].is_compatible(&access[j]) && !before[i][j] && !before[j][i]
+                {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// get the `i`th system, in registration order
+    pub(crate) fn get(&self, i: usize) -> &(dyn System + Send)
+    {
+        &*self.systems[i]
+    }
+
+    /// number of systems in this list
+    pub fn len(&self) -> usize
+    {
+        self.systems.len()
+    }
+
+    /// is this list empty of systems?
+    pub fn is_empty(&self) -> bool
+    {
+        self.systems.is_empty()
+    }
+}
+
+/// pool shared by lists of systems, see `Systems::set_pool`
+#[derive(Clone)]
+pub(crate) struct SharedPool(pub(crate) Arc<dyn TaskPool + Send>);
+
+impl std::fmt::Debug for SharedPool
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str("SharedPool")
+    }
+}
+
+/// pointer to a scene shared by systems running in parallel, see `System::run_shared`
+#[derive(Clone, Copy)]
+struct ScenePtr(*mut Scene);
+
+// SAFETY: systems sharing the scene have compatible accesses, and only use the
+// components and resources they access, which are all `Send + Sync`
+unsafe impl Send for ScenePtr { }
+unsafe impl Sync for ScenePtr { }
+
+impl ScenePtr
+{
+    /// get the pointer, which closures must capture whole to be `Send`
+    fn get(self) -> *mut Scene
+    {
+        self.0
+    }
+}
+
+impl std::fmt::Debug for Systems
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_list()
+            .entries(self.systems.iter().map(|sys| sys.name()))
+            .finish()
+    }
+}
+
+/// conversion into a `System`, implemented for systems themselves and for
+/// functions of up to 8 `SystemParam`s:
+/// ```ignore
+/// fn physics(pos: View<(&mut Pos, &Vel)>, time: Res<Time>) { ... }
+///
+/// scene.run(physics);
+/// ```
+/// `M` only tells the implementations apart, and is inferred
+pub trait IntoSystem<M>
+{
+    /// system this converts into
+    type System: System;
+
+    /// convert into a system. panics if the parameters of a function alias
+    /// each other, ie. `View<&mut Pos>` and `View<&Pos>`
+    fn into_system(self) -> Self::System;
+}
+
+impl<S: System> IntoSystem<()> for S
+{
+    type System = S;
+
+    fn into_system(self) -> S
+    {
+        self
+    }
+}
+
+/// a function turned into a system, see `IntoSystem`
+pub struct FnSystem<F, M, S>
+{
+    /// the function itself
+    func: F,
+    /// components and resources accessed by the function's parameters
+    access: Access,
+    /// state of the function's parameters, see `SystemParam::State`
+    state: S,
+    /// `M` is only used for its type
+    marker: PhantomData<fn() -> M>,
+}
+
+macro_rules! impl_fn_system
+{
+    ($($p:ident),*) =>
+    {
+        impl<F, $($p: SystemParam),*> IntoSystem<fn($($p,)*)> for F
+        where
+            F: FnMut($($p),*) + for<'a> FnMut($($p::Item<'a>),*)
+        {
+            type System = FnSystem<F, fn($($p,)*), ($($p::State,)*)>;
+
+            fn into_system(self) -> Self::System
+            {
+                #[allow(unused_mut)]
+                let mut access = Access::default();
+
+                $($p::access(&mut access);)*
+                access.check();
+
+                FnSystem { func: self, access, state: Default::default(), marker: PhantomData }
+            }
+        }
+
+        // SAFETY: the access is that of every parameter, which only fetch what they report
+        unsafe impl<F, $($p: SystemParam),*> System for FnSystem<F, fn($($p,)*), ($($p::State,)*)>
+        where
+            F: FnMut($($p),*) + for<'a> FnMut($($p::Item<'a>),*)
+        {
+            fn run(&mut self, scene: &mut Scene)
+            {
+                // SAFETY: the scene is exclusively borrowed
+                unsafe { self.run_shared(scene) }
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            unsafe fn run_shared(&mut self, scene: *mut Scene)
+            {
+                // helps inference pick the `FnMut` with the fetched items
+                #[allow(clippy::too_many_arguments)]
+                fn call<$($p),*>(mut f: impl FnMut($($p),*), $($p: $p),*)
+                {
+                    f($($p),*)
+                }
+                let ($($p,)*) = &mut self.state;
+
+                // SAFETY: the parameters' accesses were checked in `into_system`
+                call(&mut self.func, $($p::fetch(scene, $p)),*)
+            }
+
+            fn name(&self) -> &str
+            {
+                std::any::type_name::<F>()
+            }
+
+            fn access(&self) -> Access
+            {
+                self.access.clone()
+            }
+        }
+    };
+}
+
+impl_fn_system!();
+impl_fn_system!(A);
+impl_fn_system!(A, B);
+impl_fn_system!(A, B, C);
+impl_fn_system!(A, B, C, D);
+impl_fn_system!(A, B, C, D, E);
+impl_fn_system!(A, B, C, D, E, G);
+impl_fn_system!(A, B, C, D, E, G, H);
+impl_fn_system!(A, B, C, D, E, G, H, I);
+
+/// ordering constraints between the systems of the same `Systems`(ie. a stage),
+/// for when registration order is too brittle:
+/// ```ignore
+/// schedule
+///     .add_system_to_stage(UPDATE, movement.label("movement").after("input"))
+///     .add_system_to_stage(UPDATE, input.label("input"));
+/// ```
+/// constraints only order the systems they apply to, which may still run in
+/// parallel with any other system
+pub trait IntoLabeledSystem<M>: IntoSystem<M> + Sized
+{
+    /// tag this system with `label`, which other systems can be ordered around.
+    /// many systems can share the same label
+    fn label(self, label: &'static str) -> LabeledSystem<Self::System>
+    {
+        LabeledSystem::new(self.into_system()).label(label)
+    }
+
+    /// run this system before every system tagged with `label`
+    fn before(self, label: &'static str) -> LabeledSystem<Self::System>
+    {
+        LabeledSystem::new(self.into_system()).before(label)
+    }
+
+    /// run this system after every system tagged with `label`
+    fn after(self, label: &'static str) -> LabeledSystem<Self::System>
+    {
+        LabeledSystem::new(self.into_system()).after(label)
+    }
+}
+
+impl<M, S: IntoSystem<M>> IntoLabeledSystem<M> for S { }
+
+/// a system with labels and ordering constraints, see `IntoLabeledSystem`
+pub struct LabeledSystem<S: System>
+{
+    /// the system itself
+    sys: S,
+    /// see `System::labels`
+    labels: Vec<&'static str>,
+    /// see `System::runs_before`
+    before: Vec<&'static str>,
+    /// see `System::runs_after`
+    after: Vec<&'static str>,
+}
+
+impl<S: System> LabeledSystem<S>
+{
+    /// wrap a system, without any label or constraint
+    fn new(sys: S) -> Self
+    {
+        Self
+        {
+            sys,
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    /// see `IntoLabeledSystem::label`
+    pub fn label(mut self, label: &'static str) -> Self
+    {
+        self.labels.push(label);
+        self
+    }
+
+    /// see `IntoLabeledSystem::before`
+    pub fn before(mut self, label: &'static str) -> Self
+    {
+        self.before.push(label);
+        self
+    }
+
+    /// see `IntoLabeledSystem::after`
+    pub fn after(mut self, label: &'static str) -> Self
+    {
+        self.after.push(label);
+        self
+    }
+}
+
+// SAFETY: every call is forwarded to the labeled system
+unsafe impl<S: System> System for LabeledSystem<S>
+{
+    fn run(&mut self, scene: &mut Scene)
+    {
+        self.sys.run(scene)
+    }
+
+    unsafe fn run_shared(&mut self, scene: *mut Scene)
+    {
+        self.sys.run_shared(scene)
+    }
+
+    fn name(&self) -> &str
+    {
+        self.sys.name()
+    }
+
+    fn access(&self) -> Access
+    {
+        self.sys.access()
+    }
+
+    fn labels(&self) -> &[&'static str]
+    {
+        &self.labels
+    }
+
+    fn runs_before(&self) -> &[&'static str]
+    {
+        &self.before
+    }
+
+    fn runs_after(&self) -> &[&'static str]
+    {
+        &self.after
+    }
+}
+
+/// a structural change to the scene, deferred by `Commands`
+type Command = Box<dyn FnOnce(&mut Scene) + Send>;
+
+/// structural changes to a scene queued by systems, which can't spawn or
+/// despawn while they're iterating it. see `Scene::apply_commands`
+#[derive(Default)]
+pub struct CommandQueue
+{
+    /// commands, in the order they were issued
+    commands: Vec<Command>,
+}
+
+impl CommandQueue
+{
+    /// take every command out of this queue, in the order they were issued
+    pub(crate) fn take(&mut self) -> Vec<Command>
+    {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// number of commands queued
+    pub fn len(&self) -> usize
+    {
+        self.commands.len()
+    }
+
+    /// is this queue empty of commands?
+    pub fn is_empty(&self) -> bool
+    {
+        self.commands.is_empty()
+    }
+}
+
+impl std::fmt::Debug for CommandQueue
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("CommandQueue")
+            .field("len", &self.commands.len())
+            .finish()
+    }
+}
+
+/// system parameter queueing structural changes to the scene, applied once
+/// the system is done running
+pub struct Commands<'a>
+{
+    /// the scene's command queue
+    queue: &'a mut CommandQueue,
+}
+
+impl<'a> Commands<'a>
+{
+    /// queue commands into `queue`
+    pub(crate) fn new(queue: &'a mut CommandQueue) -> Self
+    {
+        Self { queue }
+    }
+
+    /// spawn an entity, see `Scene::spawn`. its ID is allocated right away,
+    /// but it's only in the scene once the commands are applied
+    pub fn spawn<T: CmpSet + Send>(&mut self, cmp: T) -> Entity
+    {
+        let ent = Entity::next(1).start;
+
+        self.push(move |scene| { scene.spawn_as(ent, cmp); });
+        ent
+    }
+
+    /// despawn an entity, see `Scene::despawn`
+    pub fn despawn(&mut self, ent: Entity)
+    {
+        self.push(move |scene| { scene.despawn(ent); });
+    }
+
+    /// add components to an entity, see `Scene::add`
+    pub fn add<T: CmpSet + Send>(&mut self, ent: Entity, cmp: T)
+    {
+        self.push(move |scene| { scene.add(ent, cmp); });
+    }
+
+    /// remove components from an entity, see `Scene::remove`
+    pub fn remove<T: StaticCmpSet>(&mut self, ent: Entity)
+    {
+        self.push(move |scene| { scene.remove::<T>(ent); });
+    }
+
+    /// insert a resource, see `Scene::insert_resource`
+    pub fn insert_resource<T: Send + 'static>(&mut self, res: T)
+    {
+        self.push(move |scene| { scene.insert_resource(res); });
+    }
+
+    /// queue any change to the scene
+    pub fn push(&mut self, cmd: impl FnOnce(&mut Scene) + Send + 'static)
+    {
+        self.queue.commands.push(Box::new(cmd));
+    }
+}
+
+/// a parameter of a `#[system]` function, fetched from the scene every time
+/// the system is run: `View<Q>`, `Res<T>`, `ResMut<T>`, `Local<T>` or `Commands`
+///
+/// # Safety
+/// `SystemParam::access` must report every component and resource that
+/// `SystemParam::fetch` reads or writes
+#[diagnostic::on_unimplemented
+(
+    message = "`{Self}` can't be a system parameter",
+    label = "not a system parameter",
+    note = "system parameters are `View<Q>`, `Res<T>`, `ResMut<T>`, `Local<T>` and `Commands`"
+)]
+pub unsafe trait SystemParam
+{
+    /// type passed to the system, borrowing from the scene
+    type Item<'a>;
+    /// state kept by every instance of a system between runs, ie. for `Local<T>`.
+    /// it's `()` for parameters that are entirely fetched from the scene
+    type State: Default + Send + 'static;
+
+    /// list every component and resource accessed by this parameter
+    fn access(access: &mut Access);
+
+    /// fetch this parameter from `scene`, borrowing only the parts of it that
+    /// are reported by `SystemParam::access`: systems fetch their parameters
+    /// while other systems may be running, see `System::run_shared`
+    ///
+    /// # Safety
+    /// `scene` must outlive `'a`, and no other item fetched from it may
+    /// alias this one's accesses(see `Access::check`)
+    unsafe fn fetch<'a>(scene: *mut Scene, state: &'a mut Self::State) -> Self::Item<'a>;
+}
+
+/// components and resources accessed by a system, and whether mutably. this is
+/// what the parallel executor uses to tell which systems can run at the same
+/// time, see `Access::is_compatible`
+#[derive(Debug, Default, Clone)]
+pub struct Access
+{
+    /// components accessed, in the format of `Query::access`
+    components: Vec<(CmpMeta, bool)>,
+    /// resources accessed, with their type name
+    resources: Vec<(TypeId, &'static str, bool)>,
+    /// components read, sorted and without duplicates
+    reads: Vec<CmpId>,
+    /// components written, sorted and without duplicates
+    writes: Vec<CmpId>,
+    /// resources read, sorted and without duplicates
+    resource_reads: Vec<TypeId>,
+    /// resources written, sorted and without duplicates
+    resource_writes: Vec<TypeId>,
+    /// is the whole scene accessed, mutably?
+    exclusive: bool,
+}
+
+impl Access
+{
+    /// access to the whole scene, mutably, ie. for closures taking a `&mut Scene`
+    pub fn exclusive() -> Self
+    {
+        Self { exclusive: true, ..Default::default() }
+    }
+
+    /// components accessed by the query `Q`
+    pub fn of_query<Q: Query>() -> Self
+    {
+        let mut access = Self::default();
+
+        access.add_query::<Q>();
+        access
+    }
+
+    /// components and resources accessed by the system parameter `P`
+    pub fn of<P: SystemParam>() -> Self
+    {
+        let mut access = Self::default();
+
+        P::access(&mut access);
+        access
+    }
+
+    /// is the whole scene accessed, mutably? see `Access::exclusive`
+    pub fn is_exclusive(&self) -> bool
+    {
+        self.exclusive
+    }
+
+    /// can systems with these accesses run at the same time? that is, is nothing
+    /// written by one and accessed at all by the other. reads are shared
+    pub fn is_compatible(&self, other: &Access) -> bool
+    {
+        if self.exclusive || other.exclusive
+        {
+            return false;
+        }
+        disjoint(&self.writes, &other.writes)
+            && disjoint(&self.writes, &other.reads)
+            && disjoint(&self.reads, &other.writes)
+            && disjoint(&self.resource_writes, &other.resource_writes)
+            && disjoint(&self.resource_writes, &other.resource_reads)
+            && disjoint(&self.resource_reads, &other.resource_writes)
+    }
+
+    /// list the names of the components and resources that make these accesses
+    /// incompatible, see `Access::is_compatible`. it's empty if either is exclusive
+    pub fn conflicts(&self, other: &Access) -> Vec<&'static str>
+    {
+        let mut names = Vec::new();
+
+        for (a, a_mut) in &self.components
+        {
+            for (b, b_mut) in &other.components
+            {
+                if a.id() == b.id() && (*a_mut || *b_mut) && !names.contains(&a.name())
+                {
+                    names.push(a.name());
+                }
+            }
+        }
+        for (a, name, a_mut) in &self.resources
+        {
+            for (b, _, b_mut) in &other.resources
+            {
+                if a == b && (*a_mut || *b_mut) && !names.contains(name)
+                {
+                    names.push(*name);
+                }
+            }
+        }
+        names
+    }
+
+    /// list the components accessed by `Q`
+    pub fn add_query<Q: Query>(&mut self)
+    {
+        let start = self.components.len();
+
+        Q::access(&mut self.components);
+
+        for (meta, mutable) in &self.components[start..]
+        {
+            insert_sorted(if *mutable { &mut self.writes } else { &mut self.reads }, meta.id());
+        }
+    }
+
+    /// list a resource of type `T`, accessed mutably or not
+    pub fn add_resource<T: 'static>(&mut self, mutable: bool)
+    {
+        let id = TypeId::of::<T>();
+
+        self.resources.push((id, std::any::type_name::<T>(), mutable));
+        insert_sorted(if mutable { &mut self.resource_writes } else { &mut self.resource_reads }, id);
+    }
+
+    /// get the components accessed, and whether mutably, in the order they were listed
+    pub fn components(&self) -> &[(CmpMeta, bool)]
+    {
+        &self.components
+    }
+
+    /// get the resources accessed(type ID and name), and whether mutably, in the
+    /// order they were listed
+    pub fn resources(&self) -> &[(TypeId, &'static str, bool)]
+    {
+        &self.resources
+    }
+
+    /// get the components read, sorted
+    pub fn reads(&self) -> &[CmpId]
+    {
+        &self.reads
+    }
+
+    /// get the components written, sorted
+    pub fn writes(&self) -> &[CmpId]
+    {
+        &self.writes
+    }
+
+    /// get the type IDs of the resources read, sorted
+    pub fn resource_reads(&self) -> &[TypeId]
+    {
+        &self.resource_reads
+    }
+
+    /// get the type IDs of the resources written, sorted
+    pub fn resource_writes(&self) -> &[TypeId]
+    {
+        &self.resource_writes
+    }
+
+    /// panics if the same component or resource is accessed mutably more than
+    /// once, or both mutably and immutably
+    pub fn check(&self)
+    {
+        let mut cmps = self.components.clone();
+        let mut res = self.resources.clone();
+
+        cmps.sort_by_key(|(meta, _)| meta.id());
+        res.sort_by_key(|(id, ..)| *id);
+
+        for n in cmps.windows(2)
+        {
+            if n[0].0.id() == n[1].0.id() && (n[0].1 || n[1].1)
+            {
+                panic!("system accesses `{}` mutably while it's already borrowed!", n[0].0.name());
+            }
+        }
+        for n in res.windows(2)
+        {
+            if n[0].0 == n[1].0 && (n[0].2 || n[1].2)
+            {
+                panic!("system accesses resource `{}` mutably while it's already borrowed!", n[0].1);
+            }
+        }
+    }
+}
+
+/// insert `val` into the sorted `set`, unless it's already there
+fn insert_sorted<T: Ord>(set: &mut Vec<T>, val: T)
+{
+    if let Err(i) = set.binary_search(&val)
+    {
+        set.insert(i, val);
+    }
+}
+
+/// do two sorted sets have no element in common?
+fn disjoint<T: Ord>(a: &[T], b: &[T]) -> bool
+{
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+
+    while let (Some(x), Some(y)) = (a.peek(), b.peek())
+    {
+        match x.cmp(y)
+        {
+            std::cmp::Ordering::Less => { a.next(); }
+            std::cmp::Ordering::Greater => { b.next(); }
+            std::cmp::Ordering::Equal => return false,
+        }
+    }
+    true
+}
+
+/// system parameter iterating the components of every entity matching `Q`,
+/// like `Scene::query`: `View<(&mut Pos, &Vel)>`
+pub struct View<'a, Q: Query>
+{
+    /// every archetype in the scene
+    archs: &'a [Archetype],
+    /// `Q` is only used for its type
+    marker: PhantomData<fn() -> Q>,
+}
+
+impl<'a, Q: Query> View<'a, Q>
+{
+    /// components accessed by this view, see `Access::of_query`
+    pub fn access(&self) -> Access
+    {
+        Access::of_query::<Q>()
+    }
+
+    /// iterate the components of every entity matching `Q`
+    pub fn iter(&mut self) -> QueryIter<'_, Q>
+    {
+        QueryIter::new(self.archs, 0)
+    }
+
+    /// iterate the entities and components of every chunk matching `Q`, as
+    /// parallel slices, see `Scene::query_chunks`
+    pub fn iter_chunks(&mut self) -> QueryChunks<'_, Q>
+    where
+        Q: ChunkQuery
+    {
+        QueryIter::new(self.archs, 0).into_chunks()
+    }
+
+    /// call `f` with the entities and components of every chunk matching `Q`, see
+    /// `Scene::for_each_chunk`
+    pub fn for_each_chunk(&mut self, mut f: impl for<'b> FnMut(&'b [Entity], Q::Slice<'b>))
+    where
+        Q: ChunkQuery
+    {
+        for (ents, cmps) in self.iter_chunks()
+        {
+            f(ents, cmps);
+        }
+    }
+
+    /// call `f` with the components of every entity matching `Q`, spreading the
+    /// matching chunks across the threads of `pool`, ie. a `ScopedPool`. returns once
+    /// every entity is done, so that a single heavy system scales across cores
+    pub fn par_for_each<F>(&mut self, pool: &dyn TaskPool, f: F)
+    where
+        F: for<'b> Fn(Q::Item<'b>) + Sync
+    {
+        // SAFETY: the view is exclusively borrowed
+        unsafe { par_for_each::<Q, F>(self.archs, 0, pool, f) }
+    }
+}
+
+impl<'a, Q: Query> IntoIterator for View<'a, Q>
+{
+    type Item = Q::Item<'a>;
+    type IntoIter = QueryIter<'a, Q>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        QueryIter::new(self.archs, 0)
+    }
+}
+
+unsafe impl<'w, Q: Query> SystemParam for View<'w, Q>
+{
+    type Item<'a> = View<'a, Q>;
+    type State = ();
+
+    fn access(access: &mut Access)
+    {
+        access.add_query::<Q>()
+    }
+
+    unsafe fn fetch<'a>(scene: *mut Scene, _: &'a mut ()) -> Self::Item<'a>
+    {
+        View { archs: Scene::archetypes_raw(scene), marker: PhantomData }
+    }
+}
+
+unsafe impl<'w, T: Send + Sync + 'static> SystemParam for Res<'w, T>
+{
+    type Item<'a> = Res<'a, T>;
+    type State = ();
+
+    fn access(access: &mut Access)
+    {
+        access.add_resource::<T>(false)
+    }
+
+    unsafe fn fetch<'a>(scene: *mut Scene, _: &'a mut ()) -> Self::Item<'a>
+    {
+        match Scene::resources_raw(scene).get::<T>()
+        {
+            Some(res) => Res(res),
+            None => panic!("resource `{}` doesn't exist!", std::any::type_name::<T>()),
+        }
+    }
+}
+
+unsafe impl<'w, T: Send + Sync + 'static> SystemParam for ResMut<'w, T>
+{
+    type Item<'a> = ResMut<'a, T>;
+    type State = ();
+
+    fn access(access: &mut Access)
+    {
+        access.add_resource::<T>(true)
+    }
+
+    unsafe fn fetch<'a>(scene: *mut Scene, _: &'a mut ()) -> Self::Item<'a>
+    {
+        match Scene::resources_raw(scene).get_ptr::<T>()
+        {
+            Some(res) => ResMut(&mut *res),
+            None => panic!("resource `{}` doesn't exist!", std::any::type_name::<T>()),
+        }
+    }
+}
+
+unsafe impl<'w> SystemParam for Commands<'w>
+{
+    type Item<'a> = Commands<'a>;
+    type State = ();
+
+    fn access(access: &mut Access)
+    {
+        access.add_resource::<CommandQueue>(true)
+    }
+
+    unsafe fn fetch<'a>(scene: *mut Scene, _: &'a mut ()) -> Self::Item<'a>
+    {
+        Commands::new(Scene::commands_raw(scene))
+    }
+}
+
+/// system parameter holding state that's private to a system, ie. a frame counter
+/// or a scratch buffer: `Local<u32>`. it's created with `T::default()` before the
+/// system first runs, then kept by it between runs. every instance of a system
+/// has its own, whether it's a function or a `#[system]`
+pub struct Local<'a, T>(&'a mut T);
+
+impl<T> Deref for Local<'_, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.0
+    }
+}
+
+impl<T> DerefMut for Local<'_, T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.0
+    }
+}
+
+unsafe impl<'w, T: Default + Send + 'static> SystemParam for Local<'w, T>
+{
+    type Item<'a> = Local<'a, T>;
+    type State = T;
+
+    fn access(_: &mut Access) { }
+
+    unsafe fn fetch<'a>(_: *mut Scene, state: &'a mut T) -> Self::Item<'a>
+    {
+        Local(state)
+    }
+}
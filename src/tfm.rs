@@ -0,0 +1,162 @@
+//! transform components, propagated down the scene's hierarchy. behind the
+//! `glam` cargo feature
+
+use glam::{ Mat4, Quat, Vec3 };
+
+use crate::{ App, CmpId, Component, Entity, MutComponent, Parent, Plugin, Scene, StageLabel, Without };
+
+/// component placing an entity relative to its parent, or to the world if it
+/// has none. see `GlobalTransform`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform
+{
+    /// position, relative to the parent
+    pub translation: Vec3,
+    /// orientation, relative to the parent
+    pub rotation: Quat,
+    /// scale along each axis, relative to the parent
+    pub scale: Vec3,
+}
+
+/// component placing an entity relative to the world, computed from its own
+/// `Transform` and those of its ancestors by `propagate_transforms`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlobalTransform(Mat4);
+
+impl Transform
+{
+    /// transform that doesn't move, rotate or scale anything
+    pub const IDENTITY: Self = Self
+    {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    /// create a new transform that only moves by `translation`
+    pub fn from_translation(translation: Vec3) -> Self
+    {
+        Self { translation, ..Self::IDENTITY }
+    }
+
+    /// create a new transform that only rotates by `rotation`
+    pub fn from_rotation(rotation: Quat) -> Self
+    {
+        Self { rotation, ..Self::IDENTITY }
+    }
+
+    /// create a new transform that only scales by `scale`
+    pub fn from_scale(scale: Vec3) -> Self
+    {
+        Self { scale, ..Self::IDENTITY }
+    }
+
+    /// get this transform as a matrix, which scales, then rotates, then moves
+    pub fn matrix(&self) -> Mat4
+    {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform
+{
+    fn default() -> Self
+    {
+        Self::IDENTITY
+    }
+}
+
+impl GlobalTransform
+{
+    /// get this transform as a matrix
+    #[inline]
+    pub fn matrix(&self) -> Mat4
+    {
+        self.0
+    }
+
+    /// get the position of this transform within the world
+    #[inline]
+    pub fn translation(&self) -> Vec3
+    {
+        self.0.w_axis.truncate()
+    }
+}
+
+impl Default for GlobalTransform
+{
+    fn default() -> Self
+    {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl Component for Transform
+{
+    const ID: CmpId = unsafe { CmpId::from_u64(CmpId::BUILTIN | 202) };
+    const NAME: &'static str = "Transform";
+}
+
+impl Component for GlobalTransform
+{
+    const ID: CmpId = unsafe { CmpId::from_u64(CmpId::BUILTIN | 203) };
+    const NAME: &'static str = "GlobalTransform";
+}
+
+impl MutComponent for Transform { }
+
+impl MutComponent for GlobalTransform { }
+
+crate::__cmp_register!(Transform);
+crate::__cmp_register!(GlobalTransform);
+
+/// exclusive system computing the `GlobalTransform` of every entity with a
+/// `Transform`, from the roots of the hierarchy down, so that parents are always
+/// done before their children. entities that don't have a `GlobalTransform` yet
+/// are given one, and children without a `Transform` are skipped along with
+/// their whole subtree. see `Schedule::add_exclusive_system_to_stage`
+pub fn propagate_transforms(scene: &mut Scene)
+{
+    // roots of the hierarchy, and the children left to visit
+    let mut stack = scene
+        .query::<(Entity, &Transform, Without<Parent>)>()
+        .map(|(ent, local, _)| (ent, local.matrix()))
+        .collect::<Vec<_>>();
+
+    while let Some((ent, global)) = stack.pop()
+    {
+        match scene.get_mut::<GlobalTransform>(ent)
+        {
+            Some(cmp) => cmp.0 = global,
+            None => { scene.add(ent, GlobalTransform(global)); },
+        }
+        for child in scene.children(ent)
+        {
+            if let Some(local) = scene.get::<Transform>(*child)
+            {
+                stack.push((*child, global * local.matrix()));
+            }
+        }
+    }
+}
+
+/// plugin running `propagate_transforms` every frame, in its own stage after
+/// every other one at the time it's added
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TransformPlugin;
+
+impl TransformPlugin
+{
+    /// the stage `propagate_transforms` runs in
+    pub const STAGE: StageLabel = StageLabel::new("transform");
+}
+
+impl Plugin for TransformPlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        app
+            .add_stage(Self::STAGE)
+            .add_exclusive_system_to_stage(Self::STAGE, propagate_transforms);
+    }
+}
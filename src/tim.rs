@@ -0,0 +1,236 @@
+use std::time::{ Duration, Instant };
+
+use crate::{ App, Plugin, Scene };
+
+/// frame timing, stored as a resource: how long the last frame took, and how
+/// long the scene has been running for. `Schedule::run` inserts it if needed,
+/// then updates it at the start of every run(see `Time::update`), which fixed
+/// stages read to know how many times to run, see `Schedule::add_fixed_stage`
+#[derive(Debug, Default, Clone)]
+pub struct Time
+{
+    /// duration of the last frame
+    delta: Duration,
+    /// sum of every frame's duration
+    elapsed: Duration,
+    /// number of frames so far
+    frame: u64,
+    /// instant the current frame started at, if any
+    last: Option<Instant>,
+    /// duration of every frame, overriding the time actually elapsed
+    fixed_delta: Option<Duration>,
+}
+
+impl Time
+{
+    /// create a new time resource, at zero
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// start a new frame, which lasted however long it's been since the previous
+    /// call, or `Time::set_fixed_delta` if it's set. the first frame lasts zero
+    pub fn update(&mut self)
+    {
+        let now = Instant::now();
+        let delta = match (self.fixed_delta, self.last)
+        {
+            (Some(delta), _) => delta,
+            (None, Some(last)) => now - last,
+            (None, None) => Duration::ZERO,
+        };
+        self.last = Some(now);
+        self.advance(delta);
+    }
+
+    /// start a new frame, which lasted `delta`
+    pub fn advance(&mut self, delta: Duration)
+    {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame += 1;
+    }
+
+    /// make every frame started by `Time::update` last exactly `delta` rather
+    /// than the time actually elapsed, ie. for deterministic tests or replays.
+    /// `None` goes back to real time
+    pub fn set_fixed_delta(&mut self, delta: Option<Duration>)
+    {
+        self.fixed_delta = delta;
+    }
+
+    /// duration of the last frame
+    #[inline]
+    pub fn delta(&self) -> Duration
+    {
+        self.delta
+    }
+
+    /// duration of the last frame, in seconds
+    #[inline]
+    pub fn delta_secs(&self) -> f32
+    {
+        self.delta.as_secs_f32()
+    }
+
+    /// sum of every frame's duration
+    #[inline]
+    pub fn elapsed(&self) -> Duration
+    {
+        self.elapsed
+    }
+
+    /// number of frames so far, including the current one
+    #[inline]
+    pub fn frame(&self) -> u64
+    {
+        self.frame
+    }
+}
+
+/// plugin inserting the `Time` resource that `Schedule::run` maintains, optionally
+/// with a fixed delta(see `Time::set_fixed_delta`), ie. for deterministic tests
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimePlugin
+{
+    /// duration of every frame, rather than the time actually elapsed
+    pub fixed_delta: Option<Duration>,
+}
+
+impl Plugin for TimePlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        let mut time = Time::new();
+        time.set_fixed_delta(self.fixed_delta);
+
+        app.insert_resource(time);
+    }
+}
+
+/// run criteria of a stage that runs at a fixed rate, regardless of the frame
+/// rate: zero, one or more times per `Schedule::run`, to catch up with the
+/// `Time` resource. see `Schedule::add_fixed_stage`
+#[derive(Debug, Clone)]
+pub struct FixedTimestep
+{
+    /// duration simulated by every run of the stage
+    step: Duration,
+    /// most runs of the stage per frame
+    max_steps: u32,
+    /// time not yet simulated, always less than `step` between frames
+    accumulator: Duration,
+}
+
+impl FixedTimestep
+{
+    /// default for `FixedTimestep::max_steps`
+    pub const MAX_STEPS: u32 = 8;
+
+    /// create a new fixed timestep, running every `step` seconds. panics if
+    /// `step` isn't positive
+    pub fn new(step: f64) -> Self
+    {
+        assert!(step > 0.0, "fixed timestep must be positive!");
+
+        Self
+        {
+            step: Duration::from_secs_f64(step),
+            max_steps: Self::MAX_STEPS,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// cap the number of runs per frame, after which the time left to simulate
+    /// is dropped. without a cap, frames that are slow because the stage itself
+    /// is slow would have it run even more times the next frame, and so on
+    pub fn max_steps(mut self, max: u32) -> Self
+    {
+        self.max_steps = max;
+        self
+    }
+
+    /// duration simulated by every run of the stage
+    #[inline]
+    pub fn step(&self) -> Duration
+    {
+        self.step
+    }
+
+    /// accumulate the duration of a frame, returning how many times the stage
+    /// should run to catch up
+    pub fn accumulate(&mut self, delta: Duration) -> u32
+    {
+        self.accumulator += delta;
+
+        let steps = (self.accumulator.as_nanos() / self.step.as_nanos()).min(self.max_steps as u128) as u32;
+
+        self.accumulator -= self.step * steps;
+
+        // spiral of death: drop the whole steps that couldn't be caught up with
+        if self.accumulator >= self.step
+        {
+            self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+        }
+        steps
+    }
+
+    /// time not yet simulated, as a fraction of a step in `[0, 1)`
+    pub fn alpha(&self) -> f32
+    {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+/// state of the last fixed stage that ran, stored as a resource by
+/// `Schedule::run`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixedTime
+{
+    /// duration simulated by every run of the stage
+    pub(crate) step: Duration,
+    /// number of times the stage ran this frame
+    pub(crate) steps: u32,
+    /// time not yet simulated, as a fraction of a step
+    pub(crate) alpha: f32,
+}
+
+impl FixedTime
+{
+    /// duration simulated by every run of the stage
+    #[inline]
+    pub fn step(&self) -> Duration
+    {
+        self.step
+    }
+
+    /// number of times the stage ran this frame
+    #[inline]
+    pub fn steps(&self) -> u32
+    {
+        self.steps
+    }
+
+    /// time not yet simulated, as a fraction of a step in `[0, 1)`. render code
+    /// interpolates between the last two simulated states with it
+    #[inline]
+    pub fn alpha(&self) -> f32
+    {
+        self.alpha
+    }
+}
+
+/// update the scene's `Time` resource, inserting it if needed, and return the
+/// duration of the frame that just started. see `Schedule::run`
+pub(crate) fn update_time(scene: &mut Scene) -> Duration
+{
+    if scene.resource::<Time>().is_none()
+    {
+        scene.insert_resource(Time::new());
+    }
+    let time = scene.resource_mut::<Time>().unwrap();
+
+    time.update();
+    time.delta()
+}
@@ -0,0 +1,493 @@
+use std::any::Any;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::mpsc::{ self, Receiver, Sender, TryRecvError };
+use std::sync::{ Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError };
+use std::thread;
+
+use crate::{ App, CmpSet, Entity, Plugin, Scene, StageLabel };
+
+/// runs work items across threads, ie. the chunks of a query in `View::par_for_each`
+/// or the systems of `Systems::run_parallel`. implement it to run them on an existing
+/// thread pool instead of `ThreadPool` or `ScopedPool`
+pub trait TaskPool: Sync
+{
+    /// call `f` once with every index in `0..n`, spread across threads, and
+    /// return once every call is done
+    fn for_each(&self, n: usize, f: &(dyn Fn(usize) + Sync));
+}
+
+/// the crate-provided `TaskPool`, which spawns scoped threads for every call
+/// that take work items from a shared counter until there's none left. the
+/// calling thread takes part too
+#[derive(Debug, Clone, Copy)]
+pub struct ScopedPool
+{
+    /// number of threads work is spread across, including the calling one
+    threads: usize,
+}
+
+impl ScopedPool
+{
+    /// create a new pool spreading work across `threads` threads, including the
+    /// calling one. panics if `threads` is zero
+    pub fn new(threads: usize) -> Self
+    {
+        assert!(threads > 0, "a task pool needs at least one thread!");
+
+        Self { threads }
+    }
+
+    /// number of threads work is spread across, including the calling one
+    #[inline]
+    pub fn threads(&self) -> usize
+    {
+        self.threads
+    }
+}
+
+/// one thread per core
+impl Default for ScopedPool
+{
+    fn default() -> Self
+    {
+        Self::new(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+impl TaskPool for ScopedPool
+{
+    fn for_each(&self, n: usize, f: &(dyn Fn(usize) + Sync))
+    {
+        let next = AtomicUsize::new(0);
+        let work = ||
+        {
+            loop
+            {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+
+                if i >= n
+                {
+                    break;
+                }
+                f(i);
+            }
+        };
+
+        thread::scope(|s|
+        {
+            let handles = (1..self.threads.min(n))
+                .map(|_| s.spawn(work))
+                .collect::<Vec<_>>();
+            work();
+
+            for handle in handles
+            {
+                // keep the work item's own panic message
+                if let Err(err) = handle.join()
+                {
+                    panic::resume_unwind(err);
+                }
+            }
+        });
+    }
+}
+
+/// a `TaskPool` whose threads are spawned once, when it's created, and then wait
+/// for work until it's dropped. the calling thread takes part too. this is what
+/// `Systems::run_parallel` runs on, so that it doesn't spawn threads every frame
+///
+/// a single call runs on the pool at a time: calls made while it's busy, ie. from
+/// within a work item, run on the calling thread alone
+#[derive(Debug)]
+pub struct ThreadPool
+{
+    /// state shared with the workers
+    shared: Arc<PoolShared>,
+    /// threads waiting for work, besides the calling one
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+/// state of a `ThreadPool`, shared with its workers
+#[derive(Debug, Default)]
+struct PoolShared
+{
+    state: Mutex<PoolState>,
+    /// notified when there's a new job, or the pool is dropped
+    work: Condvar,
+    /// notified when a worker is done with the current job
+    done: Condvar,
+}
+
+/// see `PoolShared`
+#[derive(Debug, Default)]
+struct PoolState
+{
+    /// job being run, if any
+    job: Option<Job>,
+    /// bumped by every job, so that workers only join each one once
+    generation: u64,
+    /// number of workers running the current job
+    active: usize,
+    /// first panic of a worker during the current job
+    panic: Option<Box<dyn Any + Send>>,
+    /// is the pool being dropped?
+    shutdown: bool,
+}
+
+/// a call to `ThreadPool::for_each`, whose pointers borrow the caller's stack
+#[derive(Debug, Clone, Copy)]
+struct Job
+{
+    /// work item, see `TaskPool::for_each`
+    f: *const (dyn Fn(usize) + Sync + 'static),
+    /// next work item to run
+    next: *const AtomicUsize,
+    /// number of work items
+    n: usize,
+}
+
+// SAFETY: the caller of `ThreadPool::for_each` waits until no worker uses the job
+// anymore, and `f` is `Sync`
+unsafe impl Send for Job { }
+
+impl ThreadPool
+{
+    /// create a new pool spreading work across `threads` threads, including the
+    /// calling one, spawning all but the latter now. panics if `threads` is zero
+    pub fn new(threads: usize) -> Self
+    {
+        assert!(threads > 0, "a task pool needs at least one thread!");
+
+        let shared = Arc::new(PoolShared::default());
+        let workers = (1..threads)
+            .map(|_|
+            {
+                let shared = shared.clone();
+
+                thread::spawn(move || shared.work())
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// the pool shared by the whole process, with one thread per core. it's created
+    /// the first time it's needed
+    pub fn global() -> &'static Self
+    {
+        static GLOBAL: OnceLock<ThreadPool> = OnceLock::new();
+
+        GLOBAL.get_or_init(||
+        {
+            Self::new(thread::available_parallelism().map_or(1, |n| n.get()))
+        })
+    }
+
+    /// number of threads work is spread across, including the calling one
+    #[inline]
+    pub fn threads(&self) -> usize
+    {
+        self.workers.len() + 1
+    }
+}
+
+impl TaskPool for ThreadPool
+{
+    fn for_each(&self, n: usize, f: &(dyn Fn(usize) + Sync))
+    {
+        let next = AtomicUsize::new(0);
+
+        // SAFETY: only the lifetime is erased, and the job is cleared below before
+        // waiting for every worker to be done with it
+        let job = Job
+        {
+            f: unsafe { std::mem::transmute::<&(dyn Fn(usize) + Sync), &'static (dyn Fn(usize) + Sync)>(f) },
+            next: &next,
+            n,
+        };
+        {
+            let mut state = self.shared.lock();
+
+            // busy, or not worth waking the workers
+            if state.job.is_some() || self.workers.is_empty() || n < 2
+            {
+                drop(state);
+                (0..n).for_each(f);
+
+                return;
+            }
+            state.job = Some(job);
+            state.generation += 1;
+        }
+        self.shared.work.notify_all();
+
+        // take part, then stop workers from joining in
+        let res = panic::catch_unwind(AssertUnwindSafe(|| unsafe { job.run() }));
+        let mut state = self.shared.lock();
+
+        state.job = None;
+        while state.active > 0
+        {
+            state = self.shared.done
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        let worker_panic = state.panic.take();
+        drop(state);
+
+        // keep the work item's own panic message
+        if let Some(err) = res.err().or(worker_panic)
+        {
+            panic::resume_unwind(err);
+        }
+    }
+}
+
+impl Drop for ThreadPool
+{
+    fn drop(&mut self)
+    {
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_all();
+
+        for worker in self.workers.drain(..)
+        {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl PoolShared
+{
+    /// lock the state, which can't be left inconsistent by a panic
+    fn lock(&self) -> MutexGuard<'_, PoolState>
+    {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// body of a worker thread, which runs every job until the pool is dropped
+    fn work(&self)
+    {
+        let mut seen = 0;
+        let mut state = self.lock();
+
+        loop
+        {
+            if state.shutdown
+            {
+                return;
+            }
+            let job = match state.job
+            {
+                Some(job) if state.generation != seen => job,
+                _ =>
+                {
+                    state = self.work
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            };
+            seen = state.generation;
+            state.active += 1;
+            drop(state);
+
+            // SAFETY: the job is alive until `active` is back to zero
+            let res = panic::catch_unwind(AssertUnwindSafe(|| unsafe { job.run() }));
+
+            state = self.lock();
+            state.active -= 1;
+            if let Err(err) = res
+            {
+                state.panic.get_or_insert(err);
+            }
+            self.done.notify_all();
+        }
+    }
+}
+
+impl Job
+{
+    /// run work items until there's none left
+    ///
+    /// # Safety
+    /// the caller of `ThreadPool::for_each` must still be waiting for this job
+    unsafe fn run(self)
+    {
+        let (f, next) = (&*self.f, &*self.next);
+
+        loop
+        {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+
+            if i >= self.n
+            {
+                break;
+            }
+            f(i);
+        }
+    }
+}
+
+/// resource running long work, ie. pathfinding or asset loading, on threads of its
+/// own rather than blocking the frame. results are either fed back to the scene at
+/// the next sync point, see `apply_tasks`, or polled through a `TaskHandle`:
+/// ```ignore
+/// fn request_paths(mut agents: View<(Entity, &Goal)>, tasks: Res<AsyncTasks>)
+/// {
+///     for (ent, goal) in agents.iter()
+///     {
+///         let goal = *goal;
+///
+///         tasks.spawn_insert(ent, move || find_path(goal));
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncTasks
+{
+    /// cloned by every task fed back to the scene
+    sender: Sender<Completed>,
+    /// results of the tasks fed back to the scene, in the order they completed
+    receiver: Mutex<Receiver<Completed>>,
+}
+
+/// result of a task, applied to the scene at the next sync point
+type Completed = Box<dyn FnOnce(&mut Scene) + Send>;
+
+/// handle to the result of a task spawned by `AsyncTasks::spawn`
+#[derive(Debug)]
+pub struct TaskHandle<T>
+{
+    receiver: Receiver<T>,
+}
+
+impl AsyncTasks
+{
+    /// create a new, empty set of tasks
+    pub fn new() -> Self
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        Self { sender, receiver: Mutex::new(receiver) }
+    }
+
+    /// run `task` on a thread of its own, and get a handle to poll its result
+    pub fn spawn<T: Send + 'static>(&self, task: impl FnOnce() -> T + Send + 'static) -> TaskHandle<T>
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+
+        thread::spawn(move ||
+        {
+            // the handle may be dropped, and the result with it
+            let _ = sender.send(task());
+        });
+        TaskHandle { receiver }
+    }
+
+    /// run `task` on a thread of its own, then call `then` with its result and the
+    /// scene at the next sync point, see `apply_tasks`
+    pub fn spawn_then<T: Send + 'static>(&self, task: impl FnOnce() -> T + Send + 'static, then: impl FnOnce(T, &mut Scene) + Send + 'static)
+    {
+        let sender = self.sender.clone();
+
+        thread::spawn(move ||
+        {
+            let res = task();
+
+            // the resource may be dropped, and the result with it
+            let _ = sender.send(Box::new(move |scene: &mut Scene| then(res, scene)));
+        });
+    }
+
+    /// run `task` on a thread of its own, then add the components it returns to
+    /// `ent` at the next sync point, see `apply_tasks`. they're dropped if `ent`
+    /// was despawned by then
+    pub fn spawn_insert<T: CmpSet + Send + 'static>(&self, ent: Entity, task: impl FnOnce() -> T + Send + 'static)
+    {
+        self.spawn_then(task, move |cmp, scene| { scene.add(ent, cmp); });
+    }
+
+    /// take the results of the tasks completed so far, in the order they completed
+    fn drain(&self) -> Vec<Completed>
+    {
+        self.receiver
+            .lock()
+            .unwrap()
+            .try_iter()
+            .collect()
+    }
+}
+
+impl Default for AsyncTasks
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl<T> TaskHandle<T>
+{
+    /// take the result of the task, or `None` if it isn't done yet. panics if the
+    /// task panicked, or its result was already taken
+    pub fn try_take(&mut self) -> Option<T>
+    {
+        match self.receiver.try_recv()
+        {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => panic!("task panicked, or its result was already taken!"),
+        }
+    }
+
+    /// block until the task is done, and take its result. panics if the task
+    /// panicked, or its result was already taken
+    pub fn wait(self) -> T
+    {
+        self.receiver
+            .recv()
+            .expect("task panicked, or its result was already taken!")
+    }
+}
+
+/// exclusive system feeding the results of the `AsyncTasks` completed so far back
+/// to the scene, in the order they completed. this is the sync point of
+/// `AsyncTasks::spawn_then` and `AsyncTasks::spawn_insert`. see
+/// `Schedule::add_exclusive_system_to_stage`
+pub fn apply_tasks(scene: &mut Scene)
+{
+    let completed = match scene.resource::<AsyncTasks>()
+    {
+        Some(tasks) => tasks.drain(),
+        None => return,
+    };
+    for then in completed
+    {
+        then(scene);
+    }
+}
+
+/// plugin inserting the `AsyncTasks` resource, and running `apply_tasks` every
+/// frame in its own stage, before `App::UPDATE`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsyncTasksPlugin;
+
+impl AsyncTasksPlugin
+{
+    /// the stage `apply_tasks` runs in
+    pub const STAGE: StageLabel = StageLabel::new("tasks");
+}
+
+impl Plugin for AsyncTasksPlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        app.insert_resource(AsyncTasks::new());
+        app.schedule_mut().add_stage_before(Self::STAGE, App::UPDATE);
+        app.add_exclusive_system_to_stage(Self::STAGE, apply_tasks);
+    }
+}
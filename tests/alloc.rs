@@ -0,0 +1,67 @@
+//! tests that spawning doesn't allocate, with a counting global allocator
+
+use std::alloc::{ GlobalAlloc, Layout, System };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use ezgame::*;
+
+/// system allocator that counts its allocations
+struct Counting;
+
+/// number of allocations so far
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+        ALLOCS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+    {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Vel(f32, f32, f32);
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Mass(f32);
+
+#[test]
+fn spawn_many()
+{
+    let mut scene = Scene::default();
+
+    // first spawn of each set builds its archetype and caches its types
+    let ents = (0..100)
+        .map(|_| scene.spawn((Pos(0.0, 0.0, 0.0), Vel(0.0, 0.0, 0.0), Mass(1.0))))
+        .collect::<Vec<_>>();
+    for ent in ents
+    {
+        scene.despawn(ent);
+    }
+
+    // spawning into free slots of an existing archetype
+    let before = ALLOCS.load(Ordering::SeqCst);
+    for _ in 0..100
+    {
+        scene.spawn((Pos(0.0, 0.0, 0.0), Vel(0.0, 0.0, 0.0), Mass(1.0)));
+    }
+    let allocs = ALLOCS.load(Ordering::SeqCst) - before;
+
+    // the entity map may still grow, but not once per spawn
+    assert!(allocs < 10, "{} allocations for 100 spawns", allocs);
+}
@@ -0,0 +1,246 @@
+//! tests the app
+
+use std::time::Duration;
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounce(Entity);
+
+/// number of times each system ran
+#[derive(Resource, Default)]
+struct Runs(u32, u32);
+
+fn spawn_level(mut cmds: Commands, mut runs: ResMut<Runs>)
+{
+    for i in 0..10
+    {
+        cmds.spawn((Pos(0.0), Vel(i as f32)));
+    }
+    runs.0 += 1;
+}
+
+fn movement(mut bodies: View<(Entity, &mut Pos, &Vel)>, time: Res<Time>, mut bounces: ResMut<Events<Bounce>>)
+{
+    for (ent, pos, vel) in bodies.iter()
+    {
+        pos.0 += vel.0 * time.delta_secs();
+
+        if pos.0 >= 50.0
+        {
+            pos.0 = 0.0;
+            bounces.send(Bounce(ent));
+        }
+    }
+}
+
+fn count(mut runs: ResMut<Runs>)
+{
+    runs.1 += 1;
+}
+
+#[test]
+fn headless()
+{
+    let mut time = Time::new();
+    time.set_fixed_delta(Some(Duration::from_millis(100)));
+
+    let mut app = App::new();
+    app
+        .insert_resource(time)
+        .insert_resource(Runs::default())
+        .add_event::<Bounce>()
+        .add_startup_system(spawn_level)
+        .add_system(movement)
+        .add_system(count);
+
+    let mut reader = EventReader::<Bounce>::default();
+    let mut bounces = 0;
+    for _ in 0..100
+    {
+        app.update();
+
+        let events = app.scene().resource::<Events<Bounce>>().unwrap();
+        bounces += reader.read(events).count();
+    }
+
+    // startup systems only ran once
+    let runs = app.scene().resource::<Runs>().unwrap();
+    assert_eq!((runs.0, runs.1), (1, 100));
+    assert_eq!(app.scene().resource::<Time>().unwrap().frame(), 100);
+    assert_eq!(app.scene().resource::<Time>().unwrap().elapsed(), Duration::from_secs(10));
+
+    // moved for 10 seconds, bouncing back every 50 units
+    let mut pos = app
+        .scene_mut()
+        .query::<(&Pos, &Vel)>()
+        .map(|(pos, vel)| (vel.0 as u32, pos.0.round() as u32))
+        .collect::<Vec<_>>();
+    pos.sort();
+    assert_eq!(pos, (0..10).map(|i| (i, (i * 10) % 50)).collect::<Vec<_>>());
+    assert_eq!(bounces, (0..10).map(|i| (i * 10) / 50).sum::<u32>() as usize);
+}
+
+#[test]
+fn run_until_exit()
+{
+    let mut app = App::new();
+    app
+        .insert_resource(Runs::default())
+        .add_system(|mut cmds: Commands, mut runs: ResMut<Runs>|
+        {
+            runs.1 += 1;
+
+            if runs.1 == 5
+            {
+                cmds.insert_resource(AppExit);
+            }
+        })
+        .run(Duration::from_millis(1));
+
+    assert_eq!(app.scene().resource::<Runs>().unwrap().1, 5);
+    assert_eq!(app.scene().resource::<Time>().unwrap().frame(), 5);
+}
+
+/// counts frames, with a label per instance
+struct CountPlugin(bool);
+
+impl Plugin for CountPlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        app.insert_resource(Runs::default()).add_system(count);
+    }
+
+    fn is_unique(&self) -> bool
+    {
+        self.0
+    }
+}
+
+#[test]
+fn plugins()
+{
+    let mut app = App::new();
+    app
+        .add_plugin(TimePlugin { fixed_delta: Some(Duration::from_millis(20)) })
+        .add_plugin(CountPlugin(true));
+
+    for _ in 0..10
+    {
+        app.update();
+    }
+    assert_eq!(app.scene().resource::<Runs>().unwrap().1, 10);
+    assert_eq!(app.scene().resource::<Time>().unwrap().elapsed(), Duration::from_millis(200));
+
+    // opted out of being unique
+    let mut app = App::new();
+    app
+        .add_plugin(CountPlugin(false))
+        .add_plugin(CountPlugin(false))
+        .update();
+    assert_eq!(app.scene().resource::<Runs>().unwrap().1, 2);
+}
+
+#[test]
+#[should_panic(expected = "plugin `app::CountPlugin` was already added!")]
+fn duplicate_plugin()
+{
+    App::new()
+        .add_plugin(CountPlugin(true))
+        .add_plugin(CountPlugin(true));
+}
+
+#[test]
+#[cfg(feature = "glam")]
+fn transform_plugin()
+{
+    use glam::Vec3;
+
+    let mut app = App::new();
+    app.add_plugin(TransformPlugin);
+
+    let scene = app.scene_mut();
+    let parent = scene.spawn(Transform::from_translation(Vec3::X));
+    let child = scene.spawn(Transform::from_translation(Vec3::Y));
+    scene.set_parent(child, parent).unwrap();
+
+    app.update();
+
+    let global = app.scene().get::<GlobalTransform>(child).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5));
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Path(Vec<u32>);
+
+#[derive(Component, Debug, Clone, Copy)]
+struct Goal(u32);
+
+/// number of paths found so far
+#[derive(Resource, Default)]
+struct Found(u32);
+
+fn request_paths(mut agents: View<(Entity, &Goal)>, tasks: Res<AsyncTasks>)
+{
+    for (ent, goal) in agents.iter()
+    {
+        let goal = *goal;
+
+        tasks.spawn_insert(ent, move ||
+        {
+            std::thread::sleep(Duration::from_millis(10));
+            Path((0..=goal.0).collect())
+        });
+        tasks.spawn_then(move || goal.0, |_, scene| scene.resource_mut::<Found>().unwrap().0 += 1);
+    }
+}
+
+#[test]
+fn async_tasks()
+{
+    let mut app = App::new();
+    app
+        .add_plugin(AsyncTasksPlugin)
+        .insert_resource(Found::default())
+        .add_startup_system(request_paths);
+
+    let agent = app.scene_mut().spawn(Goal(3));
+
+    // the frame isn't blocked by the task...
+    app.update();
+    assert!(app.scene().get::<Path>(agent).is_none());
+
+    // ...and its result shows up within a few frames
+    for _ in 0..100
+    {
+        if app.scene().has::<Path>(agent)
+        {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+    assert_eq!(app.scene().get::<Path>(agent), Some(&Path(vec![0, 1, 2, 3])));
+    assert_eq!(app.scene().resource::<Found>().unwrap().0, 1);
+
+    // handles are polled instead
+    let tasks = app.scene().resource::<AsyncTasks>().unwrap();
+    let mut handle = tasks.spawn(|| 6 * 7);
+
+    assert_eq!(tasks.spawn(|| 1 + 1).wait(), 2);
+    loop
+    {
+        if let Some(res) = handle.try_take()
+        {
+            assert_eq!(res, 42);
+            break;
+        }
+    }
+}
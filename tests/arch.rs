@@ -0,0 +1,321 @@
+//! tests the archetype storage
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Byte(u8);
+
+#[derive(Component, Debug, PartialEq)]
+#[repr(align(16))]
+struct Aligned16(u8);
+
+#[derive(Component, Debug, PartialEq)]
+#[repr(align(32))]
+struct Aligned32([u8; 3]);
+
+#[derive(Bundle)]
+struct Mixed
+{
+    byte: Byte,
+    a16: Aligned16,
+    a32: Aligned32,
+}
+
+#[test]
+fn over_aligned_components()
+{
+    let mut scene = Scene::default();
+
+    for i in 0..1000
+    {
+        scene.spawn(Mixed { byte: Byte(i as u8), a16: Aligned16(i as u8), a32: Aligned32([i as u8; 3]) });
+    }
+
+    let arch = scene.archetype::<Mixed>().unwrap();
+
+    assert!(arch.chunks().len() > 1, "test should span multiple chunks");
+    for chunk in arch.chunks()
+    {
+        assert_eq!(chunk.components::<Aligned16>().as_ptr() as usize % 16, 0);
+        assert_eq!(chunk.components::<Aligned32>().as_ptr() as usize % 32, 0);
+
+        for ((byte, a16), a32) in chunk.components::<Byte>()
+            .iter()
+            .zip(chunk.components::<Aligned16>())
+            .zip(chunk.components::<Aligned32>())
+        {
+            assert_eq!(byte.0, a16.0);
+            assert_eq!([byte.0; 3], a32.0);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Aligned16")]
+fn missing_component_name()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Byte(0));
+
+    scene
+        .archetype::<Byte>()
+        .unwrap()
+        .chunks()[0]
+        .components::<Aligned16>();
+}
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Huge([u8; 20_000]);
+
+#[test]
+#[should_panic(expected = "largest is `Huge`")]
+fn too_large_components()
+{
+    Scene::default().spawn((Byte(0), Huge([0; 20_000])));
+}
+
+/// claims to be `Byte`, with another layout, like inconsistent meta-data from
+/// a scripting host would
+#[allow(dead_code)]
+struct FakeByte(u64);
+
+impl Component for FakeByte
+{
+    const ID: CmpId = Byte::ID;
+    const NAME: &'static str = "Byte";
+}
+
+#[test]
+#[should_panic(expected = "component `Byte` has a size of 8 and alignment of 8, but it's 1 and 1 in other archetypes!")]
+fn inconsistent_layouts()
+{
+    let mut map = ArchetypeMap::default();
+
+    map.get_or_insert::<(Byte, Aligned16)>();
+    map.get_or_insert::<FakeByte>();
+}
+
+#[test]
+fn iterate_chunks()
+{
+    let mut scene = Scene::default();
+
+    for i in 0..5000
+    {
+        scene.spawn(Byte((i % 256) as u8));
+    }
+
+    for chunk in scene.archetype_mut::<Byte>().unwrap()
+    {
+        chunk.components_mut::<Byte>().iter_mut().for_each(|b| b.0 = b.0.wrapping_add(1));
+    }
+
+    let arch = scene.archetype::<Byte>().unwrap();
+    let mut len = 0;
+
+    for chunk in arch
+    {
+        len += chunk.len();
+    }
+    assert_eq!(len, 5000);
+    assert_eq!(arch.into_iter().count(), arch.chunks().len());
+    assert_eq!(arch.chunks()[0].components::<Byte>()[0], Byte(1));
+}
+
+#[test]
+fn iter_containing()
+{
+    let mut scene = Scene::default();
+
+    for i in 0..100
+    {
+        scene.spawn(Byte(i));
+        scene.spawn((Byte(i), Aligned16(i)));
+        scene.spawn(Aligned32([i; 3]));
+    }
+
+    let n = scene
+        .archetypes()
+        .iter_containing(Byte::ID)
+        .inspect(|arch| assert!(arch.meta().contains::<Byte>()))
+        .count();
+    assert_eq!(n, 2);
+
+    // bulk edit
+    for arch in scene.archetypes_containing_mut(Byte::ID)
+    {
+        for chunk in arch.chunks_mut()
+        {
+            for byte in chunk.components_mut::<Byte>()
+            {
+                byte.0 = 7;
+            }
+        }
+    }
+    let n = scene
+        .archetypes()
+        .iter_containing(Byte::ID)
+        .flat_map(|arch| arch.chunks())
+        .flat_map(|chunk| chunk.components::<Byte>())
+        .filter(|byte| byte.0 == 7)
+        .count();
+    assert_eq!(n, 200);
+    assert_eq!(scene.archetypes().iter_containing(Aligned32::ID).count(), 1);
+}
+
+#[test]
+fn entities_components()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..5000)
+        .map(|i| scene.spawn((Byte(i as u8), Aligned16(i as u8))))
+        .collect::<Vec<_>>();
+    for ent in ents.iter().step_by(3)
+    {
+        scene.despawn(*ent);
+    }
+
+    for chunk in scene.archetype::<(Byte, Aligned16)>().unwrap().chunks()
+    {
+        let (ents, bytes) = chunk.entities_components::<Byte>();
+
+        assert_eq!(ents.len(), chunk.len());
+        assert_eq!(bytes.len(), chunk.len());
+        for (ent, byte) in ents.iter().zip(bytes)
+        {
+            assert_eq!(scene.get::<Byte>(*ent), Some(byte));
+        }
+    }
+}
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Position([f32; 3]);
+
+/// stand-in for a math library's vector type
+#[derive(Debug, PartialEq)]
+#[repr(C)]
+struct Vec3
+{
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[test]
+fn components_as()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn((Position([1.0, 2.0, 3.0]),));
+    scene.spawn((Position([4.0, 5.0, 6.0]),));
+
+    let chunk = &scene.archetype::<Position>().unwrap().chunks()[0];
+    let vecs = unsafe { chunk.components_as::<Position, Vec3>() };
+
+    assert_eq!(vecs, [Vec3 { x: 1.0, y: 2.0, z: 3.0 }, Vec3 { x: 4.0, y: 5.0, z: 6.0 }]);
+
+    // smaller alignment is fine
+    let bytes = unsafe { chunk.components_as::<Position, [u8; 12]>() };
+    assert_eq!(bytes[1][..4], 4.0f32.to_ne_bytes());
+}
+
+#[test]
+#[should_panic(expected = "can't read `Byte` components as `u16`, their layouts are incompatible!")]
+fn components_as_mismatch()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn((Byte(1),));
+    unsafe { scene.archetype::<Byte>().unwrap().chunks()[0].components_as::<Byte, u16>() };
+}
+
+#[test]
+fn fragmentation()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..10_000)
+        .map(|i| scene.spawn((Byte(i as u8),)))
+        .collect::<Vec<_>>();
+    let arch = scene.archetype::<Byte>().unwrap();
+    let cap = arch.capacity();
+    let chunks = arch.chunks().len();
+
+    // only the last chunk has room
+    assert_eq!(arch.free_chunk_count(), 1);
+    assert!(arch.fragmentation() < 1.0 / chunks as f32);
+    assert_eq!(arch.fragmentation(), (cap - 10_000) as f32 / cap as f32);
+
+    // despawning from every chunk
+    for ent in ents.iter().step_by(2)
+    {
+        scene.despawn(*ent);
+    }
+    let arch = scene.archetype::<Byte>().unwrap();
+
+    assert_eq!(arch.free_chunk_count(), chunks);
+    assert_eq!(arch.fragmentation(), (cap - 5_000) as f32 / cap as f32);
+
+    // nothing allocated
+    for ent in ents.iter().skip(1).step_by(2)
+    {
+        scene.despawn(*ent);
+    }
+    scene.prune_empty_archetypes();
+
+    let arch = scene.archetype::<Byte>().unwrap();
+    assert_eq!(arch.free_chunk_count(), 0);
+    assert_eq!(arch.fragmentation(), 0.0);
+}
+
+#[test]
+#[should_panic(expected = "attempting to access component `Aligned16` not within this archetype! it contains [`Byte`]")]
+fn missing_component()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn((Byte(1),));
+    scene.archetype::<Byte>().unwrap().chunks()[0].components::<Aligned16>();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "entity location is past the end of its chunk!")]
+fn stale_location()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn((Byte(1),));
+    let ent = scene.spawn((Byte(2),));
+    let loc = scene.location(ent).unwrap();
+
+    // the location of the last entity is past the end once it's despawned
+    scene.despawn(ent);
+    scene.archetype_mut::<(Byte,)>().unwrap().set(loc, Byte(3));
+}
+
+#[test]
+fn on_new_archetype()
+{
+    use std::sync::{ Arc, Mutex };
+
+    let created = Arc::new(Mutex::new(Vec::new()));
+    let mut scene = Scene::default();
+
+    let log = created.clone();
+    scene.on_new_archetype(move |id, types| log.lock().unwrap().push((id, types.to_vec())));
+
+    // only brand-new archetypes are reported
+    let a = scene.spawn((Byte(1),));
+    scene.spawn((Byte(2),));
+    scene.add(a, Aligned16(0));
+
+    let byte = scene.archetype::<(Byte,)>().unwrap();
+    let both = scene.archetype::<(Byte, Aligned16)>().unwrap();
+
+    assert_eq!(*created.lock().unwrap(), [(byte.id(), byte.types().to_vec()), (both.id(), both.types().to_vec())]);
+}
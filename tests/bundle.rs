@@ -0,0 +1,264 @@
+//! tests the bundle derive macros
+
+use ezgame::*;
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Vel(f32, f32, f32);
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Name(String);
+
+#[derive(Bundle)]
+struct Body
+{
+    pos: Pos,
+    vel: Vel,
+}
+
+#[derive(Bundle)]
+struct Player
+{
+    name: Name,
+    body: Body,
+}
+
+#[test]
+fn flatten_nested()
+{
+    let player = Player
+    {
+        name: Name("yohan".to_string()),
+        body: Body { pos: Pos(0.0, 1.0, 2.0), vel: Vel(3.0, 4.0, 5.0) },
+    };
+
+    let mut expected = vec![Pos::ID, Vel::ID, Name::ID];
+    expected.sort();
+
+    assert_eq!(Player::types(), &expected[..], "types weren't flattened and sorted");
+
+    let metas = Player::metas()
+        .iter()
+        .map(|meta| meta.id())
+        .collect::<Vec<_>>();
+    assert_eq!(metas, expected, "metas weren't flattened and sorted");
+
+    assert_eq!(Player::IDS.len(), 3);
+    assert!(!Player::IDS.has_duplicates());
+
+    // spawned in the archetype of its flattened components
+    let mut scene = Scene::default();
+    scene.spawn(player);
+    assert_eq!(scene.archetype::<(Name, Vel, Pos)>().unwrap().types(), &expected[..]);
+}
+
+#[test]
+fn spawn_bundle()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(1.0, 1.0, 1.0) });
+    let b = scene.spawn(Player
+    {
+        name: Name("yohan".to_string()),
+        body: Body { pos: Pos(0.0, 1.0, 2.0), vel: Vel(3.0, 4.0, 5.0) },
+    });
+
+    assert_ne!(a, b);
+}
+
+#[test]
+fn chunk_views()
+{
+    let mut scene = Scene::default();
+
+    // enough bodies to span several chunks
+    for i in 0..2000
+    {
+        scene.spawn(Body { pos: Pos(i as f32, 0.0, 0.0), vel: Vel(1.0, 2.0, 3.0) });
+    }
+    scene.spawn(Player
+    {
+        name: Name("yohan".to_string()),
+        body: Body { pos: Pos(-1.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) },
+    });
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    // every chunk with both components, including the player's
+    let mut chunks = 0;
+    let mut len = 0;
+    for BodyView { pos, vel } in scene.view::<Body>()
+    {
+        assert_eq!(pos.len(), vel.len());
+
+        for (pos, vel) in pos.iter().zip(vel.iter_mut())
+        {
+            vel.0 = pos.0;
+        }
+        chunks += 1;
+        len += pos.len();
+    }
+    assert!(chunks > 2, "bodies didn't span several chunks");
+    assert_eq!(len, 2001);
+    assert!(scene.query::<(&Pos, &Vel)>().all(|(pos, vel)| pos.0 == vel.0));
+
+    // nested bundles are viewed as nested views
+    let views = scene.view::<Player>().collect::<Vec<_>>();
+    assert_eq!(views.len(), 1);
+    assert_eq!(views[0].name[0].0, "yohan");
+    assert_eq!(views[0].body.pos[0].0, -1.0);
+}
+
+#[test]
+fn compile_fail()
+{
+    let t = trybuild::TestCases::new();
+
+    t.compile_fail("tests/ui/bundle_*.rs");
+}
+
+#[test]
+fn flatten_tuples()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn(((Pos(0.0, 1.0, 2.0), Vel(3.0, 4.0, 5.0)), Name("a".to_string())));
+    let b = scene.spawn((Name("b".to_string()), Pos(0.0, 1.0, 2.0), Vel(3.0, 4.0, 5.0)));
+    let c = scene.spawn((Vel(3.0, 4.0, 5.0), (Name("c".to_string()), (Pos(0.0, 1.0, 2.0),))));
+
+    assert_eq!(<((Pos, Vel), Name)>::IDS.sorted(), <(Name, Pos, Vel)>::IDS.sorted());
+    assert_eq!(<((Pos, Vel), Name)>::IDS.sorted(), Player::IDS.sorted());
+
+    // every spawn landed in the same archetype
+    let arch = scene.archetype::<(Name, (Vel, Pos))>().unwrap();
+    assert_eq!(arch.chunks()[0].entities(), &[a, b, c]);
+    assert_eq!(scene.archetypes().generation(), 1);
+
+    // nested and flat tuples mix with bundles
+    scene.spawn((Player
+    {
+        name: Name("d".to_string()),
+        body: Body { pos: Pos(0.0, 1.0, 2.0), vel: Vel(3.0, 4.0, 5.0) },
+    },));
+    assert_eq!(scene.archetypes().generation(), 1);
+}
+
+/// defines numbered components
+macro_rules! numbered
+{
+    ($($name:ident),*) =>
+    {
+        $(
+            #[derive(Component, Debug, PartialEq)]
+            struct $name(u32);
+        )*
+    };
+}
+
+numbered!
+(
+    C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14,
+    C15, C16, C17, C18, C19, C20, C21, C22, C23, C24, C25, C26, C27, C28, C29
+);
+
+#[test]
+fn widest_tuple()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn
+    ((
+        C0(0), C1(1), C2(2), C3(3), C4(4), C5(5), C6(6), C7(7), C8(8), C9(9), C10(10), C11(11), C12(12),
+        C13(13), C14(14), C15(15), C16(16), C17(17), C18(18), C19(19), C20(20), C21(21), C22(22),
+        C23(23), C24(24), C25(25),
+    ));
+
+    // last elements are written to their own columns
+    assert_eq!(scene.get::<C23>(ent), Some(&C23(23)));
+    assert_eq!(scene.get::<C24>(ent), Some(&C24(24)));
+    assert_eq!(scene.get::<C25>(ent), Some(&C25(25)));
+    assert_eq!(scene.archetypes().inner()[0].types().len(), 26);
+}
+
+#[test]
+fn nested_past_limit()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn
+    ((
+        (
+            C0(0), C1(1), C2(2), C3(3), C4(4), C5(5), C6(6), C7(7), C8(8), C9(9), C10(10), C11(11), C12(12),
+            C13(13), C14(14), C15(15), C16(16), C17(17), C18(18), C19(19),
+        ),
+        (C20(20), C21(21), C22(22), C23(23), C24(24), C25(25), C26(26), C27(27), C28(28), C29(29)),
+    ));
+
+    let values = [
+        scene.get::<C0>(ent).unwrap().0, scene.get::<C1>(ent).unwrap().0, scene.get::<C2>(ent).unwrap().0,
+        scene.get::<C3>(ent).unwrap().0, scene.get::<C4>(ent).unwrap().0, scene.get::<C5>(ent).unwrap().0,
+        scene.get::<C6>(ent).unwrap().0, scene.get::<C7>(ent).unwrap().0, scene.get::<C8>(ent).unwrap().0,
+        scene.get::<C9>(ent).unwrap().0, scene.get::<C10>(ent).unwrap().0, scene.get::<C11>(ent).unwrap().0,
+        scene.get::<C12>(ent).unwrap().0, scene.get::<C13>(ent).unwrap().0, scene.get::<C14>(ent).unwrap().0,
+        scene.get::<C15>(ent).unwrap().0, scene.get::<C16>(ent).unwrap().0, scene.get::<C17>(ent).unwrap().0,
+        scene.get::<C18>(ent).unwrap().0, scene.get::<C19>(ent).unwrap().0, scene.get::<C20>(ent).unwrap().0,
+        scene.get::<C21>(ent).unwrap().0, scene.get::<C22>(ent).unwrap().0, scene.get::<C23>(ent).unwrap().0,
+        scene.get::<C24>(ent).unwrap().0, scene.get::<C25>(ent).unwrap().0, scene.get::<C26>(ent).unwrap().0,
+        scene.get::<C27>(ent).unwrap().0, scene.get::<C28>(ent).unwrap().0, scene.get::<C29>(ent).unwrap().0,
+    ];
+    assert!(values.iter().enumerate().all(|(i, n)| i as u32 == *n));
+    assert_eq!(scene.archetypes().inner()[0].types().len(), 30);
+}
+
+/// number of `Counted`s dropped so far
+static DROPPED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[derive(Component, Debug, PartialEq)]
+struct Counted(u64, String);
+
+impl Drop for Counted
+{
+    fn drop(&mut self)
+    {
+        DROPPED.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn dyn_set()
+{
+    let dropped = || DROPPED.load(std::sync::atomic::Ordering::SeqCst);
+    let mut scene = Scene::default();
+
+    // spawned, so moved into the scene
+    let mut set = DynCmpSet::new();
+    set.push(Counted(1, "one".to_string())).push(Name("a".to_string()));
+    unsafe { set.push_raw(Pos::META, &[0; 12]) };
+    assert_eq!(set.len(), 3);
+
+    let ent = scene.spawn(set);
+    assert_eq!(dropped(), 0);
+    assert_eq!(scene.get::<Counted>(ent).map(|c| (c.0, c.1.as_str())), Some((1, "one")));
+    assert!(scene.archetype::<(Pos, Name, Counted)>().is_some());
+
+    // added to an existing entity
+    let mut set = DynCmpSet::new();
+    set.push(Vel(1.0, 2.0, 3.0));
+    assert!(scene.add(ent, set));
+    assert!(scene.archetype::<(Pos, Vel, Name, Counted)>().is_some());
+    assert_eq!(dropped(), 0);
+
+    // never spawned, so dropped with the set
+    let mut set = DynCmpSet::new();
+    set.push(Counted(2, "two".to_string()));
+    drop(set);
+    assert_eq!(dropped(), 1);
+
+    scene.despawn(ent);
+    assert_eq!(dropped(), 2);
+}
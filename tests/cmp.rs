@@ -1,5 +1,8 @@
 //! tests the component derive macros
 
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::mem::ManuallyDrop;
+
 use ezgame::*;
 
 #[derive(Component)]
@@ -9,6 +12,7 @@ struct CmpA;
 struct CmpB;
 
 #[derive(Component)]
+#[allow(dead_code)]
 struct CmpC(i32, u32);
 
 #[test]
@@ -31,4 +35,188 @@ fn print_meta()
     println!("a: {:?}", CmpA::META);
     println!("b: {:?}", CmpB::META);
     println!("c: {:?}", CmpC::META);
-}
\ No newline at end of file
+}
+
+/// number of `DropCounter`s dropped so far
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct DropCounter;
+
+impl Drop for DropCounter
+{
+    fn drop(&mut self)
+    {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Component)]
+#[allow(dead_code)]
+enum CmpEnum
+{
+    Empty,
+    Name(String),
+    Counted(u8, DropCounter),
+}
+
+#[test]
+fn enum_meta()
+{
+    assert_eq!(CmpEnum::META.size(), std::mem::size_of::<CmpEnum>());
+    assert_eq!(CmpEnum::META.alignment(), std::mem::align_of::<CmpEnum>());
+
+    // drops the right variant...
+    let mut counted = ManuallyDrop::new(CmpEnum::Counted(0, DropCounter));
+    unsafe { (CmpEnum::META.drop_fn().unwrap())(&mut *counted as *mut CmpEnum as *mut u8) };
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+
+    // ...and only that one
+    let mut empty = ManuallyDrop::new(CmpEnum::Empty);
+    unsafe { (CmpEnum::META.drop_fn().unwrap())(&mut *empty as *mut CmpEnum as *mut u8) };
+    let mut name = ManuallyDrop::new(CmpEnum::Name("yohan".to_string()));
+    unsafe { (CmpEnum::META.drop_fn().unwrap())(&mut *name as *mut CmpEnum as *mut u8) };
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn needs_drop()
+{
+    assert!(CmpA::META.drop_fn().is_none());
+    assert!(CmpC::META.drop_fn().is_none());
+    assert!(CmpEnum::META.drop_fn().is_some());
+}
+
+#[test]
+fn derive_forms()
+{
+    let t = trybuild::TestCases::new();
+
+    t.pass("tests/ui/cmp_ok_*.rs");
+    t.compile_fail("tests/ui/cmp_err_*.rs");
+}
+
+/// stand-in for a module of types generated by another macro
+mod proto
+{
+    macro_rules! packets
+    {
+        ($($name:ident($ty:ty);)*) => { $(#[derive(Debug, PartialEq)] pub struct $name(pub $ty);)* };
+    }
+    packets!
+    {
+        Ping(u64);
+        Chat(String);
+    }
+}
+
+impl_component!(crate::proto::Ping);
+impl_component!(crate::proto::Chat);
+
+mod inner
+{
+    pub struct Pong;
+
+    // invoked elsewhere than the crate root, with odd spacing
+    ezgame::impl_component!(crate :: inner :: Pong);
+}
+
+#[test]
+fn impl_component()
+{
+    assert_ne!(proto::Ping::ID, proto::Chat::ID);
+    assert_ne!(proto::Ping::ID, CmpA::ID);
+    assert_eq!(proto::Ping::NAME, "Ping");
+    assert_eq!(proto::Chat::META.name(), "Chat");
+
+    // from the crate root, regardless of the call site
+    assert_eq!(proto::Ping::ID, CmpId::of_path("cmp::proto::Ping"));
+    assert_eq!(proto::Chat::ID, CmpId::of_path("cmp::proto::Chat"));
+    assert_eq!(inner::Pong::ID, CmpId::of_path("cmp::inner::Pong"));
+
+    let mut scene = Scene::default();
+    let ent = scene.spawn((proto::Ping(7), proto::Chat("hi".to_string())));
+
+    assert_eq!(scene.get::<proto::Ping>(ent), Some(&proto::Ping(7)));
+    assert_eq!(scene.get::<proto::Chat>(ent), Some(&proto::Chat("hi".to_string())));
+}
+
+#[derive(Component, Debug, PartialEq)]
+#[component(eq)]
+struct Score(u32);
+
+#[test]
+fn values_eq()
+{
+    let (a, b, c) = (Score(1), Score(1), Score(2));
+    let ptr = |s: &Score| s as *const Score as *const u8;
+
+    unsafe
+    {
+        assert_eq!(Score::META.values_eq(ptr(&a), ptr(&b)), Some(true));
+        assert_eq!(Score::META.values_eq(ptr(&a), ptr(&c)), Some(false));
+
+        // `CmpA` has no equality function
+        let unit = &CmpA as *const CmpA as *const u8;
+        assert_eq!(CmpA::META.values_eq(unit, unit), None);
+    }
+    assert!(CmpMeta::of_eq::<Score>().eq_fn().is_some());
+}
+
+mod a
+{
+    #[derive(ezgame::Component)]
+    pub struct Shared;
+}
+
+mod b
+{
+    #[derive(ezgame::Component)]
+    pub struct Shared;
+}
+
+#[test]
+fn path_ids()
+{
+    // same name, different modules
+    assert_ne!(a::Shared::ID, b::Shared::ID);
+    assert_eq!(a::Shared::NAME, b::Shared::NAME);
+
+    // stable, and independent of the derive's expansion order
+    #[derive(Component)] struct Here; let line = line!();
+    assert_eq!(Here::ID, CmpId::of_path(&format!("{}::Here@{}:33", module_path!(), line)));
+    assert!(!CmpA::ID.is_dynamic());
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Data(u32);
+
+/// spawn a `Data(String)`, from a type of the same name as another function's
+fn spawn_local(scene: &mut Scene) -> Entity
+{
+    #[derive(Component)]
+    #[allow(dead_code)]
+    struct Data(String);
+
+    assert_ne!(Data::ID, local_id());
+    scene.spawn(Data("hi".to_string()))
+}
+
+fn local_id() -> CmpId
+{
+    #[derive(Component)]
+    #[allow(dead_code)]
+    struct Data(Vec<u64>);
+
+    Data::ID
+}
+
+#[test]
+fn local_ids()
+{
+    let mut scene = Scene::default();
+    let ent = spawn_local(&mut scene);
+
+    // same path, but different types
+    assert_ne!(Data::ID, local_id());
+    assert_eq!(scene.get::<Data>(ent), None);
+}
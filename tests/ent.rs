@@ -0,0 +1,278 @@
+//! tests the entities
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32, f32);
+
+#[test]
+fn packed_location()
+{
+    assert_eq!(std::mem::size_of::<EntityLocation>(), 8);
+
+    // null is never a valid archetype
+    assert_eq!(EntityLocation::NULL.archetype(), EntityLocation::MAX_ARCHETYPE);
+
+    let mut scene = Scene::default();
+
+    // span a few chunks
+    let locs = (0..5000)
+        .map(|i| scene.spawn_mut(Pos(i as f32, 0.0, 0.0)).1.location())
+        .collect::<Vec<_>>();
+
+    let arch = scene.archetype::<Pos>().unwrap();
+    for (i, loc) in locs.iter().enumerate()
+    {
+        assert_ne!(*loc, EntityLocation::NULL);
+        assert_eq!(loc.archetype(), arch.id());
+        assert_eq!(arch.get::<Pos>(*loc), Some(&Pos(i as f32, 0.0, 0.0)));
+    }
+    assert_eq!(locs.last().unwrap().chunk(), arch.chunks().len() - 1);
+    assert_eq!(locs[1].index(), 1);
+}
+
+#[test]
+fn spawn_batch()
+{
+    let mut scene = Scene::default();
+
+    // nothing is allocated
+    assert!(scene.spawn_batch(std::iter::empty::<Pos>()).is_empty());
+    assert!(scene.spawn_batch(Vec::<(Pos, Pos)>::new()).is_empty());
+    assert_eq!(scene.archetypes().generation(), 0);
+
+    // IDs are allocated all at once
+    let ents = scene.spawn_batch((0..100).map(|i| Pos(i as f32, 0.0, 0.0)));
+    assert_eq!(ents.len(), 100);
+    for (i, ent) in ents.iter().enumerate()
+    {
+        assert_eq!(ent.id(), ents[0].id() + i as u64);
+        assert_eq!(scene.get::<Pos>(*ent), Some(&Pos(i as f32, 0.0, 0.0)));
+    }
+    scene.validate();
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Tile(usize, usize);
+
+#[test]
+fn spawn_with()
+{
+    let mut scene = Scene::default();
+
+    assert!(scene.spawn_with(0, |_| Pos(0.0, 0.0, 0.0)).is_empty());
+    assert_eq!(scene.archetypes().generation(), 0);
+
+    // a 100x100 grid
+    let tiles = scene.spawn_with(100 * 100, |i| (Tile(i % 100, i / 100), Pos((i % 100) as f32, (i / 100) as f32, 0.0)));
+    assert_eq!(tiles.len(), 10_000);
+    assert_eq!(scene.archetypes().generation(), 1);
+    for (i, ent) in tiles.iter().enumerate()
+    {
+        assert_eq!(ent.id(), tiles[0].id() + i as u64);
+        assert_eq!(scene.get::<Tile>(*ent), Some(&Tile(i % 100, i / 100)));
+    }
+
+    // sets made at runtime may differ
+    let mixed = scene.spawn_with(10, |i|
+    {
+        let mut set = DynCmpSet::new();
+
+        set.push(Tile(i, 0));
+        if i % 2 == 0
+        {
+            set.push(Pos(i as f32, 0.0, 0.0));
+        }
+        set
+    });
+    assert_eq!(scene.archetypes().generation(), 2);
+    assert!(scene.has::<Pos>(mixed[4]));
+    assert!(!scene.has::<Pos>(mixed[5]));
+    assert_eq!(scene.get::<Tile>(mixed[5]), Some(&Tile(5, 0)));
+    scene.validate();
+}
+
+#[test]
+fn entity_from_id()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Pos(1.0, 2.0, 3.0),));
+    let b = scene.spawn_empty();
+
+    // round-trip through the wire
+    let id: u64 = a.into();
+    assert_eq!(id, a.id());
+    assert_eq!(scene.entity(id), Some(a));
+    assert_eq!(scene.entity(b.id()), Some(b));
+
+    // dead, or never spawned into this scene
+    scene.despawn(a);
+    assert_eq!(scene.entity(id), None);
+    assert_eq!(Scene::default().entity(b.id()), None);
+    assert_eq!(scene.entity(u64::MAX), None);
+}
+
+#[test]
+fn entity_map_remove()
+{
+    let mut scene = Scene::default();
+
+    // contiguous IDs, so some of them share a chunk of the map
+    let ents = scene.spawn_batch((0..40).map(|i| (Pos(i as f32, 0.0, 0.0),)));
+    let loc = scene.location(ents[0]).unwrap();
+    let first = ents
+        .iter()
+        .position(|e| e.id() % 16 == 0)
+        .unwrap();
+    let (a, b, c) = (ents[first], ents[first + 1], ents[first + 2]);
+
+    let mut map = EntityMap::default();
+
+    // never inserted, and its chunk doesn't exist
+    map.remove(a);
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+
+    map.insert(a, loc);
+    map.insert(b, loc);
+    map.insert(b, loc);
+    assert_eq!(map.len(), 2);
+
+    // never inserted, but its chunk exists
+    map.remove(c);
+    assert_eq!(map.len(), 2);
+    assert!(!map.contains(c));
+
+    // removed twice
+    map.remove(a);
+    map.remove(a);
+    assert_eq!(map.len(), 1);
+    assert!(!map.is_empty());
+    assert!(map.contains(b));
+
+    // the chunk is dropped along with its last entity
+    map.remove(b);
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+    map.remove(b);
+    assert!(map.is_empty());
+
+    // ...and recreated as needed
+    map.insert(c, loc);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(c), loc);
+}
+
+#[test]
+fn spawn_located()
+{
+    let mut scene = Scene::default();
+
+    let (a, loc_a) = scene.spawn_located((Pos(1.0, 2.0, 3.0),));
+    let (b, loc_b) = scene.spawn_located((Pos(4.0, 5.0, 6.0),));
+
+    assert_eq!(scene.location(a), Some(loc_a));
+    assert_eq!(scene.location(b), Some(loc_b));
+    assert_eq!(loc_a.archetype(), loc_b.archetype());
+    assert_eq!(loc_b.index(), loc_a.index() + 1);
+
+    let arch = scene.archetype_by_id(loc_b.archetype()).unwrap();
+    assert_eq!(arch.get::<Pos>(loc_b), Some(&Pos(4.0, 5.0, 6.0)));
+    assert_eq!(arch.chunks()[loc_a.chunk()].entities()[loc_a.index()], a);
+}
+
+#[test]
+fn id_hasher()
+{
+    use std::collections::HashMap;
+    use std::hash::{ BuildHasherDefault, Hasher };
+
+    let mut scene = Scene::default();
+    let ents = (0..1000)
+        .map(|i| scene.spawn((Pos(i as f32, 0.0, 0.0),)))
+        .collect::<Vec<_>>();
+
+    // keys user maps
+    let mut map = HashMap::<Entity, usize, BuildHasherDefault<IdHasher>>::default();
+    for (i, ent) in ents.iter().enumerate()
+    {
+        map.insert(*ent, i);
+    }
+    for (i, ent) in ents.iter().enumerate()
+    {
+        assert_eq!(map.get(ent), Some(&i));
+    }
+
+    // consecutive IDs don't collide
+    let hash = |n: u64|
+    {
+        let mut hasher = IdHasher::default();
+        hasher.write_u64(n);
+        hasher.finish()
+    };
+    let mut hashes = (0..1000).map(hash).collect::<Vec<_>>();
+    hashes.sort();
+    hashes.dedup();
+    assert_eq!(hashes.len(), 1000);
+}
+
+#[test]
+fn reserve_block()
+{
+    // every thread spawns into its own scene, reserving IDs in blocks
+    let threads = (0..4)
+        .map(|_| std::thread::spawn(||
+        {
+            let mut scene = Scene::default();
+
+            Entity::reserve_block(64);
+
+            let ids = (0..1000)
+                .map(|i| scene.spawn(Pos(i as f32, 0.0, 0.0)).id())
+                .collect::<Vec<_>>();
+            (scene, ids)
+        }))
+        .collect::<Vec<_>>();
+
+    // ...which are then merged into one
+    let mut world = Scene::default();
+    let mut ids = Vec::new();
+
+    for thread in threads
+    {
+        let (mut scene, spawned) = thread.join().unwrap();
+
+        world.append(&mut scene);
+        assert_eq!(scene.query::<Entity>().count(), 0);
+
+        ids.extend(spawned);
+    }
+    assert_eq!(world.query::<Entity>().count(), 4000);
+    world.validate();
+    assert_eq!(world.query::<&Pos>().filter(|pos| pos.0 == 999.0).count(), 4);
+
+    // blocks never overlap, and entities keep their IDs
+    for id in &ids
+    {
+        assert_eq!(world.entity(*id).map(|ent| ent.id()), Some(*id));
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), 4000);
+
+    // IDs are handed out in order within a block, and batches larger than a
+    // block go straight to the global cursor
+    let mut scene = Scene::default();
+
+    Entity::reserve_block(8);
+
+    let a = scene.spawn(Pos(0.0, 0.0, 0.0));
+    let b = scene.spawn(Pos(0.0, 0.0, 0.0));
+    let batch = scene.spawn_batch((0..100).map(|_| (Pos(0.0, 0.0, 0.0),)));
+
+    assert_eq!(b.id(), a.id() + 1);
+    assert_eq!(batch.len(), 100);
+
+    Entity::reserve_block(0);
+}
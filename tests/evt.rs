@@ -0,0 +1,103 @@
+//! tests the events
+
+use ezgame::*;
+
+const UPDATE: StageLabel = StageLabel::new("update");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Collision(u32);
+
+/// events seen by each reader
+#[derive(Resource, Default)]
+struct Seen(Vec<u32>, Vec<u32>);
+
+#[derive(Resource, Default)]
+struct Frame(u32);
+
+fn collide(mut frame: ResMut<Frame>, mut events: ResMut<Events<Collision>>)
+{
+    events.send(Collision(frame.0));
+    frame.0 += 1;
+}
+
+#[test]
+fn readers()
+{
+    let mut fast = EventReader::<Collision>::default();
+    let mut slow = EventReader::<Collision>::default();
+    let mut schedule = Schedule::new();
+
+    // the fast reader reads every frame, the slow one every third frame
+    schedule
+        .add_event::<Collision>()
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, collide)
+        .add_exclusive_system_to_stage(UPDATE, move |scene: &mut Scene|
+        {
+            let frame = scene.resource::<Frame>().unwrap().0;
+            let events = scene.resource::<Events<Collision>>().unwrap();
+
+            let fast = fast.read(events).map(|ev| ev.0).collect::<Vec<_>>();
+            let slow = match frame % 3
+            {
+                0 => slow.read(events).map(|ev| ev.0).collect::<Vec<_>>(),
+                _ => Vec::new(),
+            };
+            let seen = scene.resource_mut::<Seen>().unwrap();
+
+            seen.0.extend(fast);
+            seen.1.extend(slow);
+        });
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Events::<Collision>::new());
+    scene.insert_resource(Frame::default());
+    scene.insert_resource(Seen::default());
+    for _ in 0..9
+    {
+        schedule.run(&mut scene);
+    }
+
+    // every event is seen exactly once by the fast reader...
+    let seen = scene.resource::<Seen>().unwrap();
+    assert_eq!(seen.0, (0..9).collect::<Vec<_>>());
+
+    // ...while the slow one misses those dropped in-between its reads, on frames 3 and 6
+    assert_eq!(seen.1, [1, 2, 4, 5, 7, 8]);
+
+    // only this frame's event and the previous one's are still buffered
+    let events = scene.resource::<Events<Collision>>().unwrap();
+    assert_eq!(events.iter().copied().collect::<Vec<_>>(), [Collision(7), Collision(8)]);
+}
+
+#[test]
+fn missed_events()
+{
+    let mut events = Events::new();
+    let mut reader = EventReader::default();
+
+    events.send(0);
+    events.send(1);
+    events.update();
+    events.send(2);
+    assert_eq!(reader.len(&events), 3);
+    assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(reader.is_empty(&events));
+
+    // skips two frames
+    events.send(3);
+    events.update();
+    events.send(4);
+    events.update();
+    events.send(5);
+    assert_eq!(reader.len(&events), 2);
+    assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), [4, 5]);
+    assert_eq!(reader.missed(), 1);
+
+    // a reader made now only sees future events
+    let mut late = events.reader();
+    events.send(6);
+    assert_eq!(late.read(&events).copied().collect::<Vec<_>>(), [6]);
+    assert_eq!(late.missed(), 0);
+}
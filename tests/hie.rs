@@ -0,0 +1,155 @@
+//! tests the scene hierarchy
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Name(&'static str);
+
+/// root -> (a -> (a1, a2), b)
+fn tree(scene: &mut Scene) -> [Entity; 5]
+{
+    let root = scene.spawn(Name("root"));
+    let a = scene.spawn(Name("a"));
+    let b = scene.spawn(Name("b"));
+    let a1 = scene.spawn(Name("a1"));
+    let a2 = scene.spawn(Name("a2"));
+
+    scene.set_parent(a, root).unwrap();
+    scene.set_parent(b, root).unwrap();
+    scene.set_parent(a1, a).unwrap();
+    scene.set_parent(a2, a).unwrap();
+
+    [root, a, b, a1, a2]
+}
+
+#[test]
+fn build_and_reparent()
+{
+    let mut scene = Scene::default();
+    let [root, a, b, a1, a2] = tree(&mut scene);
+
+    assert_eq!(scene.children(root), [a, b]);
+    assert_eq!(scene.children(a), [a1, a2]);
+    assert_eq!(scene.children(a1), []);
+    assert_eq!(scene.parent(a1), Some(a));
+    assert_eq!(scene.parent(root), None);
+
+    // moved from `a`'s children to `b`'s
+    scene.set_parent(a2, b).unwrap();
+    assert_eq!(scene.children(a), [a1]);
+    assert_eq!(scene.children(b), [a2]);
+    assert_eq!(scene.parent(a2), Some(b));
+    assert_eq!(scene.get::<Name>(a2), Some(&Name("a2")));
+
+    // made a root
+    assert_eq!(scene.remove_parent(a2), Some(b));
+    assert_eq!(scene.remove_parent(a2), None);
+    assert_eq!(scene.children(b), []);
+}
+
+#[test]
+fn reject_cycles()
+{
+    let mut scene = Scene::default();
+    let [root, a, _, a1, _] = tree(&mut scene);
+
+    assert_eq!(scene.set_parent(root, a1), Err(EcsError::HierarchyCycle(root, a1)));
+    assert_eq!(scene.set_parent(a, a), Err(EcsError::HierarchyCycle(a, a)));
+
+    // left untouched
+    assert_eq!(scene.parent(root), None);
+    assert_eq!(scene.parent(a), Some(root));
+
+    scene.despawn(a1);
+    assert_eq!(scene.set_parent(a1, root), Err(EcsError::EntityNotFound(a1)));
+}
+
+#[test]
+fn despawn()
+{
+    let mut scene = Scene::default();
+    let [root, a, b, a1, a2] = tree(&mut scene);
+
+    // removed from its parent's children, and its own children orphaned
+    scene.despawn(a);
+    assert_eq!(scene.children(root), [b]);
+    assert_eq!(scene.parent(a1), None);
+    assert_eq!(scene.parent(a2), None);
+    assert_eq!(scene.get::<Name>(a1), Some(&Name("a1")));
+
+    // takes the whole subtree down
+    scene.set_parent(a1, b).unwrap();
+    scene.set_parent(a2, a1).unwrap();
+    let c = scene.spawn(Name("c"));
+    scene.set_parent(c, root).unwrap();
+
+    assert!(scene.despawn_recursive(b));
+    assert!(!scene.despawn_recursive(b));
+    for ent in &[b, a1, a2]
+    {
+        assert!(scene.location(*ent).is_none());
+    }
+    assert_eq!(scene.children(root), [c]);
+    assert_eq!(scene.get::<Name>(c), Some(&Name("c")));
+}
+
+#[test]
+fn remove_components()
+{
+    let mut scene = Scene::default();
+    let [root, a, b, a1, a2] = tree(&mut scene);
+
+    // like `Scene::remove_parent`
+    assert!(scene.remove::<Parent>(a1));
+    assert_eq!(scene.children(a), [a2]);
+    assert_eq!(scene.parent(a1), None);
+
+    // its children are orphaned
+    assert!(scene.remove::<(Name, Children)>(root));
+    assert_eq!(scene.parent(a), None);
+    assert_eq!(scene.parent(b), None);
+    assert_eq!(scene.children(root), []);
+
+    // both at once, via commands
+    scene.set_parent(a, b).unwrap();
+    scene.for_each_mut::<Name>(|ent, name, cmds|
+    {
+        if name.0 == "a"
+        {
+            cmds.remove::<(Parent, Children)>(ent);
+        }
+    });
+    assert_eq!(scene.children(b), []);
+    assert_eq!(scene.parent(a2), None);
+    assert_eq!(scene.get::<Name>(a), Some(&Name("a")));
+}
+
+#[test]
+#[cfg(feature = "glam")]
+fn propagate_transforms()
+{
+    use glam::{ Quat, Vec3 };
+
+    let mut scene = Scene::default();
+    let [root, a, b, a1, a2] = tree(&mut scene);
+
+    scene.add(root, Transform::from_translation(Vec3::new(1.0, 0.0, 0.0)));
+    scene.add(a, Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)));
+    scene.add(b, Transform::from_scale(Vec3::splat(2.0)));
+    scene.add(a1, Transform::from_translation(Vec3::new(1.0, 0.0, 0.0)));
+
+    ezgame::propagate_transforms(&mut scene);
+
+    let global = |scene: &Scene, ent| scene.get::<GlobalTransform>(ent).map(GlobalTransform::translation);
+    assert!(global(&scene, root).unwrap().abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5));
+    assert!(global(&scene, b).unwrap().abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5));
+    // rotated by `a`, then moved by `root`
+    assert!(global(&scene, a1).unwrap().abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5));
+    // no transform of its own
+    assert_eq!(global(&scene, a2), None);
+
+    // follows its new parent
+    scene.set_parent(a1, b).unwrap();
+    ezgame::propagate_transforms(&mut scene);
+    assert!(global(&scene, a1).unwrap().abs_diff_eq(Vec3::new(3.0, 0.0, 0.0), 1e-5));
+}
@@ -0,0 +1,30 @@
+//! tests the automatic registration of components
+#![cfg(feature = "inventory")]
+
+use ezgame::*;
+
+#[derive(Component)]
+#[allow(dead_code)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component)]
+#[component(name = "Velocity")]
+#[allow(dead_code)]
+struct Vel(f32, f32, f32);
+
+#[test]
+fn registered_at_startup()
+{
+    // no call to `Registry::register`
+    assert_eq!(Registry::by_id(Pos::ID).map(CmpMeta::name), Some("Pos"));
+    assert_eq!(Registry::by_name("Velocity").map(CmpMeta::id), Some(Vel::ID));
+
+    let ids = registered_components()
+        .map(CmpMeta::id)
+        .collect::<Vec<_>>();
+    assert!(ids.contains(&Pos::ID) && ids.contains(&Vel::ID));
+
+    // scenes fall back to the registry
+    let scene = Scene::default();
+    assert_eq!(scene.component_by_name("Velocity").map(CmpMeta::id), Some(Vel::ID));
+}
@@ -0,0 +1,43 @@
+//! tests the math crates' components
+#![cfg(all(feature = "glam", feature = "cgmath"))]
+
+use ezgame::*;
+
+#[test]
+fn glam_components()
+{
+    use glam::{ Quat, Vec3 };
+
+    let mut scene = Scene::default();
+
+    let ents = (0..1000)
+        .map(|i| scene.spawn((Vec3::splat(i as f32), Quat::from_rotation_y(i as f32))))
+        .collect::<Vec<_>>();
+
+    // SIMD types are 16-byte aligned
+    for chunk in scene.archetype::<(Vec3, Quat)>().unwrap()
+    {
+        assert_eq!(chunk.components::<Quat>().as_ptr() as usize % 16, 0);
+    }
+    for (i, ent) in ents.iter().enumerate()
+    {
+        assert_eq!(scene.get::<Vec3>(*ent), Some(&Vec3::splat(i as f32)));
+        assert_eq!(scene.get::<Quat>(*ent), Some(&Quat::from_rotation_y(i as f32)));
+    }
+    assert_ne!(Vec3::ID, Quat::ID);
+    assert_eq!(Quat::NAME, "glam::Quat");
+}
+
+#[test]
+fn cgmath_components()
+{
+    use cgmath::{ Matrix4, Vector3 };
+
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn((Vector3::new(1.0f32, 2.0, 3.0), Matrix4::<f32>::from_scale(2.0)));
+
+    assert_eq!(scene.get::<Vector3<f32>>(ent), Some(&Vector3::new(1.0, 2.0, 3.0)));
+    assert_eq!(scene.get::<Matrix4<f32>>(ent), Some(&Matrix4::from_scale(2.0)));
+    assert_ne!(<Vector3<f32>>::ID, <glam::Vec3>::ID);
+}
@@ -0,0 +1,476 @@
+//! tests the queries
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(f32, f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Tag;
+
+#[derive(Bundle)]
+struct Body
+{
+    pos: Pos,
+    vel: Vel,
+}
+
+#[test]
+fn query_state_cache()
+{
+    let mut scene = Scene::default();
+    let mut query = QueryState::<&Pos>::new();
+
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+    scene.spawn(Vel(0.0, 0.0, 0.0));
+    scene.spawn(Body { pos: Pos(1.0, 1.0, 1.0), vel: Vel(1.0, 1.0, 1.0) });
+
+    assert_eq!(query.archetypes(&scene).count(), 2);
+
+    // stable composition doesn't change the cache
+    let generation = scene.archetypes().generation();
+    scene.spawn(Pos(2.0, 2.0, 2.0));
+    assert_eq!(scene.archetypes().generation(), generation);
+    assert_eq!(query.archetypes(&scene).count(), 2);
+
+    // new archetype mid-game is picked up
+    let ent = scene.spawn(Vel(3.0, 3.0, 3.0));
+    scene.add(ent, Tag);
+    scene.add(ent, Pos(3.0, 3.0, 3.0));
+    assert_eq!(query.archetypes(&scene).count(), 3);
+
+    // mutate every `Pos` through the cache
+    for arch in query.archetypes_mut(&mut scene)
+    {
+        for chunk in arch.chunks_mut()
+        {
+            for pos in chunk.components_mut::<Pos>()
+            {
+                pos.0 = -1.0;
+            }
+        }
+    }
+    let n = query
+        .archetypes(&scene)
+        .flat_map(|arch| arch.chunks())
+        .flat_map(|chunk| chunk.components::<Pos>())
+        .filter(|pos| pos.0 == -1.0)
+        .count();
+    assert_eq!(n, 4);
+}
+
+#[test]
+fn query_state_iter()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(1.0, 0.0, 0.0) });
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    let mut query = scene.query_state::<(&mut Pos, &Vel)>();
+    assert_eq!(query.matches().len(), 1);
+
+    for (pos, vel) in query.iter(&mut scene)
+    {
+        pos.0 += vel.0;
+    }
+    assert_eq!(query.iter(&mut scene).count(), 1);
+
+    // new matching archetype mid-game is picked up on the next iteration
+    let ent = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(2.0, 0.0, 0.0) });
+    scene.add(ent, Tag);
+    assert_eq!(query.matches().len(), 1);
+
+    for (pos, vel) in query.iter(&mut scene)
+    {
+        pos.0 += vel.0;
+    }
+    assert_eq!(query.matches().len(), 2);
+
+    let mut xs = scene
+        .query::<&Pos>()
+        .map(|pos| pos.0)
+        .collect::<Vec<_>>();
+    xs.sort_by(f32::total_cmp);
+    assert_eq!(xs, [0.0, 2.0, 2.0]);
+
+    // a cache used with another scene starts over
+    let mut other = Scene::default();
+    other.spawn(Pos(0.0, 0.0, 0.0));
+    assert_eq!(query.iter(&mut other).count(), 0);
+    assert!(query.matches().is_empty());
+}
+
+#[test]
+fn query_chunks()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(1.0, 2.0, 3.0) });
+    let b = scene.spawn((Pos(0.0, 0.0, 0.0), Vel(1.0, 1.0, 1.0), Tag));
+    let c = scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    for (ents, (pos, vel)) in scene.query_chunks::<(&mut Pos, &Vel)>()
+    {
+        assert_eq!(ents.len(), pos.len());
+        assert_eq!(ents.len(), vel.len());
+
+        for (pos, vel) in pos.iter_mut().zip(vel)
+        {
+            pos.0 += vel.0;
+            pos.1 += vel.1;
+            pos.2 += vel.2;
+        }
+    }
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(1.0, 2.0, 3.0)));
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(1.0, 1.0, 1.0)));
+    assert_eq!(scene.get::<Pos>(c), Some(&Pos(0.0, 0.0, 0.0)));
+
+    // optional components and filters
+    let mut seen = Vec::new();
+    scene.for_each_chunk::<(&Pos, Option<&Vel>, Without<Tag>)>(|ents, (pos, vel, ())|
+    {
+        assert_eq!(ents.len(), pos.len());
+        seen.extend(ents.iter().map(|ent| (*ent, vel.is_some())));
+    });
+    seen.sort();
+    assert_eq!(seen, [(a, true), (c, false)]);
+}
+
+#[test]
+fn query_matches()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+    scene.spawn((Pos(0.0, 0.0, 0.0), Tag));
+    scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+
+    let pos = scene.archetype::<Pos>().unwrap();
+    let tag = scene.archetype::<(Pos, Tag)>().unwrap();
+    let body = scene.archetype::<Body>().unwrap();
+
+    assert!(<&Pos>::matches(pos));
+    assert!(<(&Pos, &mut Tag)>::matches(tag));
+    assert!(!<(&Pos, &mut Tag)>::matches(body));
+    assert!(<(Entity, &mut Pos, Option<&Vel>)>::matches(pos));
+    assert!(<(Entity, &mut Pos, Option<&Vel>)>::matches(body));
+    assert!(!<(Entity, &Vel, Option<&Tag>)>::matches(tag));
+    assert!(<()>::matches(tag));
+}
+
+#[test]
+fn query_iter()
+{
+    let mut scene = Scene::default();
+
+    for i in 0..1000
+    {
+        let i = i as f32;
+
+        match i as u32 % 3
+        {
+            0 => scene.spawn(Pos(i, 0.0, 0.0)),
+            1 => scene.spawn((Pos(i, 0.0, 0.0), Tag)),
+            _ => scene.spawn(Body { pos: Pos(i, 0.0, 0.0), vel: Vel(1.0, 2.0, 3.0) }),
+        };
+    }
+
+    // integrate
+    let mut n = 0;
+    for (pos, vel) in scene.query::<(&mut Pos, Option<&Vel>)>()
+    {
+        if let Some(vel) = vel
+        {
+            pos.1 += vel.1;
+            n += 1;
+        }
+    }
+    assert_eq!(n, 333);
+
+    // read back
+    let mut sum = 0.0;
+    let mut ents = Vec::new();
+    for (ent, pos, _) in scene.query::<(Entity, &Pos, &Vel)>()
+    {
+        assert_eq!(pos.1, 2.0);
+        sum += pos.0;
+
+        ents.push(ent);
+    }
+    assert_eq!(sum, (0..1000).filter(|i| i % 3 == 2).sum::<i32>() as f32);
+    assert!(ents.iter().all(|ent| scene.get::<Vel>(*ent).is_some()));
+    assert_eq!(scene.query::<(&Pos, &Tag)>().count(), 333);
+    assert_eq!(scene.query::<Entity>().count(), 1000);
+}
+
+#[test]
+#[should_panic(expected = "query accesses `Pos` mutably while it's already borrowed!")]
+fn query_aliasing()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+    scene.query::<(&mut Pos, Option<&Pos>)>();
+}
+
+tags!
+{
+    Player,
+    Dead,
+    /// currently selected in the editor
+    pub Selected,
+}
+
+#[test]
+fn tag_filters()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..1000)
+        .map(|i| scene.spawn(Pos(i as f32, 0.0, 0.0)))
+        .collect::<Vec<_>>();
+
+    for (i, ent) in ents.iter().enumerate()
+    {
+        if i % 2 == 0
+        {
+            assert!(scene.tag::<Player>(*ent));
+        }
+        if i % 3 == 0
+        {
+            assert!(scene.tag::<Dead>(*ent));
+        }
+    }
+    assert!(scene.has::<Player>(ents[0]) && scene.has::<Dead>(ents[0]));
+    assert!(!scene.has::<Player>(ents[1]) && !scene.has::<Selected>(ents[1]));
+
+    // filter by two tags
+    let alive = scene
+        .query::<(&Pos, With<Player>, Without<Dead>)>()
+        .map(|(pos, _, _)| pos.0 as usize)
+        .collect::<Vec<_>>();
+
+    assert_eq!(alive.len(), (0..1000).filter(|i| i % 2 == 0 && i % 3 != 0).count());
+    assert!(alive.iter().all(|i| i % 2 == 0 && i % 3 != 0));
+
+    // tags take no space
+    assert_eq!(std::mem::size_of::<Player>(), 0);
+    assert_eq!
+    (
+        scene.archetype::<(Pos, Player, Dead)>().unwrap().meta().get::<Player>().0.size(),
+        0
+    );
+    let mut other = Scene::default();
+    other.spawn(Pos(0.0, 0.0, 0.0));
+    other.spawn((Pos(0.0, 0.0, 0.0), Player, Dead));
+    assert_eq!
+    (
+        other.archetype::<Pos>().unwrap().capacity(),
+        other.archetype::<(Pos, Player, Dead)>().unwrap().capacity()
+    );
+}
+
+/// number of chunks fetched by `Counted`
+static FETCHED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// `&Pos`, counting the chunks it's fetched from
+struct Counted;
+
+unsafe impl Query for Counted
+{
+    type Item<'a> = &'a Pos;
+    type Fetch = *const Pos;
+
+    fn matches(arch: &Archetype) -> bool
+    {
+        <&Pos>::matches(arch)
+    }
+
+    fn access(out: &mut Vec<(CmpMeta, bool)>)
+    {
+        <&Pos>::access(out)
+    }
+
+    unsafe fn fetch(arch: &Archetype, chunk: &ArchetypeChunk) -> Self::Fetch
+    {
+        FETCHED.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        <&Pos>::fetch(arch, chunk)
+    }
+
+    unsafe fn get<'a>(fetch: Self::Fetch, index: usize) -> Self::Item<'a>
+    {
+        &*fetch.add(index)
+    }
+}
+
+#[test]
+fn query_skips_empty()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..10_000)
+        .map(|i| scene.spawn(Pos(i as f32, 0.0, 0.0)))
+        .collect::<Vec<_>>();
+    let tagged = scene.spawn((Pos(0.0, 0.0, 0.0), Tag));
+    scene.despawn(tagged);
+
+    // despawn all but the last few
+    for ent in &ents[..9_990]
+    {
+        scene.despawn(*ent);
+    }
+    let arch = scene.archetype::<Pos>().unwrap();
+    assert!(arch.chunks().len() > 10);
+    assert_eq!(arch.len(), 10);
+    assert!(scene.archetype::<(Pos, Tag)>().unwrap().is_empty());
+
+    // only the chunks with live entities are fetched
+    assert_eq!(scene.query::<Counted>().count(), 10);
+    assert_eq!(FETCHED.load(std::sync::atomic::Ordering::SeqCst), 1);
+    scene.validate();
+}
+
+#[test]
+fn change_filters()
+{
+    let mut scene = Scene::default();
+
+    // several chunks' worth
+    scene.spawn_batch((0..10_000).map(|i| Pos(i as f32, 0.0, 0.0)));
+
+    let chunks = scene.archetype::<Pos>().unwrap().chunks().len();
+    let max = scene.archetype::<Pos>().unwrap().chunks()[0].len();
+    assert!(chunks >= 4);
+
+    // everything changed since the beginning
+    assert_eq!(scene.query::<Changed<Pos>>().count(), 10_000);
+    assert_eq!(scene.query::<Added<Pos>>().count(), 10_000);
+
+    let since = scene.tick();
+    assert_eq!(scene.change_tick(), since + 1);
+    assert_eq!(scene.query_since::<Changed<Pos>>(since).count(), 0);
+
+    // mutate every other chunk
+    let mut changed = Vec::new();
+    for (i, chunk) in scene.archetype_mut::<Pos>().unwrap().chunks_mut().iter_mut().enumerate()
+    {
+        if i % 2 == 0
+        {
+            chunk.components_mut::<Pos>()[0].1 = 1.0;
+            changed.extend_from_slice(chunk.entities());
+        }
+    }
+    let mut yielded = scene
+        .query_since::<(Entity, Changed<Pos>)>(since)
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    changed.sort();
+    yielded.sort();
+    assert_eq!(yielded, changed);
+    assert!(yielded.len() >= 2 * max && yielded.len() < 10_000);
+
+    // nothing was added, and other filters still apply
+    assert_eq!(scene.query_since::<Added<Pos>>(since).count(), 0);
+    assert_eq!(scene.query_since::<(Changed<Pos>, With<Tag>)>(since).count(), 0);
+
+    // mutable queries count as changes, for the chunks they visit
+    let since = scene.tick();
+    for pos in scene.query::<&mut Pos>().take(1)
+    {
+        pos.1 = 2.0;
+    }
+    assert_eq!(scene.query_since::<Changed<Pos>>(since).count(), max);
+
+    // identical values aren't changes
+    let since = scene.tick();
+    let ent = scene.query::<Entity>().next().unwrap();
+    assert!(!scene.set_if_changed(ent, Pos(0.0, 2.0, 0.0)));
+    assert_eq!(scene.query_since::<Changed<Pos>>(since).count(), 0);
+    assert!(scene.set_if_changed(ent, Pos(0.0, 3.0, 0.0)));
+    assert_eq!(scene.query_since::<Changed<Pos>>(since).count(), max);
+
+    // new entities only show up in the chunks they were added to
+    let since = scene.tick();
+    let new = scene.spawn(Pos(0.0, 0.0, 0.0));
+    let added = scene
+        .query_since::<(Entity, Added<Pos>)>(since)
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    assert!(added.contains(&new));
+    assert!(added.len() < 10_001);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "scene was structurally changed while being queried!")]
+fn reentrant_mutation()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+    scene.spawn(Pos(1.0, 1.0, 1.0));
+
+    // going around the borrow checker, as `unsafe` code or interior mutability could
+    let ptr = &mut scene as *mut Scene;
+    let mut iter = unsafe { (*ptr).query::<&Pos>() };
+
+    iter.next();
+    unsafe { (*ptr).spawn(Pos(2.0, 2.0, 2.0)); }
+    iter.next();
+}
+
+/// view the scene behind `ptr` without borrowing it, as parallel systems do
+///
+/// # Safety
+/// see `SystemParam::fetch`
+unsafe fn view<'a, Q: Query>(ptr: *mut Scene) -> View<'a, Q>
+{
+    <View<Q> as SystemParam>::fetch(ptr, Box::leak(Box::new(())))
+}
+
+#[test]
+#[cfg(not(feature = "no-runtime-checks"))]
+#[should_panic(expected = "component `Pos` is already borrowed mutably!")]
+fn column_borrow_conflict()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+
+    // going around the borrow checker, as two parallel systems reporting the
+    // wrong `Access` would: two live queries over the same column
+    let ptr = &mut scene as *mut Scene;
+    let mut writer = unsafe { view::<&mut Pos>(ptr) }.into_iter();
+    let mut reader = unsafe { view::<(&Pos, &Vel)>(ptr) }.into_iter();
+
+    writer.next();
+    reader.next();
+}
+
+#[test]
+fn column_borrow_shared()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    // any number of readers, and writers of other columns
+    let ptr = &mut scene as *mut Scene;
+    let mut a = unsafe { view::<&Pos>(ptr) }.into_iter();
+    let mut b = unsafe { view::<(&Pos, &mut Vel)>(ptr) }.into_iter();
+
+    assert!(a.next().is_some());
+    assert!(b.next().is_some());
+    assert!(a.next().is_some());
+    drop((a, b));
+
+    // borrows are released once the queries are dropped
+    assert_eq!(scene.query::<&mut Pos>().count(), 2);
+    assert_eq!(scene.query_chunks::<&mut Pos>().count(), 2);
+    assert_eq!(scene.view::<Pos>().count(), 2);
+}
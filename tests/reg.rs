@@ -0,0 +1,70 @@
+//! tests the global component registry
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+#[component(name = "Velocity")]
+struct Vel(f32, f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Mass(f32);
+
+#[test]
+fn register_resolve()
+{
+    let pos = Registry::register::<Pos>();
+    let vel = Registry::register::<Vel>();
+    let mass = Registry::register::<Mass>();
+
+    // registering twice is a no-op
+    assert!(std::ptr::eq(pos, Registry::register::<Pos>()));
+
+    // by name
+    assert_eq!(Registry::by_name("Pos"), Some(pos));
+    assert_eq!(Registry::by_name("Velocity"), Some(vel));
+    assert_eq!(Registry::by_name("Mass"), Some(mass));
+    assert_eq!(Registry::by_name("Vel"), None);
+
+    // by ID
+    assert_eq!(Registry::by_id(Pos::ID).map(CmpMeta::name), Some("Pos"));
+    assert_eq!(Registry::by_id(Vel::ID).map(CmpMeta::name), Some("Velocity"));
+    assert_eq!(Registry::by_id(Mass::ID).map(CmpMeta::name), Some("Mass"));
+
+    // runtime-defined
+    let script = Registry::register_meta(CmpMeta::new_dynamic("ScriptPos", 12, 4, None));
+    assert_eq!(Registry::by_name("ScriptPos").map(CmpMeta::id), Some(script.id()));
+}
+
+/// shares the ID of `Pos`, as if their paths hashed the same
+struct Clash;
+
+impl Component for Clash
+{
+    const ID: CmpId = Pos::ID;
+    const NAME: &'static str = "Clash";
+}
+
+#[test]
+#[should_panic(expected = "component types `Pos` and `Clash` share the ID")]
+fn register_clash()
+{
+    Registry::register::<Pos>();
+    Registry::register::<Clash>();
+}
+
+#[test]
+fn spawn_by_name()
+{
+    Registry::register::<Mass>();
+
+    let mut scene = Scene::default();
+
+    // as if deserialized from a save file
+    let id = scene.component_by_name("Mass").unwrap().id();
+    let ent = unsafe { scene.spawn_dyn(&[(id, &2.5f32.to_ne_bytes())]) };
+
+    assert_eq!(scene.get::<Mass>(ent), Some(&Mass(2.5)));
+}
@@ -0,0 +1,85 @@
+//! tests the resources
+
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use ezgame::*;
+
+#[derive(Resource, Debug, PartialEq)]
+struct Time(f32);
+
+#[derive(Debug, PartialEq)]
+struct Input
+{
+    keys: Vec<char>,
+}
+
+#[test]
+fn insert_get()
+{
+    let mut res = Resources::new();
+
+    assert_eq!(res.get::<Time>(), None);
+    assert_eq!(res.insert(Time(0.0)), None);
+    assert_eq!(res.insert(Input { keys: vec!['w'] }), None);
+
+    // overwrite
+    assert_eq!(res.insert(Time(1.0)), Some(Time(0.0)));
+    assert_eq!(res.get::<Time>(), Some(&Time(1.0)));
+
+    // mutate
+    res.get_mut::<Input>().unwrap().keys.push('a');
+    assert_eq!(res.get::<Input>(), Some(&Input { keys: vec!['w', 'a'] }));
+}
+
+/// number of `Assets` dropped so far
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Resource)]
+struct Assets
+{
+    paths: Vec<&'static str>,
+}
+
+impl Drop for Assets
+{
+    fn drop(&mut self)
+    {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32);
+
+#[test]
+fn scene_resources()
+{
+    let mut scene = Scene::default();
+
+    assert_eq!(Time::NAME, "Time");
+    assert!(scene.insert_resource(Time(0.5)).is_none());
+    assert!(scene.insert_resource(Assets { paths: vec!["a.png"] }).is_none());
+    assert_eq!(scene.resource::<Time>(), Some(&Time(0.5)));
+
+    scene.resource_mut::<Assets>().unwrap().paths.push("b.png");
+    assert_eq!(scene.resource::<Assets>().unwrap().paths, ["a.png", "b.png"]);
+
+    // queries and resources at once
+    scene.spawn(Pos(1.0));
+    scene.spawn(Pos(2.0));
+
+    let (query, res) = scene.query_with_resources::<&mut Pos>();
+    for pos in query
+    {
+        pos.0 += res.get::<Time>().unwrap().0;
+        res.get_mut::<Time>().unwrap().0 += 1.0;
+    }
+    assert_eq!(scene.remove_resource::<Time>(), Some(Time(2.5)));
+    assert_eq!(scene.resource::<Time>(), None);
+    assert_eq!(scene.query::<&Pos>().map(|pos| pos.0).sum::<f32>(), 5.0);
+
+    // dropped with the scene
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+    drop(scene);
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+}
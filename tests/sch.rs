@@ -0,0 +1,422 @@
+//! tests the schedule
+
+use ezgame::*;
+
+const INPUT: StageLabel = StageLabel::new("input");
+const UPDATE: StageLabel = StageLabel::new("update");
+const PHYSICS: StageLabel = StageLabel::new("physics");
+const RENDER: StageLabel = StageLabel::new("render");
+
+#[derive(Resource, Default)]
+struct Log(Vec<&'static str>);
+
+fn input(mut log: ResMut<Log>) { log.0.push("input") }
+fn ai(mut log: ResMut<Log>) { log.0.push("ai") }
+fn movement(mut log: ResMut<Log>) { log.0.push("movement") }
+fn collide(mut log: ResMut<Log>) { log.0.push("collide") }
+fn extract(mut log: ResMut<Log>) { log.0.push("extract") }
+
+#[test]
+fn stage_order()
+{
+    let mut schedule = Schedule::new();
+
+    // stages, and systems, are registered out of order
+    schedule
+        .add_stage(UPDATE)
+        .add_stage(RENDER)
+        .add_stage_before(INPUT, UPDATE)
+        .add_stage_after(PHYSICS, UPDATE)
+        .add_system_to_stage(RENDER, extract)
+        .add_system_to_stage(PHYSICS, collide)
+        .add_system_to_stage(UPDATE, ai)
+        .add_system_to_stage(UPDATE, movement)
+        .add_system_to_stage(INPUT, input);
+
+    assert_eq!(schedule.stages().collect::<Vec<_>>(), [INPUT, UPDATE, PHYSICS, RENDER]);
+    assert_eq!(schedule.stage(UPDATE).unwrap().len(), 2);
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Log::default());
+    schedule.run(&mut scene);
+    schedule.run(&mut scene);
+
+    let frame = ["input", "ai", "movement", "collide", "extract"];
+    assert_eq!(scene.resource::<Log>().unwrap().0, [frame, frame].concat());
+}
+
+#[test]
+#[should_panic(expected = "stage `render` doesn't exist!")]
+fn missing_stage()
+{
+    Schedule::new()
+        .add_stage(UPDATE)
+        .add_system_to_stage(RENDER, extract);
+}
+
+#[test]
+#[should_panic(expected = "stage `update` already exists!")]
+fn duplicate_stage()
+{
+    Schedule::new()
+        .add_stage(UPDATE)
+        .add_stage(INPUT)
+        .add_stage_after(UPDATE, INPUT);
+}
+
+#[derive(Resource, Default)]
+struct Counter<const N: usize>(u32);
+
+fn count<const N: usize>(mut n: ResMut<Counter<N>>)
+{
+    n.0 += 1;
+}
+
+#[derive(Resource, Default)]
+struct Snapshots(Vec<[u32; 4]>);
+
+#[test]
+fn exclusive_barrier()
+{
+    let mut schedule = Schedule::new();
+
+    // the counters run in parallel, on either side of the exclusive system
+    schedule
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, count::<0>)
+        .add_system_to_stage(UPDATE, count::<1>)
+        .add_exclusive_system_to_stage(UPDATE, |scene: &mut Scene|
+        {
+            let counters =
+            [
+                scene.resource::<Counter<0>>().unwrap().0,
+                scene.resource::<Counter<1>>().unwrap().0,
+                scene.resource::<Counter<2>>().unwrap().0,
+                scene.resource::<Counter<3>>().unwrap().0,
+            ];
+            scene.resource_mut::<Snapshots>().unwrap().0.push(counters);
+        })
+        .add_system_to_stage(UPDATE, count::<2>)
+        .add_system_to_stage(UPDATE, count::<3>);
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Snapshots::default());
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+    scene.insert_resource(Counter::<2>::default());
+    scene.insert_resource(Counter::<3>::default());
+    for _ in 0..50
+    {
+        schedule.run(&mut scene);
+    }
+
+    let expected = (0..50)
+        .map(|i| [i + 1, i + 1, i, i])
+        .collect::<Vec<_>>();
+    assert_eq!(scene.resource::<Snapshots>().unwrap().0, expected);
+    assert_eq!(scene.resource::<Counter<3>>().unwrap().0, 50);
+}
+
+#[test]
+fn fixed_timestep()
+{
+    use std::time::Duration;
+
+    let mut schedule = Schedule::new();
+
+    // exactly representable step, of 15.625ms
+    schedule
+        .add_stage(UPDATE)
+        .add_fixed_stage(PHYSICS, FixedTimestep::new(1.0 / 64.0).max_steps(4))
+        .add_system_to_stage(UPDATE, count::<0>)
+        .add_system_to_stage(PHYSICS, count::<1>);
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Time::new());
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+
+    // (frame duration, physics runs that frame, alpha after it)
+    let frames =
+    [
+        (10, 0, 0.64),
+        (10, 1, 0.28),
+        (50, 3, 0.48),
+        (0, 0, 0.48),
+        // way behind: capped, and the backlog is dropped
+        (1000, 4, 0.48),
+        (9, 1, 0.056),
+    ];
+    let mut total = 0;
+    for (ms, steps, alpha) in frames
+    {
+        scene.resource_mut::<Time>().unwrap().set_fixed_delta(Some(Duration::from_millis(ms)));
+        schedule.run(&mut scene);
+
+        total += steps;
+        let fixed = scene.resource::<FixedTime>().unwrap();
+        assert_eq!(fixed.steps(), steps);
+        assert_eq!(fixed.step(), Duration::from_micros(15_625));
+        assert!((fixed.alpha() - alpha).abs() < 1e-4, "{} != {}", fixed.alpha(), alpha);
+        assert_eq!(scene.resource::<Counter<1>>().unwrap().0, total);
+    }
+    assert_eq!(scene.resource::<Counter<0>>().unwrap().0, frames.len() as u32);
+    assert_eq!(scene.resource::<Time>().unwrap().elapsed(), Duration::from_millis(1079));
+}
+
+#[test]
+fn time_resource()
+{
+    use std::time::Duration;
+
+    let mut schedule = Schedule::new();
+    let mut scene = Scene::default();
+
+    // inserted by the first run, which lasts zero
+    schedule.run(&mut scene);
+
+    let time = scene.resource::<Time>().unwrap();
+    assert_eq!(time.frame(), 1);
+    assert_eq!(time.delta(), Duration::ZERO);
+
+    // mocked deltas
+    scene.resource_mut::<Time>().unwrap().set_fixed_delta(Some(Duration::from_millis(16)));
+    for frame in 2..=5
+    {
+        schedule.run(&mut scene);
+
+        let time = scene.resource::<Time>().unwrap();
+        assert_eq!(time.frame(), frame);
+        assert_eq!(time.delta(), Duration::from_millis(16));
+        assert_eq!(time.elapsed(), Duration::from_millis(16 * (frame - 1)));
+    }
+
+    // back to real time
+    scene.resource_mut::<Time>().unwrap().set_fixed_delta(None);
+    schedule.run(&mut scene);
+    std::thread::sleep(Duration::from_millis(5));
+    schedule.run(&mut scene);
+
+    let time = scene.resource::<Time>().unwrap();
+    assert_eq!(time.frame(), 7);
+    assert!(time.delta() >= Duration::from_millis(5));
+    assert!(time.elapsed() >= Duration::from_millis(64) + time.delta());
+}
+
+#[test]
+fn ordering_constraints()
+{
+    let mut schedule = Schedule::new();
+
+    // registered in reverse
+    schedule
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, extract.after("physics"))
+        .add_system_to_stage(UPDATE, collide.label("physics").after("movement"))
+        .add_system_to_stage(UPDATE, movement.label("movement"))
+        .add_system_to_stage(UPDATE, input.before("movement"))
+        // constraints on missing labels are ignored
+        .add_system_to_stage(UPDATE, ai.after("network"));
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Log::default());
+    schedule.run(&mut scene);
+
+    assert_eq!(scene.resource::<Log>().unwrap().0, ["input", "movement", "collide", "extract", "ai"]);
+
+    // same order when run sequentially
+    let mut systems = Systems::new();
+
+    systems
+        .add(extract.after("physics"))
+        .add(collide.label("physics").after("movement"))
+        .add(movement.label("movement"));
+    scene.insert_resource(Log::default());
+    systems.run(&mut scene);
+
+    assert_eq!(scene.resource::<Log>().unwrap().0, ["movement", "collide", "extract"]);
+}
+
+#[test]
+fn ordering_constraints_parallel()
+{
+    use std::sync::{ Arc, Barrier };
+
+    // both counters run at the same time, since their constraints don't involve
+    // each other: they'd deadlock otherwise
+    let barrier = Arc::new(Barrier::new(2));
+    let (a, b) = (Arc::clone(&barrier), barrier);
+
+    let mut schedule = Schedule::new();
+
+    schedule
+        .set_pool(ThreadPool::new(2))
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, (move |_: ResMut<Counter<0>>| { a.wait(); }).after("first"))
+        .add_system_to_stage(UPDATE, (move |_: ResMut<Counter<1>>| { b.wait(); }).after("first"))
+        .add_system_to_stage(UPDATE, count::<2>.label("first"));
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+    scene.insert_resource(Counter::<2>::default());
+    schedule.run(&mut scene);
+
+    assert_eq!(scene.resource::<Counter<2>>().unwrap().0, 1);
+}
+
+#[test]
+#[should_panic(expected = "systems have cyclic ordering constraints: `sch::movement` -> `sch::collide` -> `sch::movement`!")]
+fn cyclic_constraints()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Log::default());
+    Schedule::new()
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, input)
+        .add_system_to_stage(UPDATE, collide.label("physics").after("movement"))
+        .add_system_to_stage(UPDATE, movement.label("movement").after("physics"))
+        .run(&mut scene);
+}
+
+fn slow(mut log: ResMut<Log>)
+{
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    log.0.push("slow");
+}
+
+#[test]
+fn diagnostics()
+{
+    let mut scene = Scene::default();
+    let mut schedule = Schedule::new();
+
+    schedule
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, input)
+        .add_system_to_stage(UPDATE, slow)
+        .add_system_to_stage(UPDATE, extract);
+
+    scene.insert_resource(Log::default());
+    scene.insert_resource(Diagnostics::with_frames(2));
+    for _ in 0..3
+    {
+        schedule.run(&mut scene);
+    }
+
+    let diagnostics = scene.resource_mut::<Diagnostics>().unwrap();
+    let timings = diagnostics.get("sch::slow").unwrap();
+
+    // only the last frames are kept
+    assert_eq!(timings.samples().count(), 2);
+    assert!(timings.min() >= std::time::Duration::from_millis(20));
+    assert!(timings.min() <= timings.avg() && timings.avg() <= timings.max());
+
+    // slowest system first, after the header
+    let report = diagnostics.report();
+    let mut lines = report.lines();
+
+    assert!(lines.next().unwrap().starts_with("system"));
+    assert!(lines.next().unwrap().starts_with("sch::slow "));
+    assert_eq!(lines.count(), 2);
+
+    // nothing is recorded while disabled
+    diagnostics.set_enabled(false);
+    diagnostics.clear();
+    schedule.run(&mut scene);
+
+    assert_eq!(scene.resource::<Diagnostics>().unwrap().iter().count(), 0);
+}
+
+#[test]
+fn profiler()
+{
+    use std::sync::{ Arc, Mutex };
+
+    let mut scene = Scene::default();
+    let mut schedule = Schedule::new();
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let log = names.clone();
+
+    schedule
+        .add_stage(UPDATE)
+        .add_system_to_stage(UPDATE, input)
+        .add_system_to_stage(UPDATE, ai)
+        .set_profiler(move |name, _| log.lock().unwrap().push(name.to_owned()));
+
+    scene.insert_resource(Log::default());
+    schedule.run(&mut scene);
+    schedule.run(&mut scene);
+
+    assert_eq!(*names.lock().unwrap(), ["sch::input", "sch::ai", "sch::input", "sch::ai"]);
+
+    schedule.remove_profiler().run(&mut scene);
+
+    assert_eq!(names.lock().unwrap().len(), 4);
+}
+
+fn double<const N: usize>(mut n: ResMut<Counter<N>>)
+{
+    n.0 *= 2;
+}
+
+/// schedule where `count<0>` and `double<0>` are ambiguous, and so would be
+/// `count<1>` and `double<1>` if they weren't allowed
+fn ambiguous_schedule() -> Schedule
+{
+    let mut schedule = Schedule::new();
+
+    schedule
+        .add_stage(UPDATE)
+        // ordered, even if only indirectly between `input` and `collide`
+        .add_system_to_stage(UPDATE, input.label("input"))
+        .add_system_to_stage(UPDATE, movement.label("movement").after("input"))
+        .add_system_to_stage(UPDATE, collide.after("movement"))
+        // unordered
+        .add_system_to_stage(UPDATE, count::<0>)
+        .add_system_to_stage(UPDATE, double::<0>)
+        .add_system_to_stage(UPDATE, count::<1>.label("count"))
+        .add_system_to_stage(UPDATE, double::<1>)
+        .allow_ambiguity("count", "sch::double<1>");
+    schedule
+}
+
+#[test]
+fn ambiguities()
+{
+    let ambiguities = ambiguous_schedule().ambiguities();
+
+    assert_eq!(ambiguities.len(), 1);
+    assert_eq!(ambiguities[0].stage, UPDATE);
+    assert_eq!(ambiguities[0].first, "sch::count<0>");
+    assert_eq!(ambiguities[0].second, "sch::double<0>");
+    assert_eq!(ambiguities[0].conflicts.len(), 1);
+
+    // ordering them fixes it
+    let mut schedule = ambiguous_schedule();
+
+    schedule.add_system_to_stage(UPDATE, count::<2>.label("count2"));
+    schedule.add_system_to_stage(UPDATE, double::<2>.after("count2"));
+
+    assert_eq!(schedule.ambiguities().len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "systems have ambiguous execution order:\n  `sch::count<0>` and `sch::double<0>` in stage `update` conflict on")]
+fn deny_ambiguities()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Log::default());
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+    ambiguous_schedule()
+        .deny_ambiguities(true)
+        .run(&mut scene);
+}
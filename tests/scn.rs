@@ -0,0 +1,1115 @@
+//! tests the scene
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(f32, f32, f32);
+
+#[derive(Bundle)]
+struct Body
+{
+    pos: Pos,
+    vel: Vel,
+}
+
+#[test]
+fn archetype_mut()
+{
+    let mut scene = Scene::default();
+
+    for i in 0..100
+    {
+        let i = i as f32;
+
+        scene.spawn(Body { pos: Pos(i, i, i), vel: Vel(0.0, 0.0, 0.0) });
+    }
+    assert!(scene.archetype::<Pos>().is_none(), "archetype was never spawned");
+
+    // bulk edit
+    for chunk in scene.archetype_mut::<Body>().unwrap().chunks_mut()
+    {
+        for vel in chunk.components_mut::<Vel>()
+        {
+            vel.1 = 1.0;
+        }
+    }
+
+    // read back
+    let arch = scene.archetype::<Body>().unwrap();
+    let mut n = 0;
+
+    for chunk in arch.chunks()
+    {
+        for (pos, vel) in chunk.components::<Pos>().iter().zip(chunk.components::<Vel>())
+        {
+            assert_eq!(pos.0, pos.1);
+            assert_eq!(vel, &Vel(0.0, 1.0, 0.0));
+
+            n += 1;
+        }
+    }
+    assert_eq!(n, 100);
+}
+
+/// (spawned, despawned) `Tracked` components so far
+static TRACKED: std::sync::Mutex<(Vec<Entity>, Vec<Entity>)> = std::sync::Mutex::new((Vec::new(), Vec::new()));
+
+#[derive(Component)]
+#[component(hooks)]
+struct Tracked;
+
+impl Tracked
+{
+    /// take the (spawned, despawned) events so far
+    fn events() -> (Vec<Entity>, Vec<Entity>)
+    {
+        std::mem::take(&mut *TRACKED.lock().unwrap())
+    }
+}
+
+impl CmpHooks for Tracked
+{
+    fn on_spawn(&mut self, ent: Entity)
+    {
+        TRACKED.lock().unwrap().0.push(ent);
+    }
+
+    fn on_despawn(&mut self, ent: Entity)
+    {
+        TRACKED.lock().unwrap().1.push(ent);
+    }
+}
+
+#[test]
+fn lifecycle_hooks()
+{
+    let mut scene = Scene::default();
+
+    // spawn
+    let a = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+    let b = scene.spawn(Tracked);
+    assert_eq!(Tracked::events(), (vec![b], vec![]));
+
+    // add
+    assert!(scene.add(a, Tracked));
+    assert_eq!(Tracked::events(), (vec![a], vec![]));
+
+    // add, overwriting
+    assert!(scene.add(a, Tracked));
+    assert_eq!(Tracked::events(), (vec![a], vec![a]));
+
+    // add, untracked
+    assert!(scene.add(b, Pos(1.0, 2.0, 3.0)));
+    assert_eq!(Tracked::events(), (vec![], vec![]));
+
+    // remove
+    assert!(scene.remove::<Tracked>(a));
+    assert!(!scene.remove::<Tracked>(a));
+    assert_eq!(Tracked::events(), (vec![], vec![a]));
+
+    // despawn
+    assert!(scene.despawn(b));
+    assert!(!scene.despawn(b));
+    assert_eq!(Tracked::events(), (vec![], vec![b]));
+
+    // components survived the migrations
+    let pos = scene.archetype::<Body>().unwrap().chunks()[0].components::<Pos>();
+    assert_eq!(pos, &[Pos(0.0, 0.0, 0.0)]);
+}
+
+#[test]
+fn script_component()
+{
+    let mut scene = Scene::default();
+
+    // a 12-byte component defined by a "scripting language"
+    let script = CmpMeta::new_dynamic("ScriptPos", 12, 4, None);
+    let id = scene.register_component(script);
+
+    assert!(id.is_dynamic());
+    assert!(!Pos::ID.is_dynamic());
+    assert_eq!(scene.component_by_name("ScriptPos").map(CmpMeta::id), Some(id));
+
+    // spawn
+    let ents = (0..100u8)
+        // SAFETY: the component is plain data
+        .map(|i| unsafe { scene.spawn_dyn(&[(id, &[i; 12])]) })
+        .collect::<Vec<_>>();
+
+    // add to a rust-defined entity
+    let body = scene.spawn(Body { pos: Pos(1.0, 2.0, 3.0), vel: Vel(0.0, 0.0, 0.0) });
+    assert!(unsafe { scene.add_dyn(body, &[(id, &[42; 12])]) });
+
+    // read back
+    for (i, ent) in ents.iter().enumerate()
+    {
+        // SAFETY: the component is plain data, without padding
+        assert_eq!(scene.get_dyn(*ent, id).map(|b| unsafe { assume_init(b) }), Some(&[i as u8; 12][..]));
+        assert!(scene.get_dyn(*ent, Pos::ID).is_none());
+    }
+    assert_eq!(scene.get_dyn(body, id).map(|b| unsafe { assume_init(b) }), Some(&[42; 12][..]));
+}
+
+#[test]
+fn spawn_raw()
+{
+    let mut scene = Scene::default();
+
+    // nothing is registered, the meta-data comes along with the bytes
+    let script = CmpMeta::new_dynamic("ScriptPos", 12, 4, None);
+    let health = 42u32.to_ne_bytes();
+    let pos = [1.0f32, 2.0, 3.0]
+        .iter()
+        .flat_map(|x| x.to_ne_bytes())
+        .collect::<Vec<_>>();
+
+    // SAFETY: every component is plain data
+    let ent = unsafe { scene.spawn_raw(&[(script.clone(), &[7; 12]), (Health::META, &health), (Pos::META, &pos)]) };
+
+    assert_eq!(scene.get::<Health>(ent), Some(&Health(42)));
+    assert_eq!(scene.get::<Pos>(ent), Some(&Pos(1.0, 2.0, 3.0)));
+    assert_eq!(scene.get_dyn(ent, script.id()).map(|b| unsafe { assume_init(b) }), Some(&[7; 12][..]));
+
+    // order doesn't matter
+    let other = unsafe { scene.spawn_raw(&[(Pos::META, &pos), (Health::META, &health), (script, &[8; 12])]) };
+    assert_eq!(scene.location(ent).unwrap().archetype(), scene.location(other).unwrap().archetype());
+}
+
+/// read bytes returned by `Scene::get_dyn` or `Scene::dump_raw` as initialized
+///
+/// # Safety
+/// they must be those of components without padding
+unsafe fn assume_init(bytes: &[std::mem::MaybeUninit<u8>]) -> &[u8]
+{
+    &*(bytes as *const [std::mem::MaybeUninit<u8>] as *const [u8])
+}
+
+#[test]
+fn dump_raw()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Health(10), Pos(1.0, 2.0, 3.0)));
+    let b = scene.spawn(Health(20));
+    let c = scene.spawn((Health(30), Pos(4.0, 5.0, 6.0)));
+    scene.despawn(c);
+
+    let mut dump = scene.dump_raw().collect::<Vec<_>>();
+    dump.sort_by_key(|(ent, _)| *ent);
+
+    assert_eq!(dump.len(), 2);
+    assert_eq!(dump[0].0, a);
+    assert_eq!(dump[1].0, b);
+    assert_eq!(dump[1].1.len(), 1);
+    assert_eq!(dump[1].1[0].0, Health::ID);
+    // SAFETY: `Health` has no padding, so all of its bytes are initialized
+    assert_eq!(unsafe { assume_init(dump[1].1[0].1) }, &20u32.to_ne_bytes()[..]);
+
+    // round-trip into another scene
+    let mut other = Scene::default();
+
+    for (_, cmp) in &dump
+    {
+        let cmp = cmp
+            .iter()
+            .map(|(id, bytes)| (if *id == Health::ID { Health::META } else { Pos::META }, unsafe { assume_init(bytes) }))
+            .collect::<Vec<_>>();
+
+        // SAFETY: `Health` and `Pos` are plain data without padding
+        unsafe { other.spawn_raw(&cmp) };
+    }
+    let mut copied = other
+        .query::<(&Health, Option<&Pos>)>()
+        .map(|(health, pos)| (health.0, pos.map(|pos| pos.0)))
+        .collect::<Vec<_>>();
+    copied.sort_by_key(|(health, _)| *health);
+
+    assert_eq!(copied, [(10, Some(1.0)), (20, None)]);
+}
+
+#[test]
+fn add_dyn_wrong_size()
+{
+    let mut scene = Scene::default();
+    scene.register_component(Health::META);
+
+    let ent = scene.spawn(Pos(1.0, 2.0, 3.0));
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+    {
+        unsafe { scene.add_dyn(ent, &[(Health::ID, &[0; 3])]) }
+    }));
+
+    // the entity wasn't moved to the archetype with a `Health`
+    assert!(res.is_err());
+    assert_eq!(scene.get::<Pos>(ent), Some(&Pos(1.0, 2.0, 3.0)));
+    assert!(!scene.has::<Health>(ent));
+    assert!(scene.archetype::<(Health, Pos)>().is_none_or(|arch| arch.is_empty()));
+}
+
+#[test]
+#[should_panic(expected = "component `Health` has the wrong size!")]
+fn spawn_raw_wrong_size()
+{
+    let mut scene = Scene::default();
+
+    unsafe { scene.spawn_raw(&[(Health::META, &[0; 3])]) };
+}
+
+#[test]
+#[should_panic(expected = "component set contains `Pos` more than once!")]
+fn duplicate_components()
+{
+    Scene::default().spawn((Pos(1.0, 2.0, 3.0), Pos(4.0, 5.0, 6.0)));
+}
+
+#[test]
+#[should_panic(expected = "component set contains `Vel` more than once!")]
+fn duplicate_nested_components()
+{
+    let mut scene = Scene::default();
+    let ent = scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    scene.add(ent, (Body { pos: Pos(1.0, 2.0, 3.0), vel: Vel(0.0, 0.0, 0.0) }, (Vel(4.0, 5.0, 6.0),)));
+}
+
+#[test]
+fn try_spawn()
+{
+    let mut scene = Scene::default();
+
+    let err = scene.try_spawn((Body { pos: Pos(1.0, 2.0, 3.0), vel: Vel(0.0, 0.0, 0.0) }, Pos(4.0, 5.0, 6.0)));
+    assert_eq!(err, Err(EcsError::DuplicateComponent("Pos")));
+    assert_eq!(scene.archetypes().generation(), 0);
+
+    assert!(scene.try_spawn((Pos(1.0, 2.0, 3.0), Vel(4.0, 5.0, 6.0))).is_ok());
+}
+
+#[test]
+fn spawn_mut()
+{
+    let mut scene = Scene::default();
+
+    let (ent, mut handle) = scene.spawn_mut(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+
+    // initialize from the assigned entity ID
+    handle.get_mut::<Pos>().unwrap().0 = ent.id() as f32;
+    assert!(handle.get_mut::<Tracked>().is_none());
+
+    assert_eq!(scene.get::<Pos>(ent), Some(&Pos(ent.id() as f32, 0.0, 0.0)));
+    assert_eq!(scene.get::<Vel>(ent), Some(&Vel(0.0, 0.0, 0.0)));
+    assert_eq!(scene.get::<Tracked>(ent).map(|_| ()), None);
+
+    scene.get_mut::<Vel>(ent).unwrap().2 = 1.0;
+    assert_eq!(scene.get::<Vel>(ent), Some(&Vel(0.0, 0.0, 1.0)));
+
+    scene.despawn(ent);
+    assert_eq!(scene.get::<Pos>(ent), None);
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Name(String);
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Health(u32);
+
+#[test]
+fn clone_entity()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn((Name("yohan".to_string()), Health(100)));
+    let copy = scene.clone_entity(ent).unwrap();
+
+    assert_ne!(ent, copy);
+    assert_eq!(scene.get::<Name>(copy), Some(&Name("yohan".to_string())));
+
+    // mutate the copy independently
+    scene.get_mut::<Name>(copy).unwrap().0.push_str("dev");
+    scene.get_mut::<Health>(copy).unwrap().0 -= 10;
+
+    assert_eq!(scene.get::<Name>(ent), Some(&Name("yohan".to_string())));
+    assert_eq!(scene.get::<Health>(ent), Some(&Health(100)));
+    assert_eq!(scene.get::<Name>(copy), Some(&Name("yohandev".to_string())));
+    assert_eq!(scene.get::<Health>(copy), Some(&Health(90)));
+
+    // `Pos` isn't cloneable
+    let ent = scene.spawn((Name("pos".to_string()), Pos(0.0, 0.0, 0.0)));
+    assert_eq!(scene.clone_entity(ent), None);
+    assert_eq!(scene.archetype::<(Name, Pos)>().unwrap().chunks()[0].len(), 1);
+}
+
+#[derive(Component, Clone, Default, Debug, PartialEq)]
+#[component(clone, default)]
+struct Inventory(Vec<String>);
+
+#[test]
+fn add_default()
+{
+    let mut scene = Scene::default();
+
+    // typed path
+    let a = scene.spawn(Health(10));
+    assert!(scene.add_default::<Inventory>(a));
+    assert_eq!(scene.get::<Inventory>(a), Some(&Inventory(vec![])));
+    assert_eq!(scene.get::<Health>(a), Some(&Health(10)));
+
+    // dyn path, knowing only the meta-data
+    let b = scene.spawn(Health(20));
+    assert!(scene.add_default_dyn(b, &Inventory::META));
+    assert_eq!(scene.get::<Inventory>(b), Some(&Inventory(vec![])));
+    assert_eq!(scene.get::<Health>(b), Some(&Health(20)));
+
+    // keeps the existing component
+    scene.get_mut::<Inventory>(b).unwrap().0.push("sword".to_string());
+    assert!(scene.add_default_dyn(b, &Inventory::META));
+    assert!(scene.add_default::<Inventory>(b));
+    assert_eq!(scene.get::<Inventory>(b), Some(&Inventory(vec!["sword".to_string()])));
+    assert_eq!(scene.archetype::<(Health, Inventory)>().unwrap().chunks()[0].len(), 2);
+
+    // `Health` has no default function
+    assert!(!scene.add_default_dyn(b, &Health::META));
+    assert!(scene.despawn(a));
+    assert!(!scene.add_default_dyn(a, &Inventory::META));
+
+    // both are cloneable
+    assert!(scene.clone_entity(b).is_some());
+}
+
+#[test]
+fn instantiate()
+{
+    // prefabs
+    let mut template = Scene::default();
+
+    let orc = template.spawn((Name("orc".to_string()), Health(50)));
+    let pos = template.spawn((Name("pos".to_string()), Pos(0.0, 0.0, 0.0)));
+
+    // live scene
+    let mut scene = Scene::default();
+
+    let a = scene.instantiate(&template, orc).unwrap();
+    let b = scene.instantiate(&template, orc).unwrap();
+
+    assert_ne!(a, b);
+    scene.get_mut::<Health>(a).unwrap().0 -= 20;
+
+    assert_eq!(scene.get::<Name>(a), Some(&Name("orc".to_string())));
+    assert_eq!(scene.get::<Health>(a), Some(&Health(30)));
+    assert_eq!(scene.get::<Health>(b), Some(&Health(50)));
+    assert_eq!(template.get::<Health>(orc), Some(&Health(50)));
+    assert_eq!(scene.archetype::<(Name, Health)>().unwrap().chunks()[0].len(), 2);
+
+    // `Pos` isn't cloneable
+    assert_eq!(scene.instantiate(&template, pos), None);
+    assert!(scene.archetype::<(Name, Pos)>().is_none());
+
+    // not in the template
+    assert_eq!(scene.instantiate(&template, a), None);
+}
+
+#[test]
+#[should_panic(expected = "component `ScriptTag`, which isn't registered in this scene!")]
+fn instantiate_unregistered()
+{
+    unsafe fn copy4(src: *const u8, dst: *mut u8)
+    {
+        std::ptr::copy_nonoverlapping(src, dst, 4);
+    }
+
+    let mut template = Scene::default();
+
+    let tag = template.register_component(CmpMeta::new_dynamic("ScriptTag", 4, 4, None).with_clone(copy4));
+    let ent = unsafe { template.spawn_dyn(&[(tag, &[1, 2, 3, 4])]) };
+
+    Scene::default().instantiate(&template, ent);
+}
+
+#[test]
+fn archetype_by_id()
+{
+    let mut scene = Scene::default();
+
+    let loc = scene.spawn_mut((Pos(0.0, 0.0, 0.0), Health(1))).1.location();
+    let arch = scene.archetype_by_id(loc.archetype()).unwrap();
+
+    assert_eq!(arch.id(), loc.archetype());
+    assert_eq!(arch.types(), scene.archetype::<(Pos, Health)>().unwrap().types());
+    assert_eq!(scene.archetypes().get(arch.id()).map(Archetype::id), Some(arch.id()));
+
+    // out of range
+    assert!(scene.archetype_by_id(scene.archetypes().generation()).is_none());
+    assert!(scene.archetype_by_id(EntityLocation::NULL.archetype()).is_none());
+}
+
+#[test]
+fn for_each_mut()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..1000)
+        .map(|i| match i % 3
+        {
+            0 => scene.spawn(Health(i)),
+            1 => scene.spawn((Health(i), Pos(0.0, 0.0, 0.0))),
+            _ => scene.spawn(Pos(0.0, 0.0, 0.0)),
+        })
+        .collect::<Vec<_>>();
+
+    let mut n = 0;
+    scene.for_each_mut::<Health>(|ent, health, _|
+    {
+        health.0 = ent.id() as u32;
+        n += 1;
+    });
+    assert_eq!(n, 667);
+
+    for (i, ent) in ents.iter().enumerate()
+    {
+        let expected = if i % 3 == 2 { None } else { Some(Health(ent.id() as u32)) };
+
+        assert_eq!(scene.get::<Health>(*ent), expected.as_ref());
+    }
+}
+
+#[test]
+fn for_each_mut_commands()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..100)
+        .map(|i| scene.spawn(Health(i * 10)))
+        .collect::<Vec<_>>();
+
+    // subtract health, despawn if it's all gone
+    scene.for_each_mut::<Health>(|ent, health, cmds|
+    {
+        health.0 = health.0.saturating_sub(500);
+
+        if health.0 == 0
+        {
+            cmds.despawn(ent);
+        }
+        else if health.0 > 200
+        {
+            cmds.add(ent, Pos(0.0, 0.0, 0.0));
+        }
+    });
+
+    // applied as soon as iteration is done
+    for (i, ent) in ents.iter().enumerate()
+    {
+        assert_eq!(scene.location(*ent).is_some(), i > 50);
+        assert_eq!(scene.has::<Pos>(*ent), i > 70);
+    }
+    assert_eq!(scene.query::<&Health>().count(), 49);
+    assert_eq!(scene.query::<(&Health, &Pos)>().count(), 29);
+}
+
+#[test]
+fn removed_components()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+    let b = scene.spawn(Body { pos: Pos(0.0, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) });
+    let c = scene.spawn(Pos(0.0, 0.0, 0.0));
+    let d = scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    scene.despawn(a);
+    scene.remove::<Vel>(b);
+    scene.add(c, Pos(1.0, 1.0, 1.0));
+    scene.add(d, Vel(1.0, 1.0, 1.0));
+
+    // each removal shows up once, and adding a new component isn't one
+    assert_eq!(scene.removed::<Pos>().collect::<Vec<_>>(), [a, c]);
+    assert_eq!(scene.removed::<Vel>().collect::<Vec<_>>(), [a, b]);
+
+    // still there the next frame...
+    scene.clear_trackers();
+    scene.despawn(d);
+    assert_eq!(scene.removed::<Pos>().collect::<Vec<_>>(), [a, c, d]);
+    assert_eq!(scene.removed::<Vel>().collect::<Vec<_>>(), [a, b, d]);
+
+    // ...but not the one after
+    scene.clear_trackers();
+    assert_eq!(scene.removed::<Pos>().collect::<Vec<_>>(), [d]);
+    scene.clear_trackers();
+    assert_eq!(scene.removed::<Pos>().count(), 0);
+}
+
+#[test]
+fn get_components()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn(Body { pos: Pos(1.0, 2.0, 3.0), vel: Vel(4.0, 5.0, 6.0) });
+    let b = scene.spawn(Pos(7.0, 8.0, 9.0));
+
+    assert_eq!(scene.get_components::<(&Pos, &Vel)>(a), Some((&Pos(1.0, 2.0, 3.0), &Vel(4.0, 5.0, 6.0))));
+    assert_eq!(scene.get_components::<(Entity, &Pos, Option<&Vel>)>(b), Some((b, &Pos(7.0, 8.0, 9.0), None)));
+
+    // missing a component
+    assert_eq!(scene.get_components::<(&Pos, &Vel)>(b), None);
+
+    if let Some((pos, vel)) = scene.get_components_mut::<(&mut Pos, &Vel)>(a)
+    {
+        pos.0 += vel.0;
+    }
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(5.0, 2.0, 3.0)));
+
+    // despawned
+    scene.despawn(a);
+    assert_eq!(scene.get_components::<&Pos>(a), None);
+    assert!(scene.get_components_mut::<&mut Pos>(a).is_none());
+}
+
+#[test]
+#[should_panic(expected = "query accesses `Pos` mutably while it's already borrowed!")]
+fn get_components_aliased()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn(Pos(0.0, 0.0, 0.0));
+
+    scene.get_components_mut::<(&mut Pos, &Pos)>(ent);
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Frozen(u64);
+
+#[test]
+fn add_to_all()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..3000)
+        .map(|i| match i % 3
+        {
+            0 => scene.spawn(Pos(i as f32, 0.0, 0.0)),
+            1 => scene.spawn(Body { pos: Pos(i as f32, 0.0, 0.0), vel: Vel(0.0, 0.0, 0.0) }),
+            _ => scene.spawn(Vel(i as f32, 0.0, 0.0)),
+        })
+        .collect::<Vec<_>>();
+
+    // moved to new archetypes, which match the query too but aren't visited again
+    assert_eq!(scene.add_to_all::<&Pos, _>(|ent| Frozen(ent.id())), 2000);
+    for (i, ent) in ents.iter().enumerate()
+    {
+        let frozen = if i % 3 == 2 { None } else { Some(Frozen(ent.id())) };
+
+        assert_eq!(scene.get::<Frozen>(*ent), frozen.as_ref());
+        assert_eq!(scene.get::<Pos>(*ent).is_some(), i % 3 != 2);
+        assert_eq!(scene.get::<Pos>(*ent).map(|pos| pos.0).unwrap_or(i as f32), i as f32);
+    }
+    assert_eq!(scene.archetype::<Pos>().unwrap().len(), 0);
+    assert_eq!(scene.archetype::<(Pos, Frozen)>().unwrap().len(), 1000);
+
+    // overwritten in place
+    scene.clear_trackers();
+    scene.clear_trackers();
+    assert_eq!(scene.add_to_all::<(&Frozen, &Vel), _>(|_| Frozen(u64::MAX)), 1000);
+    assert_eq!(scene.query::<&Frozen>().filter(|frozen| frozen.0 == u64::MAX).count(), 1000);
+    assert_eq!(scene.removed::<Frozen>().count(), 1000);
+    assert_eq!(scene.archetype::<(Pos, Vel, Frozen)>().unwrap().len(), 1000);
+}
+
+#[test]
+fn add_to_all_existing_destination()
+{
+    let mut scene = Scene::default();
+
+    // the destination of `Pos` entities is the archetype of `(Pos, Frozen)`, which is
+    // visited after them
+    let a = scene.spawn(Pos(0.0, 0.0, 0.0));
+    let b = scene.spawn((Pos(1.0, 0.0, 0.0), Frozen(0)));
+
+    let mut calls = Vec::new();
+    assert_eq!(scene.add_to_all::<&Pos, _>(|ent| { calls.push(ent); Frozen(ent.id()) }), 2);
+
+    calls.sort();
+    assert_eq!(calls, [a, b]);
+    assert_eq!(scene.get::<Frozen>(a), Some(&Frozen(a.id())));
+    assert_eq!(scene.get::<Frozen>(b), Some(&Frozen(b.id())));
+    assert_eq!(scene.removed::<Frozen>().count(), 1);
+}
+
+#[test]
+fn try_get()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn(Pos(1.0, 2.0, 3.0));
+
+    assert_eq!(scene.try_get::<Pos>(ent), Ok(&Pos(1.0, 2.0, 3.0)));
+    assert_eq!(scene.try_get::<Vel>(ent), Err(EcsError::ComponentMissing(ent, "Vel")));
+
+    scene.despawn(ent);
+    assert_eq!(scene.try_get::<Pos>(ent), Err(EcsError::EntityNotFound(ent)));
+    assert_eq!(scene.get::<Pos>(ent), None);
+}
+
+#[test]
+fn rebuild_archetype()
+{
+    let mut scene = Scene::default();
+
+    // as if read from a save file
+    let types = [Health::ID];
+    let metas = [Health::META];
+
+    let arch = scene.rebuild_archetype(&types, &metas, 5000);
+    let capacity = arch.capacity();
+
+    assert!(capacity >= 5000);
+    assert!(arch.chunks().iter().all(ArchetypeChunk::is_empty));
+
+    // spawning doesn't allocate
+    scene.register_component(Health::META);
+
+    let ents = (0..5000u32)
+        .map(|i| unsafe { scene.spawn_dyn(&[(Health::ID, &i.to_ne_bytes())]) })
+        .collect::<Vec<_>>();
+
+    let arch = scene.archetype::<Health>().unwrap();
+    assert_eq!(arch.capacity(), capacity);
+    assert_eq!(scene.get::<Health>(ents[1234]), Some(&Health(1234)));
+
+    // reserving again only tops up
+    assert_eq!(scene.rebuild_archetype(&types, &metas, 0).capacity(), capacity);
+}
+
+#[test]
+fn set_if_changed()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn((Health(10), Pos(0.0, 0.0, 0.0)));
+
+    assert!(!scene.set_if_changed(ent, Health(10)));
+    assert!(scene.set_if_changed(ent, Health(20)));
+    assert_eq!(scene.get::<Health>(ent), Some(&Health(20)));
+
+    // missing component
+    assert!(!scene.set_if_changed(ent, Vel(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn add_remove_fuzz()
+{
+    use std::collections::HashMap;
+
+    // xorshift, so the test is deterministic without extra dependencies
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut rand = move |n: usize|
+    {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % n
+    };
+
+    let mut scene = Scene::default();
+    // expected `Health` of every live entity
+    let mut model = HashMap::new();
+    let mut ents = Vec::new();
+
+    for step in 0..5000u32
+    {
+        match rand(8)
+        {
+            // spawn into a shared chunk
+            0 | 1 =>
+            {
+                let ent = scene.spawn(Health(step));
+
+                model.insert(ent, step);
+                ents.push(ent);
+            }
+            // add to a random entity, which may be last in its chunk
+            2..=4 if !ents.is_empty() =>
+            {
+                let ent = ents[rand(ents.len())];
+
+                match rand(3)
+                {
+                    0 => assert!(scene.add(ent, Pos(0.0, 0.0, step as f32))),
+                    1 => assert!(scene.add(ent, (Vel(0.0, 0.0, 0.0), Name(step.to_string())))),
+                    _ =>
+                    {
+                        assert!(scene.add(ent, Health(step)));
+                        model.insert(ent, step);
+                    }
+                }
+            }
+            // remove from a random entity
+            5 | 6 if !ents.is_empty() =>
+            {
+                let ent = ents[rand(ents.len())];
+
+                match rand(2)
+                {
+                    0 => scene.remove::<Pos>(ent),
+                    _ => scene.remove::<(Vel, Name)>(ent),
+                };
+            }
+            // despawn a random entity
+            _ if !ents.is_empty() =>
+            {
+                let ent = ents.swap_remove(rand(ents.len()));
+
+                assert!(scene.despawn(ent));
+                model.remove(&ent);
+            }
+            _ => continue,
+        }
+        scene.validate();
+    }
+
+    for ent in &ents
+    {
+        assert_eq!(scene.get::<Health>(*ent), Some(&Health(model[ent])));
+    }
+}
+
+#[test]
+fn set_many()
+{
+    let mut scene = Scene::default();
+
+    let ents = (0..1000u32)
+        .map(|i| match i % 4
+        {
+            0 => scene.spawn(Health(0)),
+            1 => scene.spawn((Health(0), Name(i.to_string()))),
+            2 => scene.spawn(Name(i.to_string())),
+            _ => scene.spawn(Health(0)),
+        })
+        .collect::<Vec<_>>();
+    scene.despawn(ents[3]);
+
+    // as if deserialized
+    let n = scene.set_many(ents.iter().enumerate().map(|(i, ent)| (*ent, Health(i as u32))));
+    assert_eq!(n, 749);
+
+    for (i, ent) in ents.iter().enumerate()
+    {
+        let expected = match i
+        {
+            3 => None,
+            _ if i % 4 == 2 => None,
+            _ => Some(Health(i as u32)),
+        };
+        assert_eq!(scene.get::<Health>(*ent), expected.as_ref());
+    }
+
+    // the previous components were dropped
+    let n = scene.set_many(vec![(ents[1], Name("one".to_string()))]);
+    assert_eq!(n, 1);
+    assert_eq!(scene.get::<Name>(ents[1]), Some(&Name("one".to_string())));
+}
+
+#[test]
+fn stable_archetype_key()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Pos(0.0, 0.0, 0.0), Vel(0.0, 0.0, 0.0)));
+    let b = scene.spawn((Vel(0.0, 0.0, 0.0), Pos(0.0, 0.0, 0.0)));
+
+    assert_eq!(scene.location(a).unwrap().archetype(), scene.location(b).unwrap().archetype());
+    assert_eq!(scene.archetypes().generation(), 1);
+
+    // the key only depends on the components' paths and locations, not their
+    // declaration order
+    let mut key = vec!
+    [
+        CmpId::of_path(concat!(module_path!(), "::Vel@9:8")),
+        CmpId::of_path(concat!(module_path!(), "::Pos@6:8")),
+    ];
+    key.sort();
+    assert_eq!(scene.archetype::<(Pos, Vel)>().unwrap().types(), &key[..]);
+    assert_eq!(scene.archetypes().find(&key), Some(0));
+}
+
+#[derive(Component)]
+struct Parent(Entity);
+
+#[test]
+fn spawn_empty()
+{
+    let mut scene = Scene::default();
+
+    scene.spawn(Pos(0.0, 0.0, 0.0));
+    assert!(scene.archetype::<()>().is_none());
+
+    // ID first, components later
+    let child = scene.spawn_empty();
+    let parent = scene.spawn(Pos(1.0, 1.0, 1.0));
+
+    assert_eq!(scene.archetype::<()>().unwrap().chunks()[0].entities(), &[child]);
+    assert!(scene.add(child, (Parent(parent), Vel(0.0, 0.0, 0.0))));
+
+    assert_eq!(scene.get::<Parent>(child).map(|p| p.0), Some(parent));
+    assert_eq!(scene.get::<Vel>(child), Some(&Vel(0.0, 0.0, 0.0)));
+    assert!(scene.archetype::<()>().unwrap().chunks().iter().all(|chunk| chunk.is_empty()));
+    scene.validate();
+}
+
+#[test]
+fn structural_version()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Pos(1.0, 2.0, 3.0),));
+    let b = scene.spawn((Pos(4.0, 5.0, 6.0),));
+
+    let (ptr, version) = scene.component_mut_ptr(b, Pos::ID).unwrap();
+    assert_eq!(version, scene.structural_version());
+    assert!(scene.component_mut_ptr(b, Vel::ID).is_none());
+
+    // writes through the pointer land in the scene, as long as it's valid
+    unsafe { (*ptr.cast::<Pos>()).0 = 7.0 };
+    scene.get_mut::<Pos>(b).unwrap().1 = 8.0;
+    assert_eq!(scene.structural_version(), version);
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(7.0, 8.0, 6.0)));
+
+    // `b` fills `a`'s gap, so the cached pointer is stale
+    scene.despawn(a);
+    assert_ne!(scene.structural_version(), version);
+    assert_ne!(scene.component_mut_ptr(b, Pos::ID).unwrap().0, ptr);
+
+    let before = scene.structural_version();
+    scene.add(b, (Vel(0.0, 0.0, 0.0),));
+    assert!(scene.structural_version() > before);
+
+    let before = scene.structural_version();
+    scene.remove::<(Vel,)>(b);
+    assert!(scene.structural_version() > before);
+}
+
+#[test]
+fn prune_empty_archetypes()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Pos(0.0, 0.0, 0.0),));
+    let rare = (0..1000)
+        .map(|_| scene.spawn((Pos(1.0, 1.0, 1.0), Vel(0.0, 0.0, 0.0))))
+        .collect::<Vec<_>>();
+    for ent in rare
+    {
+        scene.despawn(ent);
+    }
+
+    // only the rare archetype is empty
+    assert!(scene.archetype::<(Pos, Vel)>().unwrap().capacity() >= 1000);
+    assert_eq!(scene.prune_empty_archetypes(), 1);
+    assert_eq!(scene.prune_empty_archetypes(), 0);
+    assert_eq!(scene.archetype::<(Pos, Vel)>().unwrap().capacity(), 0);
+    assert_eq!(scene.archetypes().generation(), 2);
+
+    // the archetype is reused as-is
+    let id = scene.archetype::<(Pos, Vel)>().unwrap().id();
+    scene.add(a, (Vel(2.0, 2.0, 2.0),));
+
+    assert_eq!(scene.location(a).unwrap().archetype(), id);
+    assert_eq!(scene.get::<Vel>(a), Some(&Vel(2.0, 2.0, 2.0)));
+    scene.validate();
+}
+
+#[test]
+fn with_capacity()
+{
+    let mut scene = Scene::with_capacity(10_000);
+
+    assert!(scene.archetypes().inner().is_empty());
+
+    let ents = scene.spawn_batch((0..10_000).map(|i| (Pos(i as f32, 0.0, 0.0),)));
+    scene.spawn((Pos(0.0, 0.0, 0.0), Vel(0.0, 0.0, 0.0)));
+
+    assert_eq!(scene.get::<Pos>(ents[1234]), Some(&Pos(1234.0, 0.0, 0.0)));
+    assert_eq!(scene.archetypes().generation(), 2);
+    scene.validate();
+}
+
+#[test]
+fn entity_at_location()
+{
+    let mut scene = Scene::default();
+    let ents = (0..10)
+        .map(|i| scene.spawn((Pos(0.0, 0.0, 0.0), Health(i))))
+        .collect::<Vec<_>>();
+
+    for ent in &ents
+    {
+        assert_eq!(scene.entity_at_location(scene.location(*ent).unwrap()), Some(*ent));
+    }
+    assert_eq!(scene.entity_at_location(EntityLocation::NULL), None);
+
+    // the last entity takes the place of the despawned one
+    let last = scene.location(ents[9]).unwrap();
+
+    scene.despawn(ents[0]);
+
+    assert_eq!(scene.entity_at_location(last), None);
+    assert_eq!(scene.location(ents[9]).and_then(|loc| scene.entity_at_location(loc)), Some(ents[9]));
+}
+
+#[test]
+fn scene_hooks()
+{
+    use std::sync::{ Arc, Mutex };
+
+    /// marker spawned by the hooks, through commands
+    #[derive(Component)]
+    struct Spawned;
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut scene = Scene::default();
+
+    let added = log.clone();
+    let removed = log.clone();
+    scene
+        .on_add::<Health>(move |ent, health, cmds|
+        {
+            added.lock().unwrap().push(("add", ent, health.0));
+            health.0 += 1;
+            cmds.spawn((Spawned,));
+        })
+        .on_remove::<Health>(move |ent, health, _| removed.lock().unwrap().push(("remove", ent, health.0)));
+
+    let take = || std::mem::take(&mut *log.lock().unwrap());
+
+    // spawn, and hooks see the component as it was inserted
+    let a = scene.spawn((Pos(0.0, 0.0, 0.0), Health(10)));
+    assert_eq!(take(), [("add", a, 10)]);
+    assert_eq!(scene.get::<Health>(a), Some(&Health(11)));
+
+    let batch = scene.spawn_batch((0..2).map(|i| (Health(i),)));
+    assert_eq!(take(), [("add", batch[0], 0), ("add", batch[1], 1)]);
+
+    // unrelated components don't trigger them
+    let b = scene.spawn((Pos(0.0, 0.0, 0.0),));
+    scene.add(b, Vel(0.0, 0.0, 0.0));
+    assert_eq!(take(), []);
+
+    // add, then add over the existing component
+    scene.add(b, Health(20));
+    assert_eq!(take(), [("add", b, 20)]);
+    scene.add(b, Health(30));
+    assert_eq!(take(), [("remove", b, 21), ("add", b, 30)]);
+
+    // remove and despawn, before the component is dropped
+    scene.remove::<Health>(b);
+    assert_eq!(take(), [("remove", b, 31)]);
+    scene.despawn(a);
+    assert_eq!(take(), [("remove", a, 11)]);
+    scene.despawn(b);
+    assert_eq!(take(), []);
+
+    // commands are applied with the scene's other commands
+    assert_eq!(scene.query::<&Spawned>().count(), 0);
+    scene.apply_commands();
+    assert_eq!(scene.query::<&Spawned>().count(), 5);
+}
+
+#[test]
+fn spawned_despawned()
+{
+    let mut scene = Scene::default();
+
+    // single and batch paths
+    let a = scene.spawn((Pos(0.0, 0.0, 0.0),));
+    let batch = scene.spawn_batch((0..3).map(|i| (Health(i),)));
+    let with = scene.spawn_with(2, |i| (Health(i as u32),));
+    let copy = scene.clone_entity(batch[0]).unwrap();
+
+    let mut spawned = vec![a];
+    spawned.extend(&batch);
+    spawned.extend(&with);
+    spawned.push(copy);
+
+    assert_eq!(scene.spawned().collect::<Vec<_>>(), spawned);
+    assert_eq!(scene.despawned().count(), 0);
+
+    // moving entities around isn't spawning them
+    scene.add(a, Health(0));
+    scene.despawn(batch[1]);
+    scene.despawn(a);
+    assert_eq!(scene.spawned().count(), spawned.len());
+    assert_eq!(scene.despawned().collect::<Vec<_>>(), [batch[1], a]);
+
+    // kept for the next frame too...
+    scene.clear_trackers();
+    let b = scene.spawn((Pos(0.0, 0.0, 0.0),));
+    assert_eq!(scene.spawned().count(), spawned.len() + 1);
+    assert_eq!(scene.despawned().count(), 2);
+
+    // ...but not the one after that
+    scene.clear_trackers();
+    assert_eq!(scene.spawned().collect::<Vec<_>>(), [b]);
+    assert_eq!(scene.despawned().count(), 0);
+}
+
+#[test]
+fn spawn_copy()
+{
+    #[derive(Component, Clone, Copy, Debug, PartialEq)]
+    struct Material([u8; 64]);
+
+    #[derive(Component, Clone, Copy, Debug, PartialEq)]
+    struct Tile(u32);
+
+    let mut scene = Scene::default();
+    let material = Material([7; 64]);
+
+    let ents = scene.spawn_copy(&(material, Tile(3)), 100);
+
+    assert_eq!(ents.len(), 100);
+    assert!(scene.spawn_copy(&(material,), 0).is_empty());
+
+    for ent in ents
+    {
+        assert_eq!(scene.get::<Material>(ent), Some(&material));
+        assert_eq!(scene.get::<Tile>(ent), Some(&Tile(3)));
+    }
+    assert_eq!(scene.archetype::<(Material, Tile)>().unwrap().len(), 100);
+}
+
+#[test]
+fn append()
+{
+    let mut scene = Scene::default();
+    let mut other = Scene::default();
+
+    let a = scene.spawn((Pos(0.0, 0.0, 0.0), Vel(1.0, 0.0, 0.0)));
+    let b = other.spawn((Pos(1.0, 0.0, 0.0), Vel(1.0, 0.0, 0.0)));
+    let c = other.spawn((Name("c".to_string()), Health(3)));
+    other.insert_resource(Health(0));
+
+    scene.append(&mut other);
+    scene.validate();
+    other.validate();
+
+    // entities keep their ID and components, but not the resources
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(0.0, 0.0, 0.0)));
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(1.0, 0.0, 0.0)));
+    assert_eq!(scene.get::<Name>(c), Some(&Name("c".to_string())));
+    assert_eq!(scene.location(a).unwrap().archetype(), scene.location(b).unwrap().archetype());
+    assert!(scene.spawned().any(|ent| ent == c));
+    assert!(scene.resource::<Health>().is_none());
+
+    // ...leaving the other scene empty
+    assert_eq!(other.location(b), None);
+    assert_eq!(other.query::<Entity>().count(), 0);
+    assert!(other.resource::<Health>().is_some());
+
+    // which can be reused
+    let d = other.spawn(Pos(2.0, 0.0, 0.0));
+    assert_eq!(other.get::<Pos>(d), Some(&Pos(2.0, 0.0, 0.0)));
+}
@@ -0,0 +1,65 @@
+//! tests the serialization of components
+#![cfg(feature = "serde")]
+
+use serde::{ Serialize, Deserialize };
+use ezgame::*;
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serde)]
+struct Inventory
+{
+    owner: String,
+    items: Vec<u32>,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serde)]
+struct Health(u32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Secret(u32);
+
+#[test]
+fn round_trip()
+{
+    let mut scene = Scene::default();
+    scene.register_component(Inventory::META);
+
+    let ent = scene.spawn((Inventory { owner: "yohan".to_string(), items: vec![1, 2, 3] }, Secret(42)));
+
+    // serialize
+    let json = scene.serialize_component_dyn(ent, Inventory::ID, serde_json::value::Serializer).unwrap();
+    assert_eq!(json, serde_json::json!({ "owner": "yohan", "items": [1, 2, 3] }));
+
+    // deserialize into another entity
+    let copy = scene.spawn(Health(10));
+    scene.deserialize_component_dyn(copy, Inventory::ID, json).unwrap();
+
+    assert_eq!(scene.get::<Inventory>(copy), scene.get::<Inventory>(ent));
+    assert_eq!(scene.get::<Health>(copy), Some(&Health(10)));
+
+    // bad input leaves the entity untouched
+    let err = scene.deserialize_component_dyn(copy, Inventory::ID, serde_json::json!({ "owner": 5 }));
+    assert!(err.is_err());
+    assert_eq!(scene.get::<Inventory>(copy).unwrap().owner, "yohan");
+    scene.validate();
+}
+
+#[test]
+fn unserializable()
+{
+    let mut scene = Scene::default();
+
+    let ent = scene.spawn((Health(3), Secret(42)));
+
+    // component level
+    let err = scene.serialize_component_dyn(ent, Secret::ID, serde_json::value::Serializer).unwrap_err();
+    assert_eq!(err.to_string(), EcsError::NotSerializable("Secret").to_string());
+
+    // entity level
+    let json = scene.serialize_entity(ent, serde_json::value::Serializer, Unserializable::Skip).unwrap();
+    assert_eq!(json, serde_json::json!({ "Health": 3 }));
+
+    let err = scene.serialize_entity(ent, serde_json::value::Serializer, Unserializable::Error);
+    assert!(err.is_err());
+}
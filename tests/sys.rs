@@ -0,0 +1,551 @@
+//! tests the systems
+
+use ezgame::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Health(u32);
+
+#[derive(Resource)]
+struct Time(f32);
+
+#[derive(Resource, Default)]
+struct Log(Vec<&'static str>);
+
+#[system]
+fn movement(pos: View<(&mut Pos, &Vel)>, time: Res<Time>, mut log: ResMut<Log>)
+{
+    for (pos, vel) in pos
+    {
+        pos.0 += vel.0 * time.0;
+        pos.1 += vel.1 * time.0;
+    }
+    log.0.push("movement");
+}
+
+#[system]
+fn regen(mut health: View<&mut Health>, mut log: ResMut<Log>)
+{
+    for health in health.iter()
+    {
+        health.0 += 1;
+    }
+    log.0.push("regen");
+}
+
+#[system]
+fn aliasing(_a: View<&mut Pos>, _b: View<&Pos>) { }
+
+#[system]
+fn aliasing_res(_a: Res<Time>, _b: ResMut<Time>) { }
+
+#[test]
+fn system_macro()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Time(0.5));
+    scene.insert_resource(Log::default());
+
+    let a = scene.spawn((Pos(0.0, 0.0), Vel(1.0, 2.0)));
+    let b = scene.spawn((Pos(0.0, 0.0), Vel(1.0, 2.0), Health(10)));
+
+    let mut systems: Vec<Box<dyn System>> = vec![Box::new(movement), Box::new(regen)];
+    for _ in 0..2
+    {
+        for sys in &mut systems
+        {
+            sys.run(&mut scene);
+        }
+    }
+
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(1.0, 2.0)));
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(1.0, 2.0)));
+    assert_eq!(scene.get::<Health>(b), Some(&Health(12)));
+    assert_eq!(scene.resource::<Log>().unwrap().0, ["movement", "regen", "movement", "regen"]);
+    assert!(systems[0].name().ends_with("::movement"));
+}
+
+#[test]
+fn system_access()
+{
+    let access = movement.access();
+
+    let cmps = access
+        .components()
+        .iter()
+        .map(|(meta, mutable)| (meta.name(), *mutable))
+        .collect::<Vec<_>>();
+    assert_eq!(cmps, [("Pos", true), ("Vel", false)]);
+
+    let res = access
+        .resources()
+        .iter()
+        .map(|(_, name, mutable)| (name.rsplit("::").next().unwrap(), *mutable))
+        .collect::<Vec<_>>();
+    assert_eq!(res, [("Time", false), ("Log", true)]);
+
+    // disjoint from `movement`, besides the log
+    assert_eq!(regen.access().components()[0].0.name(), "Health");
+}
+
+#[test]
+#[should_panic(expected = "system accesses `Pos` mutably while it's already borrowed!")]
+fn system_aliasing()
+{
+    Scene::default().run(aliasing);
+}
+
+#[test]
+#[should_panic(expected = "mutably while it's already borrowed!")]
+fn system_aliasing_resource()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Time(0.0));
+    scene.run(aliasing_res);
+}
+
+#[test]
+#[should_panic(expected = "doesn't exist!")]
+fn system_missing_resource()
+{
+    Scene::default().run(movement);
+}
+
+#[test]
+fn system_forms()
+{
+    let t = trybuild::TestCases::new();
+
+    t.pass("tests/ui/sys_ok.rs");
+    t.compile_fail("tests/ui/sys_err_*.rs");
+}
+
+#[test]
+fn closure_systems()
+{
+    let mut scene = Scene::default();
+
+    let a = scene.spawn((Pos(0.0, 0.0), Vel(1.0, 0.0)));
+    let b = scene.spawn((Pos(0.0, 0.0), Vel(3.0, 0.0)));
+
+    // integrate, then despawn whatever went too far
+    let mut systems = Systems::new();
+    systems
+        .add(|scene: &mut Scene|
+        {
+            for (pos, vel) in scene.query::<(&mut Pos, &Vel)>()
+            {
+                pos.0 += vel.0;
+            }
+        })
+        .add(|scene: &mut Scene|
+        {
+            let far = scene
+                .query::<(Entity, &Pos)>()
+                .filter(|(_, pos)| pos.0 > 4.0)
+                .map(|(ent, _)| ent)
+                .collect::<Vec<_>>();
+            for ent in far
+            {
+                scene.despawn(ent);
+            }
+        });
+    assert_eq!(systems.len(), 2);
+
+    systems.run(&mut scene);
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(1.0, 0.0)));
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(3.0, 0.0)));
+
+    systems.run(&mut scene);
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(2.0, 0.0)));
+    assert_eq!(scene.get::<Pos>(b), None);
+
+    // single system
+    let mut n = 0;
+    scene.run(&mut |_: &mut Scene| n += 1);
+    scene.run(movement_free);
+    assert_eq!(n, 1);
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(3.0, 0.0)));
+}
+
+#[system]
+fn movement_free(pos: View<(&mut Pos, &Vel)>)
+{
+    for (pos, vel) in pos
+    {
+        pos.0 += vel.0;
+    }
+}
+
+fn physics(pos: View<(&mut Pos, &Vel)>, time: Res<Time>)
+{
+    for (pos, vel) in pos
+    {
+        pos.0 += vel.0 * time.0;
+        pos.1 += vel.1 * time.0;
+    }
+}
+
+fn reaper(health: View<(Entity, &Health)>, mut cmd: Commands)
+{
+    for (ent, health) in health
+    {
+        if health.0 == 0
+        {
+            cmd.despawn(ent);
+            cmd.spawn((Pos(0.0, 0.0),));
+        }
+    }
+}
+
+#[test]
+fn function_systems()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Time(0.5));
+
+    let a = scene.spawn((Pos(0.0, 0.0), Vel(2.0, 4.0)));
+    let b = scene.spawn((Pos(1.0, 1.0), Vel(2.0, 0.0), Health(0)));
+
+    scene.run(physics);
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(1.0, 2.0)));
+    assert_eq!(scene.get::<Pos>(b), Some(&Pos(2.0, 1.0)));
+
+    // access is known ahead of time
+    let sys = physics.into_system();
+    assert_eq!(sys.access().components().len(), 2);
+    assert!(sys.name().ends_with("::physics"));
+
+    // commands are applied once the system is done
+    let mut systems = Systems::new();
+    systems.add(reaper).add(physics);
+    systems.run(&mut scene);
+
+    assert_eq!(scene.get::<Pos>(a), Some(&Pos(2.0, 4.0)));
+    assert!(!scene.has::<Pos>(b));
+    assert_eq!(scene.query::<&Pos>().count(), 2);
+}
+
+fn aliasing_fn(_a: View<&mut Pos>, _b: View<(&Vel, &Pos)>) { }
+
+#[test]
+#[should_panic(expected = "system accesses `Pos` mutably while it's already borrowed!")]
+fn function_system_aliasing()
+{
+    Scene::default().run(aliasing_fn);
+}
+
+#[derive(Resource, Default)]
+struct Counter<const N: usize>(u32);
+
+fn count<const N: usize>(mut n: ResMut<Counter<N>>)
+{
+    n.0 += 1;
+}
+
+fn push_first(mut log: ResMut<Log>) { log.0.push("first") }
+fn push_second(mut log: ResMut<Log>, _n: Res<Counter<0>>) { log.0.push("second") }
+
+fn integrate(pos: View<(&mut Pos, &Vel)>)
+{
+    for (pos, vel) in pos
+    {
+        pos.0 += vel.0;
+    }
+}
+
+fn heal(health: View<&mut Health>)
+{
+    for health in health
+    {
+        health.0 += 1;
+    }
+}
+
+#[test]
+fn parallel_systems()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Log::default());
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+    scene.insert_resource(Counter::<2>::default());
+    scene.insert_resource(Counter::<3>::default());
+
+    let ents = (0..1000)
+        .map(|i| scene.spawn((Pos(0.0, 0.0), Vel(1.0, 0.0), Health(i))))
+        .collect::<Vec<_>>();
+
+    // `push_second` conflicts with `push_first`(the log) and `count::<0>`(its counter)
+    let mut systems = Systems::new();
+    systems
+        .add(count::<0>)
+        .add(push_second)
+        .add(count::<1>)
+        .add(integrate)
+        .add(push_first)
+        .add(heal)
+        .add(count::<2>)
+        .add(count::<3>);
+    for _ in 0..100
+    {
+        systems.run_parallel(&mut scene);
+    }
+
+    assert_eq!(scene.resource::<Counter<0>>().unwrap().0, 100);
+    assert_eq!(scene.resource::<Counter<1>>().unwrap().0, 100);
+    assert_eq!(scene.resource::<Counter<2>>().unwrap().0, 100);
+    assert_eq!(scene.resource::<Counter<3>>().unwrap().0, 100);
+    assert_eq!(scene.resource::<Log>().unwrap().0, ["second", "first"].repeat(100));
+    for (i, ent) in ents.into_iter().enumerate()
+    {
+        assert_eq!(scene.get::<Pos>(ent), Some(&Pos(100.0, 0.0)));
+        assert_eq!(scene.get::<Health>(ent), Some(&Health(i as u32 + 100)));
+    }
+}
+
+/// number of `meet` systems that started running
+static MET: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// waits for the other `meet` system to start running, up to a second, which
+/// it only can if both run at the same time
+fn meet<const N: usize>(mut met: ResMut<Counter<N>>)
+{
+    use std::sync::atomic::Ordering;
+
+    MET.fetch_add(1, Ordering::SeqCst);
+
+    let start = std::time::Instant::now();
+    while MET.load(Ordering::SeqCst) < 2 && start.elapsed().as_secs() < 1
+    {
+        std::hint::spin_loop();
+    }
+    met.0 = MET.load(Ordering::SeqCst) as u32;
+}
+
+#[test]
+fn parallel_overlap()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+
+    Systems::new()
+        .set_pool(ThreadPool::new(2))
+        .add(meet::<0>)
+        .add(meet::<1>)
+        .run_parallel(&mut scene);
+
+    // both saw each other
+    assert_eq!(scene.resource::<Counter<0>>().unwrap().0, 2);
+    assert_eq!(scene.resource::<Counter<1>>().unwrap().0, 2);
+}
+
+/// threads that `on_thread` systems ran on
+static THREADS: std::sync::Mutex<Vec<std::thread::ThreadId>> = std::sync::Mutex::new(Vec::new());
+
+fn on_thread<const N: usize>(mut n: ResMut<Counter<N>>)
+{
+    n.0 += 1;
+
+    let mut threads = THREADS.lock().unwrap();
+    let id = std::thread::current().id();
+
+    if !threads.contains(&id)
+    {
+        threads.push(id);
+    }
+}
+
+#[test]
+fn parallel_pool()
+{
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+    scene.insert_resource(Counter::<2>::default());
+
+    let mut systems = Systems::new();
+    systems
+        .set_pool(ThreadPool::new(2))
+        .add(on_thread::<0>)
+        .add(on_thread::<1>)
+        .add(on_thread::<2>);
+    for _ in 0..50
+    {
+        systems.run_parallel(&mut scene);
+    }
+
+    // the same threads every frame, rather than new ones
+    assert_eq!(scene.resource::<Counter<2>>().unwrap().0, 50);
+    assert!(THREADS.lock().unwrap().len() <= 2);
+}
+
+#[test]
+#[should_panic(expected = "system failed!")]
+fn parallel_panic()
+{
+    fn fail(_: Res<Counter<0>>) { panic!("system failed!") }
+    fn pass(_: Res<Counter<1>>) { }
+
+    let mut scene = Scene::default();
+
+    scene.insert_resource(Counter::<0>::default());
+    scene.insert_resource(Counter::<1>::default());
+
+    Systems::new()
+        .set_pool(ThreadPool::new(2))
+        .add(pass)
+        .add(fail)
+        .run_parallel(&mut scene);
+}
+
+#[test]
+fn access_compatibility()
+{
+    // nothing, a read, or a write of the same component or resource
+    let cmps = [Access::default(), Access::of_query::<&Pos>(), Access::of_query::<&mut Pos>()];
+    let res = [Access::default(), Access::of::<Res<Time>>(), Access::of::<ResMut<Time>>()];
+
+    for set in [&cmps, &res]
+    {
+        for (i, a) in set.iter().enumerate()
+        {
+            for (j, b) in set.iter().enumerate()
+            {
+                // only reads are shared
+                let written = (i == 2 && j != 0) || (j == 2 && i != 0);
+
+                assert_eq!(a.is_compatible(b), !written, "{:?} ~ {:?}", a, b);
+            }
+        }
+    }
+
+    // distinct components, or resources, never conflict
+    let (pos, vel) = (Access::of_query::<&mut Pos>(), Access::of_query::<&mut Vel>());
+    assert!(pos.is_compatible(&vel));
+    assert!(pos.is_compatible(&res[2]));
+    assert!(res[2].is_compatible(&Access::of::<ResMut<Log>>()));
+
+    // and the whole scene conflicts with everything, even nothing
+    let exclusive = Access::exclusive();
+    for a in cmps.iter().chain(&res).chain([&exclusive])
+    {
+        assert!(!a.is_compatible(&exclusive));
+        assert!(!exclusive.is_compatible(a));
+    }
+}
+
+#[test]
+fn access_sets()
+{
+    let mut access = Access::of_query::<(&Vel, &mut Pos, Option<&Vel>, Entity)>();
+    access.add_resource::<Time>(false);
+    access.add_resource::<Log>(true);
+
+    assert_eq!(access.reads(), [Vel::ID]);
+    assert_eq!(access.writes(), [Pos::ID]);
+    assert_eq!(access.resource_reads(), [std::any::TypeId::of::<Time>()]);
+    assert_eq!(access.resource_writes(), [std::any::TypeId::of::<Log>()]);
+
+    // the same as `movement`'s, which also reads `Time` and writes `Log`
+    assert_eq!(movement.access().writes(), access.writes());
+    assert_eq!(Scene::default().query::<(&Vel, &mut Pos)>().access().writes(), [Pos::ID]);
+}
+
+/// runs every work item on the calling thread, in order
+struct Sequential;
+
+impl TaskPool for Sequential
+{
+    fn for_each(&self, n: usize, f: &(dyn Fn(usize) + Sync))
+    {
+        (0..n).for_each(f);
+    }
+}
+
+#[test]
+fn chunk_parallel()
+{
+    use std::sync::atomic::{ AtomicU64, Ordering };
+
+    let mut scene = Scene::default();
+
+    scene.spawn_batch((0..100_000).map(|i| (Pos(i as f32, 0.0), Vel(1.0, 0.0))));
+    scene.spawn_batch((0..1_000).map(|_| (Pos(0.0, 0.0),)));
+
+    let pool = ScopedPool::new(4);
+    let sum = AtomicU64::new(0);
+
+    scene.par_for_each::<(&mut Pos, &Vel), _>(&pool, |(pos, vel)|
+    {
+        pos.0 += vel.0;
+        sum.fetch_add(pos.0 as u64, Ordering::Relaxed);
+    });
+    assert_eq!(sum.load(Ordering::Relaxed), (1..=100_000).sum::<u64>());
+
+    // systems' views, on a user-supplied pool
+    scene.run(|mut view: View<&Pos>|
+    {
+        let sum = AtomicU64::new(0);
+
+        view.par_for_each(&Sequential, |pos| { sum.fetch_add(pos.0 as u64, Ordering::Relaxed); });
+        assert_eq!(sum.load(Ordering::Relaxed), (1..=100_000).sum::<u64>());
+    });
+}
+
+#[derive(Resource, Default)]
+struct Counts(Vec<u32>);
+
+/// counts its own runs
+fn counter(mut runs: Local<u32>, mut counts: ResMut<Counts>)
+{
+    *runs += 1;
+    counts.0.push(*runs);
+}
+
+#[system]
+fn macro_counter(mut runs: Local<u32>, mut counts: ResMut<Counts>)
+{
+    *runs += 10;
+    counts.0.push(*runs);
+}
+
+#[test]
+fn local_state()
+{
+    let mut scene = Scene::default();
+    let mut systems = Systems::new();
+
+    // two instances of the same function, and of the same `#[system]`
+    systems
+        .add(counter)
+        .add(counter)
+        .add(macro_counter)
+        .add(macro_counter);
+    scene.insert_resource(Counts::default());
+
+    systems.run(&mut scene);
+    systems.run(&mut scene);
+    systems.run_parallel(&mut scene);
+    assert_eq!(scene.resource::<Counts>().unwrap().0, [1, 1, 10, 10, 2, 2, 20, 20, 3, 3, 30, 30]);
+
+    // state isn't shared with other systems, or kept across conversions
+    scene.insert_resource(Counts::default());
+    scene.run(counter);
+    scene.run(counter);
+    scene.run(macro_counter);
+    scene.run(macro_counter);
+    assert_eq!(scene.resource::<Counts>().unwrap().0, [1, 1, 10, 10]);
+}
@@ -0,0 +1,23 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Pos(f32, f32, f32);
+
+#[derive(Component)]
+struct Vel(f32, f32, f32);
+
+#[derive(Bundle)]
+struct Body
+{
+    pos: Pos,
+    vel: Vel,
+}
+
+#[derive(Bundle)]
+struct Bad
+{
+    body: Body,
+    pos: Pos,
+}
+
+fn main() { }
@@ -0,0 +1,25 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Vel(f32, f32, f32);
+
+// `DynCmpSet`'s components are only known at runtime
+#[derive(Bundle)]
+struct Bad
+{
+    set: DynCmpSet,
+    vel: Vel,
+}
+
+fn main()
+{
+    let mut scene = Scene::default();
+    let ent = scene.spawn(Vel(0.0, 0.0, 0.0));
+
+    // nested in another set
+    scene.spawn((DynCmpSet::new(), Vel(0.0, 0.0, 0.0)));
+
+    // named by type
+    scene.remove::<DynCmpSet>(ent);
+    scene.archetype::<DynCmpSet>();
+}
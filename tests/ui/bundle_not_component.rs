@@ -0,0 +1,15 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Pos(f32, f32, f32);
+
+struct NotComponent;
+
+#[derive(Bundle)]
+struct Bad
+{
+    pos: Pos,
+    not: NotComponent,
+}
+
+fn main() { }
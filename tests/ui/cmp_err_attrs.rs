@@ -0,0 +1,23 @@
+use ezgame::*;
+
+#[derive(Component)]
+#[component(serialize)]
+struct Unknown;
+
+#[derive(Component, Clone)]
+#[component(clone, clone)]
+struct Duplicate;
+
+#[derive(Component)]
+#[component(name = 5)]
+struct NotStr;
+
+#[derive(Component)]
+#[component(name)]
+struct Malformed;
+
+#[derive(Component)]
+#[component = "hooks"]
+struct NotList;
+
+fn main() {}
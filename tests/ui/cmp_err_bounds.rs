@@ -0,0 +1,11 @@
+use ezgame::*;
+
+#[derive(Component)]
+#[component(clone)]
+struct NotClone(u32);
+
+#[derive(Component)]
+#[component(name = "Other", default)]
+struct NotDefault(u32);
+
+fn main() {}
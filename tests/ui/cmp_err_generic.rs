@@ -0,0 +1,6 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Bad<T>(T);
+
+fn main() { }
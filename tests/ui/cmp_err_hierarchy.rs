@@ -0,0 +1,18 @@
+use ezgame::*;
+
+fn main()
+{
+    let mut scene = Scene::default();
+    let (a, b) = (scene.spawn_empty(), scene.spawn_empty());
+
+    scene.set_parent(b, a).unwrap();
+
+    // the hierarchy is only changed through the scene, so its components can't
+    // be created or copied onto another entity
+    let parent: Parent = scene.get::<Parent>(b).unwrap().clone();
+    scene.add(a, parent);
+    scene.add(b, Children::default());
+
+    // nor borrowed mutably by queries, which could swap them between entities
+    scene.get_components_mut::<&mut Parent>(b);
+}
@@ -0,0 +1,23 @@
+use ezgame::*;
+
+struct Local<T>(T);
+
+// generic
+impl_component!(Local<u32>);
+
+// not a path
+impl_component!(&'static str);
+
+// not from the crate root
+struct Bar;
+
+impl_component!(Bar);
+
+mod inner
+{
+    pub struct Foo;
+
+    ezgame::impl_component!(self::Foo);
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ezgame::*;
+
+#[derive(Component)]
+enum Bad
+{
+    A(Vec<&'static str>),
+    B(Option<&'_ u32>),
+}
+
+fn main() { }
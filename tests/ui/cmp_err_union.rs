@@ -0,0 +1,10 @@
+use ezgame::*;
+
+#[derive(Component)]
+union Bad
+{
+    a: u32,
+    b: f32,
+}
+
+fn main() { }
@@ -0,0 +1,37 @@
+use ezgame::*;
+
+#[derive(Component, Clone)]
+#[component(clone)]
+struct Cloned(u32);
+
+#[derive(Component, Default)]
+#[component(default)]
+struct Defaulted(Vec<u8>);
+
+#[derive(Component, Clone, Default, PartialEq)]
+#[component(clone, default, eq, name = "Position")]
+struct Pos(f32, f32);
+
+#[derive(Component, Clone, Default)]
+#[component(name = "Velocity")]
+#[component(default)]
+struct Vel(f32, f32);
+
+fn main()
+{
+    assert!(Cloned::META.clone_fn().is_some());
+    assert!(Cloned::META.default_fn().is_none());
+    assert!(Defaulted::META.clone_fn().is_none());
+    assert!(Defaulted::META.default_fn().is_some());
+
+    assert_eq!(Pos::NAME, "Position");
+    assert_eq!(Pos::META.name(), "Position");
+    assert!(Pos::META.clone_fn().is_some());
+    assert!(Pos::META.default_fn().is_some());
+    assert!(Pos::META.eq_fn().is_some());
+    assert!(Vel::META.eq_fn().is_none());
+
+    assert_eq!(Vel::NAME, "Velocity");
+    assert!(Vel::META.clone_fn().is_none());
+    assert!(Vel::META.default_fn().is_some());
+}
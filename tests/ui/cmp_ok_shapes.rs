@@ -0,0 +1,27 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Unit;
+
+#[derive(Component)]
+struct Tuple(f32, &'static str);
+
+#[derive(Component)]
+struct Named
+{
+    name: &'static str,
+    list: Vec<&'static [u8]>,
+}
+
+#[derive(Component)]
+enum Enum
+{
+    A,
+    B(String),
+    C { n: Box<u64> },
+}
+
+fn main()
+{
+    let _ = (Unit::ID, Tuple::ID, Named::ID, Enum::ID);
+}
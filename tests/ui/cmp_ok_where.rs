@@ -0,0 +1,12 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Bounded(Vec<String>)
+where
+    Vec<String>: Clone + Send + Sync,
+    String: Into<Box<str>>;
+
+fn main()
+{
+    let _ = Bounded::ID;
+}
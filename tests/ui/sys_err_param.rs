@@ -0,0 +1,12 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Pos(f32);
+
+#[system]
+fn bad(pos: View<&Pos>, n: u32, scene: &mut Scene)
+{
+    let _ = (pos, n, scene);
+}
+
+fn main() { }
@@ -0,0 +1,15 @@
+use ezgame::*;
+
+#[system]
+fn generic<T>() { }
+
+#[system]
+async fn asynchronous() { }
+
+#[system]
+fn returns() -> u32 { 0 }
+
+#[system(parallel)]
+fn options() { }
+
+fn main() { }
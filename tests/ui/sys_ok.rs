@@ -0,0 +1,33 @@
+use ezgame::*;
+
+#[derive(Component)]
+struct Pos(f32);
+
+struct Time(f32);
+
+/// documented, public system
+#[system]
+pub fn movement(mut pos: View<&mut Pos>, time: Res<Time>)
+{
+    for pos in pos.iter()
+    {
+        pos.0 += time.0;
+    }
+}
+
+#[system]
+#[allow(unused_variables)]
+fn no_params() { }
+
+#[system]
+fn many(a: View<(Entity, Option<&Pos>)>, b: ResMut<Time>, c: Res<String>)
+{
+    let _ = (a, b, c);
+}
+
+fn main()
+{
+    let _: Box<dyn System> = Box::new(movement);
+    let _: Box<dyn System> = Box::new(no_params);
+    let _: Box<dyn System> = Box::new(many);
+}